@@ -0,0 +1,23 @@
+-- Users whose emails differ only in case have to be merged or renamed by hand
+-- first; the migration fails listing them rather than with an index error.
+DO $$
+DECLARE
+    conflicts TEXT;
+BEGIN
+    SELECT string_agg(format('%s (ids %s)', email, ids), '; ' ORDER BY email)
+    INTO conflicts
+    FROM (
+        SELECT LOWER(email) AS email, string_agg(id::TEXT, ', ' ORDER BY id) AS ids
+        FROM users
+        GROUP BY LOWER(email)
+        HAVING COUNT(*) > 1
+    ) duplicates;
+
+    IF conflicts IS NOT NULL THEN
+        RAISE EXCEPTION 'Cannot make emails unique, these differ only in case: %', conflicts
+            USING HINT = 'Merge or rename the duplicate users, then run the migration again.';
+    END IF;
+END
+$$;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_lower_key ON users (LOWER(email));
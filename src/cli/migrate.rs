@@ -0,0 +1,70 @@
+use crate::config::app_config::AppConfig;
+use crate::config::settings::DatabaseSettings;
+use crate::db::migrations::{self, MigrationStatus};
+
+const USAGE: &str = "Usage: migrate <up|down [steps]|status>";
+
+fn print_statuses(store: &str, statuses: &[MigrationStatus]) {
+    println!("{}:", store);
+    for status in statuses {
+        println!(
+            "  {:>4}  {:<40} {:?}{}",
+            status.version,
+            status.name,
+            status.state,
+            status.applied_at.as_ref().map(|at| format!(" ({})", at)).unwrap_or_default()
+        );
+    }
+}
+
+/// Entry point for `<binary> migrate ...`, run instead of starting the server.
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let command = args.first().map(String::as_str).ok_or(USAGE)?;
+    let settings = DatabaseSettings::load()?;
+    let app_config = AppConfig::connect(&settings).await?;
+
+    // Disabled backends are skipped.
+    match command {
+        "up" => {
+            #[cfg(feature = "postgres")]
+            if let Some(pool) = &app_config.postgres_pool {
+                let applied = migrations::postgres::migrate_up(pool).await?;
+                println!("Applied {} PostgreSQL migration(s)", applied);
+            }
+            #[cfg(feature = "mongo")]
+            if let Some(db) = &app_config.mongo_db {
+                let applied = migrations::mongo::migrate_up(db).await?;
+                println!("Applied {} MongoDB migration(s)", applied);
+            }
+        }
+        "down" => {
+            let steps = match args.get(1) {
+                Some(steps) => steps.parse::<usize>().map_err(|_| USAGE)?,
+                None => 1,
+            };
+            #[cfg(feature = "postgres")]
+            if let Some(pool) = &app_config.postgres_pool {
+                let reverted = migrations::postgres::migrate_down(pool, steps).await?;
+                println!("Reverted {} PostgreSQL migration(s)", reverted);
+            }
+            #[cfg(feature = "mongo")]
+            if let Some(db) = &app_config.mongo_db {
+                let reverted = migrations::mongo::migrate_down(db, steps).await?;
+                println!("Reverted {} MongoDB migration(s)", reverted);
+            }
+        }
+        "status" => {
+            #[cfg(feature = "postgres")]
+            if let Some(pool) = &app_config.postgres_pool {
+                print_statuses("PostgreSQL", &migrations::postgres::migration_status(pool).await?);
+            }
+            #[cfg(feature = "mongo")]
+            if let Some(db) = &app_config.mongo_db {
+                print_statuses("MongoDB", &migrations::mongo::migration_status(db).await?);
+            }
+        }
+        _ => return Err(USAGE.into()),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,2 @@
+pub mod migrate;
+pub mod roles;
\ No newline at end of file
@@ -0,0 +1,26 @@
+use crate::config::app_config::AppConfig;
+use crate::config::settings::DatabaseSettings;
+use crate::models::role::Role;
+use crate::utils::validation::normalize_email;
+
+const USAGE: &str = "Usage: roles <email> <role>[,<role>...]";
+
+/// Entry point for `<binary> roles ...`: sets a user's roles directly in the
+/// auth store, which is how the first admin gets created.
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(email), Some(roles)) = (args.first(), args.get(1)) else {
+        return Err(USAGE.into());
+    };
+    let roles = roles.split(',')
+        .map(|role| role.trim().parse::<Role>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let settings = DatabaseSettings::load()?;
+    let app_config = AppConfig::connect(&settings).await?;
+    let credentials = app_config.auth_store.find_credentials_by_email(&normalize_email(email)).await?
+        .ok_or_else(|| format!("No user with email {}", email))?;
+    app_config.auth_store.set_roles(&credentials.user_id, &roles).await?;
+
+    println!("User {} now has roles {:?}", credentials.user_id, roles);
+    Ok(())
+}
@@ -1,58 +1,231 @@
-use std::{thread, time::Duration};
-use dotenv::dotenv;
-use crate::db::postgres::create_postgres_client;
-use crate::db::mongo::mongo_connect;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::config::settings::DatabaseSettings;
+#[cfg(feature = "postgres")]
+use crate::db::postgres::{create_postgres_pool, PgPool, PostgresConfig};
+#[cfg(feature = "mongo")]
+use crate::db::mongo::{mongo_connect, mongo_database, MongoConfig};
+use crate::db::migrations::{self, PendingMigrations};
+use crate::errors::app_error::AppError;
+use crate::repositories::auth_repository::AuthStore;
+use crate::utils::retry::retry;
+#[cfg(feature = "mongo")]
 use mongodb::Database as MongoDatabase;
-use tokio_postgres::Client as PostgresClient;
 use log::{info, warn, error};
 
+#[cfg(feature = "postgres")]
+const POSTGRES: &str = "PostgreSQL";
+#[cfg(feature = "mongo")]
+const MONGO: &str = "MongoDB";
+
+/// The connected databases; a backend switched off with `POSTGRES_ENABLED` or
+/// `MONGO_ENABLED` is `None`, and its routes are not mounted.
 pub struct AppConfig {
-    pub postgres_client: PostgresClient,
-    pub mongo_db: MongoDatabase,
+    #[cfg(feature = "postgres")]
+    pub postgres_pool: Option<PgPool>,
+    #[cfg(feature = "mongo")]
+    pub mongo_db: Option<MongoDatabase>,
+    pub auth_store: AuthStore,
+    /// Backends that could not be reached at startup and connect on first
+    /// use instead, which `STARTUP_ALLOW_DEGRADED` permits.
+    pub unreachable: Vec<&'static str>,
+    /// Migrations of unreachable backends, applied once they are back.
+    pub pending_migrations: PendingMigrations,
 }
 
 impl AppConfig {
-    pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        info!("Initializing AppConfig");
-        dotenv().ok();
-
-        let mut retries = 5;
-        let mut postgres_client = None;
-
-        while retries > 0 {
-            info!("Attempting to connect to PostgreSQL database (Attempt {})", 6 - retries);
-            match create_postgres_client().await {
-                Ok(client) => {
-                    info!("Successfully connected to PostgreSQL database");
-                    postgres_client = Some(client);
-                    break;
+    /// Connects to the enabled databases and applies pending migrations unless
+    /// `RUN_MIGRATIONS` is set to `false`. Those of a backend that is still
+    /// unreachable are applied in the background once it is back.
+    pub async fn new(settings: &DatabaseSettings) -> Result<Self, Box<dyn std::error::Error>> {
+        let app_config = Self::connect(settings).await?;
+
+        if settings.run_migrations {
+            info!("Running database migrations");
+            let interval = settings.startup_retry.max_delay;
+            #[cfg(feature = "postgres")]
+            if let Some(pool) = &app_config.postgres_pool {
+                if app_config.unreachable.contains(&POSTGRES) {
+                    let pool = pool.clone();
+                    migrate_when_reachable(POSTGRES, "postgres", &app_config.pending_migrations, interval, move || {
+                        let pool = pool.clone();
+                        async move { migrations::postgres::migrate_up(&pool).await }
+                    });
+                } else {
+                    migrations::postgres::migrate_up(pool).await?;
                 }
-                Err(e) => {
-                    warn!("Failed to connect to PostgreSQL. Retrying in 5 seconds... (Attempts left: {})", retries);
-                    warn!("Error: {}", e);
-                    thread::sleep(Duration::from_secs(5));
-                    retries -= 1;
+            }
+            #[cfg(feature = "mongo")]
+            if let Some(db) = &app_config.mongo_db {
+                if app_config.unreachable.contains(&MONGO) {
+                    let db = db.clone();
+                    migrate_when_reachable(MONGO, "mongo", &app_config.pending_migrations, interval, move || {
+                        let db = db.clone();
+                        async move { migrations::mongo::migrate_up(&db).await }
+                    });
+                } else {
+                    migrations::mongo::migrate_up(db).await?;
                 }
             }
+        } else {
+            warn!("RUN_MIGRATIONS=false, skipping database migrations");
         }
 
-        let postgres_client = postgres_client.ok_or_else(|| {
-            error!("Failed to connect to PostgreSQL after multiple attempts");
-            "Failed to connect to PostgreSQL after multiple attempts"
-        })?;
+        info!("AppConfig initialization completed successfully");
+        Ok(app_config)
+    }
 
-        info!("Connecting to MongoDB");
-        let mongo_db = mongo_connect().await?;
-        info!("Successfully connected to MongoDB");
+    /// Connects to the enabled databases without touching their schema,
+    /// retrying each as `STARTUP_RETRY_*` allows.
+    pub async fn connect(settings: &DatabaseSettings) -> Result<Self, Box<dyn std::error::Error>> {
+        info!("Initializing AppConfig");
+        let mut unreachable = Vec::new();
 
-        info!("Creating users table if not exists");
-        postgres_client.execute("CREATE TABLE IF NOT EXISTS users (id SERIAL PRIMARY KEY, name TEXT, email TEXT)", &[]).await?;
-        info!("Users table created or already exists");
+        #[cfg(feature = "postgres")]
+        let postgres_pool = match &settings.postgres {
+            Some(config) => Some(connect_postgres(config, settings, &mut unreachable).await?),
+            None => {
+                info!("PostgreSQL is disabled");
+                None
+            }
+        };
+
+        #[cfg(feature = "mongo")]
+        let mongo_db = match &settings.mongo {
+            Some(config) => Some(connect_mongo(config, settings, &mut unreachable).await?),
+            None => {
+                info!("MongoDB is disabled");
+                None
+            }
+        };
+
+        // The settings only accept an enabled AUTH_BACKEND.
+        let auth_store: Option<AuthStore> = match settings.auth_backend {
+            #[cfg(feature = "postgres")]
+            AuthBackend::Postgres => postgres_pool.clone().map(|pool| Arc::new(pool) as AuthStore),
+            #[cfg(feature = "mongo")]
+            AuthBackend::Mongo => mongo_db.clone().map(|db| Arc::new(db) as AuthStore),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        };
+        let auth_store = auth_store.ok_or("The AUTH_BACKEND database is not enabled")?;
 
-        info!("AppConfig initialization completed successfully");
         Ok(AppConfig {
-            postgres_client,
+            #[cfg(feature = "postgres")]
+            postgres_pool,
+            #[cfg(feature = "mongo")]
             mongo_db,
+            auth_store,
+            unreachable,
+            pending_migrations: PendingMigrations::default(),
         })
     }
-}
\ No newline at end of file
+}
+
+/// Keeps trying to apply the migrations of a backend that was unreachable at
+/// startup, every `interval`, until they succeed. Until then `pending` holds
+/// its health check name, so readiness keeps traffic away from the instance
+/// rather than let it use an outdated schema.
+fn migrate_when_reachable<F, Fut>(
+    name: &'static str,
+    check: &'static str,
+    pending: &PendingMigrations,
+    interval: Duration,
+    migrate: F,
+) where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<usize, AppError>> + Send,
+{
+    warn!("{} is unreachable; its migrations will be applied once it is back", name);
+    pending.insert(check);
+    let pending = pending.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            match migrate().await {
+                Ok(count) => {
+                    info!("{} is back, applied {} pending migration(s)", name, count);
+                    pending.remove(check);
+                    return;
+                }
+                Err(e) => warn!("{} migrations are still pending: {}", name, e),
+            }
+        }
+    });
+}
+
+/// Reports a backend that stayed unreachable: fatal unless degraded startup
+/// is allowed, in which case it is recorded in `unreachable`.
+fn give_up(name: &'static str, error: String, settings: &DatabaseSettings, unreachable: &mut Vec<&'static str>) -> Result<(), String> {
+    if !settings.allow_degraded {
+        error!("{}", error);
+        return Err(error);
+    }
+    warn!("{}. Starting degraded: {} will be connected on first use", error, name);
+    unreachable.push(name);
+    Ok(())
+}
+
+#[cfg(feature = "postgres")]
+async fn connect_postgres(
+    config: &PostgresConfig,
+    settings: &DatabaseSettings,
+    unreachable: &mut Vec<&'static str>,
+) -> Result<PgPool, Box<dyn std::error::Error>> {
+    match retry(&settings.startup_retry, "Connecting to PostgreSQL", || create_postgres_pool(config)).await {
+        Ok(pool) => {
+            info!("Successfully connected to PostgreSQL database");
+            Ok(pool)
+        }
+        Err(e) => {
+            give_up(POSTGRES, e, settings, unreachable)?;
+            Ok(config.connect_lazily()?)
+        }
+    }
+}
+
+#[cfg(feature = "mongo")]
+async fn connect_mongo(
+    config: &MongoConfig,
+    settings: &DatabaseSettings,
+    unreachable: &mut Vec<&'static str>,
+) -> Result<MongoDatabase, Box<dyn std::error::Error>> {
+    match retry(&settings.startup_retry, "Connecting to MongoDB", || mongo_connect(config)).await {
+        Ok(db) => Ok(db),
+        Err(e) => {
+            give_up(MONGO, e, settings, unreachable)?;
+            Ok(mongo_database(config).await?)
+        }
+    }
+}
+
+/// The database holding credentials, set with `AUTH_BACKEND`. It defaults to
+/// the first enabled backend and must name an enabled one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthBackend {
+    Postgres,
+    Mongo,
+}
+
+impl AuthBackend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuthBackend::Postgres => "postgres",
+            AuthBackend::Mongo => "mongo",
+        }
+    }
+}
+
+impl FromStr for AuthBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "postgres" => Ok(AuthBackend::Postgres),
+            "mongo" => Ok(AuthBackend::Mongo),
+            other => Err(format!("AUTH_BACKEND must be `postgres` or `mongo`, got `{}`", other)),
+        }
+    }
+}
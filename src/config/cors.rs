@@ -1,20 +1,21 @@
 use rocket_cors::{AllowedHeaders, AllowedOrigins, CorsOptions};
-use std::env;
 
-pub fn cors_configuration() -> rocket_cors::Cors {
-    let allowed_origins = match env::var("ALLOWED_ORIGINS") {
-        Ok(origins) => {
-            let origins: Vec<&str> = origins.split(',').collect();
-            AllowedOrigins::some_exact(&origins)
-        }
-        Err(_) => AllowedOrigins::all(),
+use crate::config::settings::SettingsSource;
+
+/// Allows the origins listed in `ALLOWED_ORIGINS`, or any origin when unset.
+pub fn cors_configuration(settings: &SettingsSource) -> Result<rocket_cors::Cors, String> {
+    let origins = settings.list("ALLOWED_ORIGINS");
+    let allowed_origins = if origins.is_empty() {
+        AllowedOrigins::all()
+    } else {
+        AllowedOrigins::some_exact(&origins)
     };
 
-    let allowed_methods: rocket_cors::AllowedMethods = ["GET", "POST", "PUT", "DELETE"]
+    let allowed_methods = ["GET", "POST", "PUT", "PATCH", "DELETE"]
         .iter()
-        .map(|s| s.parse().unwrap())
-        .collect();
-    
+        .map(|s| s.parse().map_err(|_| format!("Invalid CORS method: {}", s)))
+        .collect::<Result<rocket_cors::AllowedMethods, String>>()?;
+
     CorsOptions {
         allowed_origins,
         allowed_methods,
@@ -22,10 +23,13 @@ pub fn cors_configuration() -> rocket_cors::Cors {
             "Authorization",
             "Accept",
             "Content-Type",
+            "If-Match",
+            "If-None-Match",
         ]),
+        expose_headers: ["ETag", "Location"].iter().map(|h| h.to_string()).collect(),
         allow_credentials: true,
         ..Default::default()
     }
         .to_cors()
-        .expect("Error while building CORS")
+        .map_err(|e| format!("Invalid ALLOWED_ORIGINS: {}", e))
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use crate::config::settings::SettingsSource;
+
+/// Readiness probing of the databases.
+///
+/// Each check gets `HEALTH_CHECK_TIMEOUT_MS`. Every enabled database is
+/// required unless listed in `HEALTH_OPTIONAL_DEPENDENCIES`; an optional one
+/// that is down is reported without failing readiness.
+pub struct HealthConfig {
+    pub timeout: Duration,
+    pub optional: Vec<String>,
+}
+
+impl HealthConfig {
+    pub fn from_settings(settings: &SettingsSource, auth_backend: &str) -> Result<Self, String> {
+        let config = HealthConfig {
+            timeout: Duration::from_millis(settings.parse("HEALTH_CHECK_TIMEOUT_MS", 2000)),
+            optional: settings.list("HEALTH_OPTIONAL_DEPENDENCIES"),
+        };
+
+        if config.timeout.is_zero() {
+            return Err("HEALTH_CHECK_TIMEOUT_MS must be at least 1".to_string());
+        }
+        if let Some(name) = config.optional.iter().find(|name| !matches!(name.as_str(), "postgres" | "mongo")) {
+            return Err(format!("HEALTH_OPTIONAL_DEPENDENCIES may list `postgres` and `mongo`, got `{}`", name));
+        }
+        if config.optional.iter().any(|name| name == auth_backend) {
+            return Err(format!("HEALTH_OPTIONAL_DEPENDENCIES cannot list `{}`, the AUTH_BACKEND", auth_backend));
+        }
+        Ok(config)
+    }
+
+    pub fn is_required(&self, dependency: &str) -> bool {
+        !self.optional.iter().any(|name| name == dependency)
+    }
+}
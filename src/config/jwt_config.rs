@@ -0,0 +1,166 @@
+use std::{fs, time::Duration};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, Jwk, JwkSet, KeyAlgorithm, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header};
+use log::info;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
+use sha2::{Digest, Sha256};
+
+use crate::config::settings::SettingsSource;
+
+/// Signing settings for access tokens.
+///
+/// `JWT_ALGORITHM` is `HS256` (shared `JWT_SECRET`) or `RS256` (PEM files at
+/// `JWT_PRIVATE_KEY_FILE` and `JWT_PUBLIC_KEY_FILE`).
+///
+/// To rotate RS256 keys, sign with the new pair and list the old public key
+/// in `JWT_PREVIOUS_PUBLIC_KEY_FILES` (comma separated) until the tokens it
+/// signed have expired. Every listed key is published in the JWKS.
+pub struct JwtConfig {
+    pub algorithm: Algorithm,
+    pub encoding_key: EncodingKey,
+    pub decoding_key: DecodingKey,
+    /// `kid` of the signing key. Only set for RS256.
+    pub key_id: Option<String>,
+    /// The current public key, then the previous ones. Empty for HS256.
+    pub verification_keys: Vec<VerificationKey>,
+    pub issuer: String,
+    pub audience: String,
+    pub access_token_ttl: Duration,
+    pub refresh_token_ttl: Duration,
+}
+
+/// A public key published in the JWKS and accepted on incoming tokens.
+pub struct VerificationKey {
+    pub kid: String,
+    pub decoding_key: DecodingKey,
+    pub jwk: Jwk,
+}
+
+fn required_for(settings: &SettingsSource, key: &str, algorithm: &str) -> Result<String, String> {
+    settings.get(key).ok_or_else(|| format!("{} must be set for {}", key, algorithm))
+}
+
+fn read_key_file(settings: &SettingsSource, key: &str) -> Result<Vec<u8>, String> {
+    let path = required_for(settings, key, "RS256")?;
+    fs::read(&path).map_err(|e| format!("Failed to read {} ({}): {}", key, path, e))
+}
+
+/// Loads an RSA public key (SPKI or PKCS#1 PEM). Its `kid` is the RFC 7638
+/// thumbprint, so it stays the same wherever the key is deployed.
+fn read_public_key(path: &str) -> Result<VerificationKey, String> {
+    let pem = fs::read_to_string(path).map_err(|e| format!("Failed to read public key {}: {}", path, e))?;
+    let key = RsaPublicKey::from_public_key_pem(&pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(&pem))
+        .map_err(|e| format!("Invalid RSA public key {}: {}", path, e))?;
+
+    let n = URL_SAFE_NO_PAD.encode(key.n().to_bytes_be());
+    let e = URL_SAFE_NO_PAD.encode(key.e().to_bytes_be());
+    let thumbprint = format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n);
+    let kid = URL_SAFE_NO_PAD.encode(Sha256::digest(thumbprint.as_bytes()));
+
+    Ok(VerificationKey {
+        decoding_key: DecodingKey::from_rsa_components(&n, &e)
+            .map_err(|e| format!("Invalid RSA public key {}: {}", path, e))?,
+        jwk: Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(KeyAlgorithm::RS256),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: AlgorithmParameters::RSA(RSAKeyParameters { key_type: RSAKeyType::RSA, n, e }),
+        },
+        kid,
+    })
+}
+
+impl JwtConfig {
+    pub fn from_settings(settings: &SettingsSource) -> Result<Self, String> {
+        // Read before the keys so their problems are reported along with any key error.
+        let issuer = settings.get_or("JWT_ISSUER", "user-service");
+        let audience = settings.get_or("JWT_AUDIENCE", "user-service");
+        let access_token_ttl = Duration::from_secs(settings.parse("JWT_ACCESS_TOKEN_TTL_SECS", 900));
+        let refresh_token_ttl = Duration::from_secs(settings.parse("REFRESH_TOKEN_TTL_SECS", 30 * 24 * 3600));
+
+        let mut verification_keys = Vec::new();
+        let (algorithm, encoding_key, decoding_key) = match settings.get_or("JWT_ALGORITHM", "HS256").as_str() {
+            "HS256" => {
+                let secret = required_for(settings, "JWT_SECRET", "HS256")?;
+                if secret.len() < 32 {
+                    return Err("JWT_SECRET must be at least 32 bytes".to_string());
+                }
+                (Algorithm::HS256, EncodingKey::from_secret(secret.as_bytes()), DecodingKey::from_secret(secret.as_bytes()))
+            }
+            "RS256" => {
+                let encoding_key = EncodingKey::from_rsa_pem(&read_key_file(settings, "JWT_PRIVATE_KEY_FILE")?)
+                    .map_err(|e| format!("Invalid JWT_PRIVATE_KEY_FILE: {}", e))?;
+                verification_keys.push(read_public_key(&required_for(settings, "JWT_PUBLIC_KEY_FILE", "RS256")?)?);
+                for path in settings.list("JWT_PREVIOUS_PUBLIC_KEY_FILES") {
+                    verification_keys.push(read_public_key(&path)?);
+                }
+                let decoding_key = verification_keys[0].decoding_key.clone();
+                (Algorithm::RS256, encoding_key, decoding_key)
+            }
+            other => return Err(format!("JWT_ALGORITHM must be HS256 or RS256, got {}", other)),
+        };
+
+        info!("Signing access tokens with {:?}", algorithm);
+        Ok(JwtConfig {
+            algorithm,
+            encoding_key,
+            decoding_key,
+            key_id: verification_keys.first().map(|key| key.kid.clone()),
+            verification_keys,
+            issuer,
+            audience,
+            access_token_ttl,
+            refresh_token_ttl,
+        })
+    }
+
+    /// An HS256 configuration for unit tests, which load no settings.
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        let secret = b"test-secret-for-unit-tests-0123456789";
+        JwtConfig {
+            algorithm: Algorithm::HS256,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            key_id: None,
+            verification_keys: Vec::new(),
+            issuer: "user-service".to_string(),
+            audience: "user-service".to_string(),
+            access_token_ttl: Duration::from_secs(900),
+            refresh_token_ttl: Duration::from_secs(3600),
+        }
+    }
+
+    /// Header for tokens signed with the current key.
+    pub fn header(&self) -> Header {
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.key_id.clone();
+        header
+    }
+
+    /// Key to check a token whose header names `kid`. Tokens without one
+    /// are checked against the current key.
+    pub fn decoding_key_for(&self, kid: Option<&str>) -> Option<&DecodingKey> {
+        match kid {
+            Some(kid) if !self.verification_keys.is_empty() => {
+                self.verification_keys.iter().find(|key| key.kid == kid).map(|key| &key.decoding_key)
+            }
+            _ => Some(&self.decoding_key),
+        }
+    }
+
+    /// The public keys as a JWKS document.
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet { keys: self.verification_keys.iter().map(|key| key.jwk.clone()).collect() }
+    }
+}
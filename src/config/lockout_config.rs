@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use crate::config::settings::SettingsSource;
+
+/// Brute-force protection for password checks.
+///
+/// Failed logins are counted per account and per client address within
+/// `LOGIN_FAILURE_WINDOW_SECS`. Past a threshold the account or address is
+/// locked for `LOGIN_LOCKOUT_BASE_SECS`, doubling with every further failure
+/// up to `LOGIN_LOCKOUT_MAX_SECS`.
+pub struct LockoutConfig {
+    pub account_max_failures: u32,
+    pub ip_max_failures: u32,
+    pub failure_window: Duration,
+    pub base_lockout: Duration,
+    pub max_lockout: Duration,
+}
+
+impl LockoutConfig {
+    pub fn from_settings(settings: &SettingsSource) -> Result<Self, String> {
+        let config = LockoutConfig {
+            account_max_failures: settings.parse("LOGIN_ACCOUNT_MAX_FAILURES", 5),
+            ip_max_failures: settings.parse("LOGIN_IP_MAX_FAILURES", 20),
+            failure_window: Duration::from_secs(settings.parse("LOGIN_FAILURE_WINDOW_SECS", 15 * 60)),
+            base_lockout: Duration::from_secs(settings.parse("LOGIN_LOCKOUT_BASE_SECS", 60)),
+            max_lockout: Duration::from_secs(settings.parse("LOGIN_LOCKOUT_MAX_SECS", 3600)),
+        };
+
+        if config.account_max_failures == 0 || config.ip_max_failures == 0 {
+            return Err("LOGIN_ACCOUNT_MAX_FAILURES and LOGIN_IP_MAX_FAILURES must be at least 1".to_string());
+        }
+        if config.base_lockout.is_zero() || config.base_lockout > config.max_lockout {
+            return Err("LOGIN_LOCKOUT_BASE_SECS must be between 1 and LOGIN_LOCKOUT_MAX_SECS".to_string());
+        }
+        Ok(config)
+    }
+
+    /// How long to lock after `failures` failed logins against a threshold of `max_failures`.
+    pub fn lockout_for(&self, failures: u32, max_failures: u32) -> Option<Duration> {
+        let excess = failures.checked_sub(max_failures)?;
+        let factor = 1u32.checked_shl(excess).unwrap_or(u32::MAX);
+        Some(self.base_lockout.saturating_mul(factor).min(self.max_lockout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> LockoutConfig {
+        LockoutConfig {
+            account_max_failures: 3,
+            ip_max_failures: 10,
+            failure_window: Duration::from_secs(900),
+            base_lockout: Duration::from_secs(60),
+            max_lockout: Duration::from_secs(600),
+        }
+    }
+
+    #[test]
+    fn doubles_the_lockout_past_the_threshold_up_to_the_cap() {
+        let config = config();
+        assert_eq!(config.lockout_for(2, 3), None);
+        assert_eq!(config.lockout_for(3, 3), Some(Duration::from_secs(60)));
+        assert_eq!(config.lockout_for(4, 3), Some(Duration::from_secs(120)));
+        assert_eq!(config.lockout_for(6, 3), Some(Duration::from_secs(480)));
+        assert_eq!(config.lockout_for(7, 3), Some(Duration::from_secs(600)));
+        assert_eq!(config.lockout_for(u32::MAX, 3), Some(Duration::from_secs(600)));
+    }
+
+    #[test]
+    fn rejects_unusable_settings() {
+        assert!(LockoutConfig::from_settings(&SettingsSource::for_tests(&[])).is_ok());
+        for (key, value) in [
+            ("LOGIN_ACCOUNT_MAX_FAILURES", "0"),
+            ("LOGIN_IP_MAX_FAILURES", "0"),
+            ("LOGIN_LOCKOUT_BASE_SECS", "0"),
+            ("LOGIN_LOCKOUT_BASE_SECS", "7200"),
+        ] {
+            assert!(LockoutConfig::from_settings(&SettingsSource::for_tests(&[(key, value)])).is_err(), "{}={}", key, value);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::config::settings::SettingsSource;
+
+#[derive(Clone)]
+pub enum MailTransport {
+    Smtp {
+        host: String,
+        /// Defaults to the standard port of `security`.
+        port: Option<u16>,
+        username: Option<String>,
+        password: Option<String>,
+        /// `tls` (implicit TLS), `starttls` or `none` for local relays.
+        security: String,
+    },
+    /// Appends messages to a file, or prints them when no path is given.
+    File(Option<PathBuf>),
+}
+
+/// Outgoing mail settings.
+///
+/// `MAIL_TRANSPORT` is `smtp`, `file` (`MAIL_FILE`) or `stdout` (the default,
+/// for local development). Links in emails point at `PUBLIC_BASE_URL`.
+#[derive(Clone)]
+pub struct MailConfig {
+    pub transport: MailTransport,
+    pub from: String,
+    pub public_base_url: String,
+    pub verification_token_ttl: Duration,
+    pub password_reset_token_ttl: Duration,
+    /// Reset emails sent to one address per hour; further requests are dropped.
+    pub password_reset_hourly_limit: u64,
+}
+
+impl MailConfig {
+    pub fn from_settings(settings: &SettingsSource) -> Result<Self, String> {
+        let transport = match settings.get_or("MAIL_TRANSPORT", "stdout").as_str() {
+            "smtp" => MailTransport::Smtp {
+                host: settings.require("SMTP_HOST", " for MAIL_TRANSPORT=smtp"),
+                port: settings.parse_optional("SMTP_PORT"),
+                username: settings.get("SMTP_USERNAME"),
+                password: settings.get("SMTP_PASSWORD"),
+                security: settings.get_or("SMTP_SECURITY", "starttls"),
+            },
+            "file" => MailTransport::File(Some(PathBuf::from(settings.require("MAIL_FILE", " for MAIL_TRANSPORT=file")))),
+            "stdout" => MailTransport::File(None),
+            other => return Err(format!("MAIL_TRANSPORT must be smtp, file or stdout, got {}", other)),
+        };
+
+        Ok(MailConfig {
+            transport,
+            from: settings.get_or("MAIL_FROM", "User Service <no-reply@localhost>"),
+            public_base_url: settings.get_or("PUBLIC_BASE_URL", "http://localhost:8000")
+                .trim_end_matches('/')
+                .to_string(),
+            verification_token_ttl: Duration::from_secs(settings.parse("EMAIL_VERIFICATION_TTL_SECS", 24 * 3600)),
+            password_reset_token_ttl: Duration::from_secs(settings.parse("PASSWORD_RESET_TTL_SECS", 3600)),
+            password_reset_hourly_limit: settings.parse("PASSWORD_RESET_HOURLY_LIMIT", 3),
+        })
+    }
+}
@@ -1,2 +1,9 @@
+pub mod settings;
 pub mod cors;
-pub mod app_config;
\ No newline at end of file
+pub mod app_config;
+pub mod jwt_config;
+pub mod mail_config;
+pub mod lockout_config;
+pub mod oidc_config;
+pub mod oauth_provider_config;
+pub mod health_config;
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use crate::config::settings::SettingsSource;
+
+/// Settings for acting as an OAuth 2.0 / OpenID Connect provider to other services.
+///
+/// Relying parties verify ID tokens with the published keys, so this needs
+/// `JWT_ALGORITHM=RS256`. `OAUTH_ISSUER` defaults to `PUBLIC_BASE_URL`, where
+/// the discovery document is served. Clients send users to
+/// `OAUTH_AUTHORIZATION_PAGE_URL`, a frontend page that calls
+/// `POST /oauth/authorize` with the signed-in user's access token.
+pub struct OAuthProviderConfig {
+    pub issuer: String,
+    /// Base URL the token, userinfo and JWKS endpoints are advertised under.
+    pub public_base_url: String,
+    pub authorization_page_url: String,
+    pub authorization_code_ttl: Duration,
+}
+
+impl OAuthProviderConfig {
+    pub fn from_settings(settings: &SettingsSource, public_base_url: &str) -> Self {
+        OAuthProviderConfig {
+            issuer: settings.get_or("OAUTH_ISSUER", public_base_url),
+            public_base_url: public_base_url.to_string(),
+            authorization_page_url: settings.get_or("OAUTH_AUTHORIZATION_PAGE_URL", &format!("{}/authorize", public_base_url)),
+            authorization_code_ttl: Duration::from_secs(settings.parse("OAUTH_AUTHORIZATION_CODE_TTL_SECS", 300)),
+        }
+    }
+
+    pub fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.public_base_url, path)
+    }
+}
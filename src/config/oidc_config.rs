@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use crate::config::settings::SettingsSource;
+
+/// An external OpenID Connect provider users can sign in with.
+pub struct OidcProviderConfig {
+    /// Name used in the login URLs, e.g. `google` in `/auth/oidc/google/authorize`.
+    pub name: String,
+    /// Issuer URL; the discovery document is read from `<issuer>/.well-known/openid-configuration`.
+    pub issuer: String,
+    pub client_id: String,
+    /// Unset for public clients, which rely on PKCE alone.
+    pub client_secret: Option<String>,
+    pub scopes: String,
+    pub redirect_uri: String,
+}
+
+/// Sign-in with external OpenID Connect providers.
+///
+/// `OIDC_PROVIDERS` lists provider names (none by default). Each one is set up
+/// with `OIDC_<NAME>_ISSUER`, `OIDC_<NAME>_CLIENT_ID`, the optional
+/// `OIDC_<NAME>_CLIENT_SECRET` and `OIDC_<NAME>_SCOPES`. Providers redirect
+/// back to `<PUBLIC_BASE_URL>/auth/oidc/<name>/callback`.
+pub struct OidcConfig {
+    pub providers: Vec<OidcProviderConfig>,
+    /// How long a started login may take before its state is rejected.
+    pub login_ttl: Duration,
+}
+
+impl OidcConfig {
+    pub fn from_settings(settings: &SettingsSource, public_base_url: &str) -> Result<Self, String> {
+        let providers = settings.list("OIDC_PROVIDERS").into_iter()
+            .map(|name| OidcProviderConfig::from_settings(settings, name.to_lowercase(), public_base_url))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(OidcConfig {
+            providers,
+            login_ttl: Duration::from_secs(settings.parse("OIDC_LOGIN_TTL_SECS", 600)),
+        })
+    }
+
+    pub fn provider(&self, name: &str) -> Option<&OidcProviderConfig> {
+        self.providers.iter().find(|provider| provider.name == name)
+    }
+}
+
+impl OidcProviderConfig {
+    fn from_settings(settings: &SettingsSource, name: String, public_base_url: &str) -> Result<Self, String> {
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("OIDC provider names may only contain letters, digits and '-', got {}", name));
+        }
+        let prefix = format!("OIDC_{}_", name.to_uppercase().replace('-', "_"));
+        let context = format!(" for OIDC provider {}", name);
+
+        Ok(OidcProviderConfig {
+            issuer: settings.require(&format!("{}ISSUER", prefix), &context),
+            client_id: settings.require(&format!("{}CLIENT_ID", prefix), &context),
+            client_secret: settings.get(&format!("{}CLIENT_SECRET", prefix)),
+            scopes: settings.get_or(&format!("{}SCOPES", prefix), "openid email profile"),
+            redirect_uri: format!("{}/auth/oidc/{}/callback", public_base_url, name),
+            name,
+        })
+    }
+}
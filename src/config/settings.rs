@@ -0,0 +1,466 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::{env, fmt, str::FromStr};
+
+use dotenv::dotenv;
+use log::warn;
+use rocket::figment::providers::{Format, Toml};
+use rocket::figment::value::{Dict, Value};
+use rocket::figment::Figment;
+use rocket_cors::Cors;
+
+use crate::config::app_config::AuthBackend;
+use crate::config::cors::cors_configuration;
+use crate::config::health_config::HealthConfig;
+use crate::config::jwt_config::JwtConfig;
+use crate::config::lockout_config::LockoutConfig;
+use crate::config::mail_config::MailConfig;
+use crate::config::oauth_provider_config::OAuthProviderConfig;
+use crate::config::oidc_config::OidcConfig;
+use crate::utils::retry::RetryPolicy;
+#[cfg(feature = "mongo")]
+use crate::db::mongo::MongoConfig;
+#[cfg(feature = "postgres")]
+use crate::db::postgres::PostgresConfig;
+
+/// Where a setting's value came from, for the startup report.
+#[derive(Clone)]
+enum Origin {
+    Environment,
+    File(String),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Environment => write!(f, "environment"),
+            Origin::File(path) => write!(f, "{}", path),
+        }
+    }
+}
+
+/// Raw settings, layered from lowest to highest precedence: the defaults each
+/// config passes in, `<CONFIG_DIR>/default.toml`, `<CONFIG_DIR>/<APP_ENV>.toml`
+/// and environment variables (a `.env` file included).
+///
+/// Every setting has one name, the environment variable. In TOML files tables
+/// are joined to it with `_`, so `[jwt] algorithm = "RS256"` sets
+/// `JWT_ALGORITHM`, and arrays stand for comma separated lists.
+///
+/// Lookups never fail: problems are recorded and a default is returned, so a
+/// single pass reports every invalid or missing setting at once.
+pub struct SettingsSource {
+    environment: BTreeMap<String, String>,
+    file_values: BTreeMap<String, (String, Origin)>,
+    read_keys: RefCell<BTreeSet<String>>,
+    errors: RefCell<Vec<String>>,
+}
+
+/// Flattens a TOML table into `(KEY, value)` pairs, `prefix` being the key of
+/// the enclosing table.
+fn flatten(prefix: &str, dict: &Dict, values: &mut Vec<(String, Result<String, String>)>) {
+    for (name, value) in dict {
+        let key = if prefix.is_empty() { name.to_uppercase() } else { format!("{}_{}", prefix, name.to_uppercase()) };
+        match value {
+            Value::Dict(_, table) => flatten(&key, table, values),
+            Value::Array(_, items) => {
+                let items: Result<Vec<String>, String> = items.iter().map(scalar).collect();
+                values.push((key, items.map(|items| items.join(","))));
+            }
+            other => values.push((key, scalar(other))),
+        }
+    }
+}
+
+fn scalar(value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(_, value) => Ok(value.clone()),
+        Value::Char(_, value) => Ok(value.to_string()),
+        Value::Bool(_, value) => Ok(value.to_string()),
+        Value::Num(_, num) => num.to_i128().map(|n| n.to_string())
+            .or_else(|| num.to_f64().map(|n| n.to_string()))
+            .ok_or_else(|| "unsupported number".to_string()),
+        _ => Err("nested values are only allowed in tables".to_string()),
+    }
+}
+
+impl SettingsSource {
+    /// Reads the `.env` file and the TOML files for the current environment.
+    pub fn load() -> Self {
+        dotenv().ok();
+        let environment = env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+            .collect();
+        Self::with_environment(environment)
+    }
+
+    /// Reads the TOML files under `environment`, which stands for the
+    /// process environment.
+    fn with_environment(environment: BTreeMap<String, String>) -> Self {
+        let source = SettingsSource {
+            environment,
+            file_values: BTreeMap::new(),
+            read_keys: RefCell::new(BTreeSet::new()),
+            errors: RefCell::new(Vec::new()),
+        };
+        let directory = source.get_or("CONFIG_DIR", "config");
+        let environment = source.get_or("APP_ENV", "development");
+
+        let mut file_values = BTreeMap::new();
+        for name in ["default", environment.as_str()] {
+            let path = format!("{}/{}.toml", directory.trim_end_matches('/'), name);
+            // A missing file is an empty one; only unreadable files are errors.
+            let dict = match Figment::from(Toml::file(&path)).extract::<Dict>() {
+                Ok(dict) => dict,
+                Err(e) => {
+                    source.error(format!("{}: {}", path, e));
+                    continue;
+                }
+            };
+            let mut values = Vec::new();
+            flatten("", &dict, &mut values);
+            for (key, value) in values {
+                match value {
+                    Ok(value) => { file_values.insert(key, (value, Origin::File(path.clone()))); }
+                    Err(e) => source.error(format!("{} ({}): {}", key, path, e)),
+                }
+            }
+        }
+
+        SettingsSource { file_values, ..source }
+    }
+
+    /// Settings for unit tests: `environment` alone, without any config files.
+    #[cfg(test)]
+    pub fn for_tests(environment: &[(&str, &str)]) -> Self {
+        let mut environment: BTreeMap<String, String> = environment.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        environment.insert("CONFIG_DIR".to_string(), "/nonexistent".to_string());
+        Self::with_environment(environment)
+    }
+
+    fn lookup(&self, key: &str) -> Option<(String, Origin)> {
+        self.read_keys.borrow_mut().insert(key.to_string());
+        match self.environment.get(key) {
+            Some(value) => Some((value.clone(), Origin::Environment)),
+            None => self.file_values.get(key).cloned(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.lookup(key).map(|(value, _)| value)
+    }
+
+    pub fn get_or(&self, key: &str, default: &str) -> String {
+        self.get(key).unwrap_or_else(|| default.to_string())
+    }
+
+    /// A setting that must be present; `context` says when, e.g. ` for RS256`.
+    pub fn require(&self, key: &str, context: &str) -> String {
+        self.get(key).unwrap_or_else(|| {
+            self.error(format!("{} must be set{}", key, context));
+            String::new()
+        })
+    }
+
+    pub fn parse<T: FromStr>(&self, key: &str, default: T) -> T {
+        self.parse_optional(key).unwrap_or(default)
+    }
+
+    pub fn parse_optional<T: FromStr>(&self, key: &str) -> Option<T> {
+        let (value, origin) = self.lookup(key)?;
+        match value.trim().parse::<T>() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                self.error(format!("Invalid value for {} ({}): {}", key, origin, value));
+                None
+            }
+        }
+    }
+
+    /// A comma separated list, empty when unset.
+    pub fn list(&self, key: &str) -> Vec<String> {
+        self.get(key).unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn error(&self, message: String) {
+        self.errors.borrow_mut().push(message);
+    }
+
+    /// Records a config that could not be built at all.
+    pub fn check<T>(&self, result: Result<T, String>) -> Option<T> {
+        result.map_err(|e| self.error(e)).ok()
+    }
+
+    /// Warns about file keys no config asked for. They are not errors: they
+    /// may be typos, or belong to a mode that is not enabled (`SMTP_HOST` with
+    /// `MAIL_TRANSPORT=stdout`).
+    fn warn_unused(&self) {
+        let read_keys = self.read_keys.borrow();
+        for (key, (_, origin)) in &self.file_values {
+            if !read_keys.contains(key) {
+                warn!("Ignoring setting {} ({}), which is unknown or unused", key, origin);
+            }
+        }
+    }
+
+    /// Ends loading, failing with every problem found.
+    fn finish(self) -> Result<(), SettingsError> {
+        let errors = self.errors.into_inner();
+        if errors.is_empty() { Ok(()) } else { Err(SettingsError(errors)) }
+    }
+}
+
+/// Every problem found while loading the settings.
+pub struct SettingsError(pub Vec<String>);
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} invalid setting(s):", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// The databases, which is all the command line tools need.
+pub struct DatabaseSettings {
+    /// `None` when switched off with `POSTGRES_ENABLED=false`.
+    #[cfg(feature = "postgres")]
+    pub postgres: Option<PostgresConfig>,
+    /// `None` when switched off with `MONGO_ENABLED=false`.
+    #[cfg(feature = "mongo")]
+    pub mongo: Option<MongoConfig>,
+    pub auth_backend: AuthBackend,
+    /// Whether to apply pending migrations before serving.
+    pub run_migrations: bool,
+    /// How long to keep trying to reach each database at startup.
+    pub startup_retry: RetryPolicy,
+    /// Whether to start anyway, connecting lazily, when a database stays
+    /// unreachable.
+    pub allow_degraded: bool,
+}
+
+/// Whether a backend is compiled in and not switched off with `key`. It is on
+/// by default when compiled in; asking for one that is not is an error.
+fn backend_enabled(source: &SettingsSource, key: &str, feature: &str, compiled: bool) -> bool {
+    let enabled = source.parse(key, compiled);
+    if enabled && !compiled {
+        source.error(format!("{} is true, but this build lacks the `{}` feature", key, feature));
+    }
+    enabled && compiled
+}
+
+impl DatabaseSettings {
+    fn from_settings(source: &SettingsSource) -> Self {
+        let postgres_enabled = backend_enabled(source, "POSTGRES_ENABLED", "postgres", cfg!(feature = "postgres"));
+        let mongo_enabled = backend_enabled(source, "MONGO_ENABLED", "mongo", cfg!(feature = "mongo"));
+        if !postgres_enabled && !mongo_enabled {
+            source.error("At least one of POSTGRES_ENABLED and MONGO_ENABLED must be true".to_string());
+        }
+
+        let default_backend = if postgres_enabled { AuthBackend::Postgres } else { AuthBackend::Mongo };
+        let auth_backend = source.parse("AUTH_BACKEND", default_backend);
+        let auth_backend_enabled = match auth_backend {
+            AuthBackend::Postgres => postgres_enabled,
+            AuthBackend::Mongo => mongo_enabled,
+        };
+        if !auth_backend_enabled && (postgres_enabled || mongo_enabled) {
+            source.error("AUTH_BACKEND must name an enabled database".to_string());
+        }
+
+        DatabaseSettings {
+            #[cfg(feature = "postgres")]
+            postgres: postgres_enabled.then(|| PostgresConfig::from_settings(source)),
+            #[cfg(feature = "mongo")]
+            mongo: mongo_enabled.then(|| MongoConfig::from_settings(source)),
+            auth_backend,
+            run_migrations: source.parse("RUN_MIGRATIONS", true),
+            startup_retry: source.check(RetryPolicy::from_settings(source, "STARTUP_RETRY")).unwrap_or_default(),
+            allow_degraded: source.parse("STARTUP_ALLOW_DEGRADED", false),
+        }
+    }
+
+    /// Loads only the database settings, so tools like `migrate` do not need
+    /// the server's secrets.
+    pub fn load() -> Result<Self, SettingsError> {
+        let source = SettingsSource::load();
+        let database = Self::from_settings(&source);
+        source.finish()?;
+        Ok(database)
+    }
+}
+
+/// Everything the service is configured with, loaded and validated once at
+/// startup; see `SettingsSource` for where values come from.
+pub struct Settings {
+    pub environment: String,
+    pub port: u16,
+    /// Header a trusted reverse proxy puts the client address in, such as
+    /// `X-Real-IP`. Without it the peer address is used: anyone can send the
+    /// header, so trusting it unproxied would let clients dodge IP lockouts.
+    pub client_ip_header: Option<String>,
+    pub database: DatabaseSettings,
+    pub cors: Cors,
+    pub jwt: JwtConfig,
+    pub mail: MailConfig,
+    pub lockout: LockoutConfig,
+    pub oidc: OidcConfig,
+    pub oauth_provider: OAuthProviderConfig,
+    pub health: HealthConfig,
+}
+
+impl Settings {
+    pub fn load() -> Result<Self, SettingsError> {
+        Self::from_source(SettingsSource::load())
+    }
+
+    fn from_source(source: SettingsSource) -> Result<Self, SettingsError> {
+        let environment = source.get_or("APP_ENV", "development");
+        let port = source.parse("PORT", 8000);
+        let client_ip_header = source.get("CLIENT_IP_HEADER");
+        let database = DatabaseSettings::from_settings(&source);
+        let cors = source.check(cors_configuration(&source));
+        let jwt = source.check(JwtConfig::from_settings(&source));
+        let mail = source.check(MailConfig::from_settings(&source));
+        let lockout = source.check(LockoutConfig::from_settings(&source));
+        let public_base_url = mail.as_ref().map(|mail| mail.public_base_url.clone()).unwrap_or_default();
+        let oidc = source.check(OidcConfig::from_settings(&source, &public_base_url));
+        let oauth_provider = OAuthProviderConfig::from_settings(&source, &public_base_url);
+        let health = source.check(HealthConfig::from_settings(&source, database.auth_backend.as_str()));
+
+        source.warn_unused();
+        source.finish()?;
+        let (Some(cors), Some(jwt), Some(mail), Some(lockout), Some(oidc), Some(health)) = (cors, jwt, mail, lockout, oidc, health) else {
+            unreachable!("configs that failed to load were reported");
+        };
+
+        Ok(Settings {
+            environment,
+            port,
+            client_ip_header,
+            database,
+            cors,
+            jwt,
+            mail,
+            lockout,
+            oidc,
+            oauth_provider,
+            health,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    /// A config directory holding `files`, named after the test using it.
+    fn config_dir(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory = env::temp_dir().join(format!("settings-{}-{}", std::process::id(), test));
+        fs::create_dir_all(&directory).unwrap();
+        for (name, contents) in files {
+            fs::write(directory.join(format!("{}.toml", name)), contents).unwrap();
+        }
+        directory
+    }
+
+    fn source(directory: &Path, environment: &[(&str, &str)]) -> SettingsSource {
+        let mut environment: BTreeMap<String, String> = environment.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        environment.insert("CONFIG_DIR".to_string(), directory.display().to_string());
+        SettingsSource::with_environment(environment)
+    }
+
+    /// What a minimal deployment sets in its environment.
+    const REQUIRED: &[(&str, &str)] = &[
+        ("JWT_SECRET", "a-secret-long-enough-for-hs256-0123456789"),
+        ("DATABASE_URL", "postgres://user@localhost/db"),
+        ("MONGODB_URI", "mongodb://localhost:27017"),
+    ];
+
+    fn rejected(result: Result<Settings, SettingsError>) -> Vec<String> {
+        match result {
+            Ok(_) => panic!("the settings were accepted"),
+            Err(SettingsError(errors)) => errors,
+        }
+    }
+
+    #[test]
+    fn layers_files_under_the_environment() {
+        let directory = config_dir("layers", &[
+            ("default", "port = 8000\n[login]\naccount_max_failures = 5\n[health]\noptional_dependencies = [\"mongo\", \"smtp\"]\n"),
+            ("staging", "port = 9000\n[login]\naccount_max_failures = 7\n"),
+        ]);
+        let source = source(&directory, &[("APP_ENV", "staging"), ("PORT", "9100")]);
+
+        assert_eq!(source.parse("PORT", 0), 9100);
+        assert_eq!(source.parse("LOGIN_ACCOUNT_MAX_FAILURES", 0), 7);
+        assert_eq!(source.list("HEALTH_OPTIONAL_DEPENDENCIES"), vec!["mongo", "smtp"]);
+        assert_eq!(source.get_or("MAIL_FROM", "fallback"), "fallback");
+        assert!(source.finish().is_ok());
+    }
+
+    #[test]
+    fn accepts_a_minimal_configuration() {
+        let directory = config_dir("minimal", &[]);
+        let settings = Settings::from_source(source(&directory, REQUIRED)).unwrap();
+        assert_eq!(settings.environment, "development");
+        assert_eq!(settings.port, 8000);
+        assert!(settings.client_ip_header.is_none());
+    }
+
+    #[test]
+    fn reports_every_problem_at_once() {
+        let directory = config_dir("problems", &[("default", "[login]\naccount_max_failures = 0\n")]);
+        let mut environment = vec![("PORT", "eighty"), ("MAIL_TRANSPORT", "smtp")];
+        environment.extend(REQUIRED.iter().filter(|(key, _)| *key != "JWT_SECRET"));
+
+        let errors = rejected(Settings::from_source(source(&directory, &environment)));
+        for expected in ["PORT", "JWT_SECRET", "SMTP_HOST", "LOGIN_ACCOUNT_MAX_FAILURES"] {
+            assert!(errors.iter().any(|error| error.contains(expected)), "no error about {} in {:?}", expected, errors);
+        }
+    }
+
+    #[test]
+    fn reports_unreadable_files() {
+        let directory = config_dir("unreadable", &[("default", "port = \n")]);
+        let errors = rejected(Settings::from_source(source(&directory, REQUIRED)));
+        assert!(errors.iter().any(|error| error.contains("default.toml")), "{:?}", errors);
+    }
+
+    #[test]
+    fn requires_an_enabled_auth_backend() {
+        let directory = config_dir("backends", &[]);
+        let mut environment = vec![("POSTGRES_ENABLED", "false"), ("MONGO_ENABLED", "false")];
+        environment.extend(REQUIRED);
+        let errors = rejected(Settings::from_source(source(&directory, &environment)));
+        assert!(errors.iter().any(|error| error.contains("At least one of POSTGRES_ENABLED and MONGO_ENABLED")), "{:?}", errors);
+
+        if cfg!(all(feature = "postgres", feature = "mongo")) {
+            let mut environment = vec![("MONGO_ENABLED", "false"), ("AUTH_BACKEND", "mongo")];
+            environment.extend(REQUIRED);
+            let errors = rejected(Settings::from_source(source(&directory, &environment)));
+            assert_eq!(errors, vec!["AUTH_BACKEND must name an enabled database".to_string()]);
+        }
+    }
+}
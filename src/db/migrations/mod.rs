@@ -0,0 +1,48 @@
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "mongo")]
+pub mod mongo;
+
+/// State of a single migration as reported by `migrate status`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the embedded script no longer matches the recorded checksum.
+    /// Only SQL migrations carry a checksum.
+    #[cfg(feature = "postgres")]
+    ChecksumMismatch,
+    /// Recorded as applied, but unknown to this build.
+    Unknown,
+}
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    pub state: MigrationState,
+    pub applied_at: Option<String>,
+}
+
+/// Backends, by health check name, whose migrations are waiting for them to
+/// become reachable. Shared between the task applying them and readiness,
+/// which reports these backends down meanwhile.
+#[derive(Clone, Default)]
+pub struct PendingMigrations(Arc<Mutex<BTreeSet<&'static str>>>);
+
+impl PendingMigrations {
+    pub fn insert(&self, name: &'static str) {
+        self.0.lock().unwrap().insert(name);
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.0.lock().unwrap().remove(name);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.lock().unwrap().contains(name)
+    }
+}
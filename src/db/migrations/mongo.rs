@@ -0,0 +1,481 @@
+use mongodb::{
+    Collection, Database, IndexModel,
+    bson::{doc, Bson, DateTime, Document},
+    error::Error as MongoError,
+    options::{Collation, CollationStrength, CreateCollectionOptions, IndexOptions, ValidationLevel},
+};
+use rocket::futures::{future::BoxFuture, TryStreamExt};
+use log::{info, warn};
+
+use crate::db::migrations::{MigrationState, MigrationStatus};
+use crate::errors::app_error::AppError;
+
+type MigrationFn = for<'a> fn(&'a Database) -> BoxFuture<'a, Result<(), AppError>>;
+
+/// Mongo counterpart of the SQL migrations: ordered, versioned steps recorded
+/// in the `schema_migrations` collection.
+pub struct MongoMigration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: MigrationFn,
+    pub down: MigrationFn,
+}
+
+pub const MIGRATIONS: &[MongoMigration] = &[
+    MongoMigration {
+        version: 1,
+        name: "0001_create_users_collection",
+        up: create_users_collection,
+        down: drop_users_validation,
+    },
+    MongoMigration {
+        version: 2,
+        name: "0002_drop_stray_user_id",
+        up: drop_stray_user_id,
+        down: keep_user_ids,
+    },
+    MongoMigration {
+        version: 3,
+        name: "0003_unique_user_email",
+        up: create_unique_email_index,
+        down: drop_unique_email_index,
+    },
+    MongoMigration {
+        version: 4,
+        name: "0004_add_user_version",
+        up: add_user_version,
+        down: remove_user_version,
+    },
+    MongoMigration {
+        version: 5,
+        name: "0005_create_refresh_tokens",
+        up: create_refresh_token_indexes,
+        down: drop_refresh_tokens,
+    },
+    MongoMigration {
+        version: 6,
+        name: "0006_add_user_roles",
+        up: add_user_roles,
+        down: remove_user_roles,
+    },
+    MongoMigration {
+        version: 7,
+        name: "0007_create_api_keys",
+        up: create_api_key_index,
+        down: drop_api_keys,
+    },
+    MongoMigration {
+        version: 8,
+        name: "0008_add_user_email_verified",
+        up: add_user_email_verified,
+        down: remove_user_email_verified,
+    },
+    MongoMigration {
+        version: 9,
+        name: "0009_create_login_failures",
+        up: create_login_failure_index,
+        down: drop_login_failures,
+    },
+    MongoMigration {
+        version: 10,
+        name: "0010_create_external_identities",
+        up: create_external_identity_indexes,
+        down: drop_external_identities,
+    },
+    MongoMigration {
+        version: 11,
+        name: "0011_create_oauth_clients",
+        up: create_oauth_client_indexes,
+        down: drop_oauth_clients,
+    },
+];
+
+fn users_validator() -> Document {
+    doc! {
+        "$jsonSchema": {
+            "bsonType": "object",
+            "required": ["name", "email"],
+            "properties": {
+                "name": { "bsonType": "string" },
+                "email": { "bsonType": "string" },
+            }
+        }
+    }
+}
+
+/// Creates `users` with its validator, or attaches the validator to an existing collection.
+async fn set_users_validator(db: &Database, validator: Document) -> Result<(), MongoError> {
+    let exists = db.list_collection_names(doc! { "name": "users" }).await?
+        .iter()
+        .any(|name| name == "users");
+
+    if exists {
+        db.run_command(doc! {
+            "collMod": "users",
+            "validator": validator,
+            "validationLevel": "moderate",
+        }, None).await?;
+    } else {
+        let options = CreateCollectionOptions::builder()
+            .validator(validator)
+            .validation_level(ValidationLevel::Moderate)
+            .build();
+        db.create_collection("users", options).await?;
+    }
+    Ok(())
+}
+
+fn create_users_collection(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        set_users_validator(db, users_validator()).await?;
+
+        db.collection::<Document>("users").create_index(email_index(false), None).await?;
+        Ok(())
+    })
+}
+
+fn drop_users_validation(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("users").drop_index("users_email_idx", None).await?;
+        Ok(set_users_validator(db, doc! {}).await?)
+    })
+}
+
+/// Users used to be inserted with their id in an `id` field next to the
+/// generated `_id`; only `_id` is ever looked up.
+fn drop_stray_user_id(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("users")
+            .update_many(doc! { "id": { "$exists": true } }, doc! { "$unset": { "id": "" } }, None)
+            .await?;
+        Ok(())
+    })
+}
+
+fn keep_user_ids(_db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move { Ok(()) })
+}
+
+fn email_index(unique: bool) -> IndexModel {
+    let options = if unique {
+        // Strength 2 compares case-insensitively, matching LOWER(email) in PostgreSQL
+        IndexOptions::builder()
+            .name("users_email_unique".to_string())
+            .unique(true)
+            .collation(Collation::builder().locale("en").strength(CollationStrength::Secondary).build())
+            .build()
+    } else {
+        IndexOptions::builder().name("users_email_idx".to_string()).build()
+    };
+
+    IndexModel::builder().keys(doc! { "email": 1 }).options(options).build()
+}
+
+/// Emails held by more than one user once lowercased, with those users' ids.
+async fn duplicate_emails(users: &Collection<Document>) -> Result<Vec<String>, AppError> {
+    let pipeline = [
+        doc! { "$match": { "email": { "$type": "string" } } },
+        doc! { "$group": { "_id": { "$toLower": "$email" }, "ids": { "$push": "$_id" }, "count": { "$sum": 1 } } },
+        doc! { "$match": { "count": { "$gt": 1 } } },
+        doc! { "$sort": { "_id": 1 } },
+    ];
+    let mut cursor = users.aggregate(pipeline, None).await?;
+
+    let mut duplicates = Vec::new();
+    while let Some(group) = cursor.try_next().await? {
+        let ids: Vec<String> = group.get_array("ids").map(|ids| ids.iter().map(|id| match id {
+            Bson::ObjectId(id) => id.to_hex(),
+            other => other.to_string(),
+        }).collect()).unwrap_or_default();
+        duplicates.push(format!("{} (ids {})", group.get_str("_id").unwrap_or_default(), ids.join(", ")));
+    }
+    Ok(duplicates)
+}
+
+/// Fails with the conflicting users, rather than an index build error, when
+/// existing emails differ only in case. Those users have to be merged or
+/// renamed by hand before the migration can run.
+fn create_unique_email_index(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        let users = db.collection::<Document>("users");
+        let duplicates = duplicate_emails(&users).await?;
+        if !duplicates.is_empty() {
+            return Err(AppError::DatabaseError(format!(
+                "Cannot make emails unique, these differ only in case: {}. Merge or rename the duplicate users, then run the migration again",
+                duplicates.join("; ")
+            )));
+        }
+
+        users.create_index(email_index(true), None).await?;
+        users.drop_index("users_email_idx", None).await?;
+        Ok(())
+    })
+}
+
+fn drop_unique_email_index(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        let users = db.collection::<Document>("users");
+        users.create_index(email_index(false), None).await?;
+        users.drop_index("users_email_unique", None).await?;
+        Ok(())
+    })
+}
+
+fn add_user_version(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("users")
+            .update_many(doc! { "version": { "$exists": false } }, doc! { "$set": { "version": 1_i64 } }, None)
+            .await?;
+        Ok(())
+    })
+}
+
+fn remove_user_version(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("users")
+            .update_many(doc! {}, doc! { "$unset": { "version": "" } }, None)
+            .await?;
+        Ok(())
+    })
+}
+
+/// Refresh tokens are keyed by their hash in `_id`; MongoDB drops them once
+/// `expires_at` has passed.
+fn create_refresh_token_indexes(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        let tokens = db.collection::<Document>("refresh_tokens");
+        tokens.create_index(IndexModel::builder()
+            .keys(doc! { "family_id": 1 })
+            .options(IndexOptions::builder().name("refresh_tokens_family_id_idx".to_string()).build())
+            .build(), None).await?;
+        tokens.create_index(IndexModel::builder()
+            .keys(doc! { "user_id": 1 })
+            .options(IndexOptions::builder().name("refresh_tokens_user_id_idx".to_string()).build())
+            .build(), None).await?;
+        tokens.create_index(IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(IndexOptions::builder()
+                .name("refresh_tokens_expiry_ttl".to_string())
+                .expire_after(std::time::Duration::ZERO)
+                .build())
+            .build(), None).await?;
+        Ok(())
+    })
+}
+
+fn drop_refresh_tokens(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move { Ok(db.collection::<Document>("refresh_tokens").drop(None).await?) })
+}
+
+fn add_user_roles(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("users")
+            .update_many(doc! { "roles": { "$exists": false } }, doc! { "$set": { "roles": ["user"] } }, None)
+            .await?;
+        Ok(())
+    })
+}
+
+fn remove_user_roles(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("users")
+            .update_many(doc! {}, doc! { "$unset": { "roles": "" } }, None)
+            .await?;
+        Ok(())
+    })
+}
+
+fn create_api_key_index(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("api_keys").create_index(IndexModel::builder()
+            .keys(doc! { "key_hash": 1 })
+            .options(IndexOptions::builder().name("api_keys_key_hash_unique".to_string()).unique(true).build())
+            .build(), None).await?;
+        Ok(())
+    })
+}
+
+fn drop_api_keys(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move { Ok(db.collection::<Document>("api_keys").drop(None).await?) })
+}
+
+/// Also indexes `one_time_tokens`, which hold verification links until they
+/// are used or MongoDB expires them.
+fn add_user_email_verified(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("users")
+            .update_many(doc! { "email_verified": { "$exists": false } }, doc! { "$set": { "email_verified": false } }, None)
+            .await?;
+
+        let tokens = db.collection::<Document>("one_time_tokens");
+        tokens.create_index(IndexModel::builder()
+            .keys(doc! { "user_id": 1 })
+            .options(IndexOptions::builder().name("one_time_tokens_user_id_idx".to_string()).build())
+            .build(), None).await?;
+        tokens.create_index(IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(IndexOptions::builder()
+                .name("one_time_tokens_expiry_ttl".to_string())
+                .expire_after(std::time::Duration::ZERO)
+                .build())
+            .build(), None).await?;
+        Ok(())
+    })
+}
+
+fn remove_user_email_verified(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("one_time_tokens").drop(None).await?;
+        db.collection::<Document>("users")
+            .update_many(doc! {}, doc! { "$unset": { "email_verified": "" } }, None)
+            .await?;
+        Ok(())
+    })
+}
+
+/// Failed login counters are keyed by `scope:key` in `_id`; MongoDB drops
+/// them once `expires_at` has passed.
+fn create_login_failure_index(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("login_failures").create_index(IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(IndexOptions::builder()
+                .name("login_failures_expiry_ttl".to_string())
+                .expire_after(std::time::Duration::ZERO)
+                .build())
+            .build(), None).await?;
+        Ok(())
+    })
+}
+
+fn drop_login_failures(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move { Ok(db.collection::<Document>("login_failures").drop(None).await?) })
+}
+
+/// Identities are keyed by `provider:subject` in `_id`. Pending logins are
+/// keyed by their state hash and expire through a TTL index.
+fn create_external_identity_indexes(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("external_identities").create_index(IndexModel::builder()
+            .keys(doc! { "user_id": 1 })
+            .options(IndexOptions::builder().name("external_identities_user_id_idx".to_string()).build())
+            .build(), None).await?;
+        db.collection::<Document>("oidc_login_states").create_index(IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(IndexOptions::builder()
+                .name("oidc_login_states_expiry_ttl".to_string())
+                .expire_after(std::time::Duration::ZERO)
+                .build())
+            .build(), None).await?;
+        Ok(())
+    })
+}
+
+fn drop_external_identities(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("oidc_login_states").drop(None).await?;
+        Ok(db.collection::<Document>("external_identities").drop(None).await?)
+    })
+}
+
+/// Clients are keyed by `client_id` in `_id`. Authorization codes are keyed
+/// by their hash and expire through a TTL index.
+fn create_oauth_client_indexes(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("oauth_authorization_codes").create_index(IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(IndexOptions::builder()
+                .name("oauth_authorization_codes_expiry_ttl".to_string())
+                .expire_after(std::time::Duration::ZERO)
+                .build())
+            .build(), None).await?;
+        Ok(())
+    })
+}
+
+fn drop_oauth_clients(db: &Database) -> BoxFuture<'_, Result<(), AppError>> {
+    Box::pin(async move {
+        db.collection::<Document>("oauth_authorization_codes").drop(None).await?;
+        Ok(db.collection::<Document>("oauth_clients").drop(None).await?)
+    })
+}
+
+async fn applied_versions(db: &Database) -> Result<Vec<Document>, AppError> {
+    let cursor = db.collection::<Document>("schema_migrations")
+        .find(None, mongodb::options::FindOptions::builder().sort(doc! { "_id": 1 }).build())
+        .await?;
+    Ok(cursor.try_collect().await?)
+}
+
+fn version_of(record: &Document) -> i64 {
+    record.get_i64("_id").unwrap_or_default()
+}
+
+pub async fn migrate_up(db: &Database) -> Result<usize, AppError> {
+    let applied = applied_versions(db).await?;
+    let records = db.collection::<Document>("schema_migrations");
+
+    let mut count = 0;
+    for migration in MIGRATIONS {
+        if applied.iter().any(|a| version_of(a) == migration.version) {
+            continue;
+        }
+
+        info!("Applying MongoDB migration {} ({})", migration.version, migration.name);
+        (migration.up)(db).await?;
+        records.insert_one(doc! {
+            "_id": migration.version,
+            "name": migration.name,
+            "applied_at": DateTime::now(),
+        }, None).await?;
+        count += 1;
+    }
+
+    info!("MongoDB schema is up to date ({} migration(s) applied)", count);
+    Ok(count)
+}
+
+pub async fn migrate_down(db: &Database, steps: usize) -> Result<usize, AppError> {
+    let applied = applied_versions(db).await?;
+    let records = db.collection::<Document>("schema_migrations");
+
+    let mut count = 0;
+    for record in applied.iter().rev().take(steps) {
+        let version = version_of(record);
+        let migration = MIGRATIONS.iter().find(|m| m.version == version)
+            .ok_or_else(|| AppError::DatabaseError(format!("Cannot revert unknown migration {}", version)))?;
+
+        warn!("Reverting MongoDB migration {} ({})", migration.version, migration.name);
+        (migration.down)(db).await?;
+        records.delete_one(doc! { "_id": version }, None).await?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+pub async fn migration_status(db: &Database) -> Result<Vec<MigrationStatus>, AppError> {
+    let applied = applied_versions(db).await?;
+    let applied_at = |record: &Document| record.get_datetime("applied_at").ok()
+        .and_then(|at| at.try_to_rfc3339_string().ok());
+
+    let mut statuses: Vec<MigrationStatus> = MIGRATIONS.iter().map(|migration| {
+        let record = applied.iter().find(|a| version_of(a) == migration.version);
+        MigrationStatus {
+            version: migration.version,
+            name: migration.name.to_string(),
+            state: if record.is_some() { MigrationState::Applied } else { MigrationState::Pending },
+            applied_at: record.and_then(applied_at),
+        }
+    }).collect();
+
+    for record in applied.iter().filter(|a| MIGRATIONS.iter().all(|m| m.version != version_of(a))) {
+        statuses.push(MigrationStatus {
+            version: version_of(record),
+            name: record.get_str("name").unwrap_or_default().to_string(),
+            state: MigrationState::Unknown,
+            applied_at: applied_at(record),
+        });
+    }
+
+    Ok(statuses)
+}
@@ -0,0 +1,198 @@
+use sha2::{Digest, Sha256};
+use tokio_postgres::Client;
+use log::{info, warn, error};
+
+use crate::db::postgres::{checkout, PgPool};
+use crate::db::migrations::{MigrationState, MigrationStatus};
+use crate::errors::app_error::AppError;
+
+/// Arbitrary key for `pg_advisory_lock`, so only one instance migrates at a time.
+const MIGRATION_LOCK_KEY: i64 = 0x7573_6572_735f_6d67;
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+impl Migration {
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+macro_rules! migration {
+    ($version:expr, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            up: include_str!(concat!("../../../migrations/postgres/", $name, ".up.sql")),
+            down: include_str!(concat!("../../../migrations/postgres/", $name, ".down.sql")),
+        }
+    };
+}
+
+/// Every migration known to this build, in the order they must be applied.
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_create_users_table"),
+    migration!(2, "0002_unique_user_email"),
+    migration!(3, "0003_add_user_version"),
+    migration!(4, "0004_add_user_password_hash"),
+    migration!(5, "0005_create_refresh_tokens"),
+    migration!(6, "0006_add_user_roles"),
+    migration!(7, "0007_create_api_keys"),
+    migration!(8, "0008_add_user_email_verified"),
+    migration!(9, "0009_add_user_totp"),
+    migration!(10, "0010_create_login_failures"),
+    migration!(11, "0011_create_external_identities"),
+    migration!(12, "0012_create_oauth_clients"),
+];
+
+struct AppliedMigration {
+    version: i64,
+    name: String,
+    checksum: String,
+    applied_at: String,
+}
+
+async fn ensure_migrations_table(conn: &Client) -> Result<(), AppError> {
+    conn.batch_execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )"
+    ).await?;
+    Ok(())
+}
+
+async fn applied_migrations(conn: &Client) -> Result<Vec<AppliedMigration>, AppError> {
+    let rows = conn.query(
+        "SELECT version, name, checksum, applied_at::text FROM schema_migrations ORDER BY version",
+        &[]
+    ).await?;
+
+    Ok(rows.iter().map(|row| AppliedMigration {
+        version: row.get(0),
+        name: row.get(1),
+        checksum: row.get(2),
+        applied_at: row.get(3),
+    }).collect())
+}
+
+fn verify_checksums(applied: &[AppliedMigration]) -> Result<(), AppError> {
+    for record in applied {
+        if let Some(migration) = MIGRATIONS.iter().find(|m| m.version == record.version) {
+            if migration.checksum() != record.checksum {
+                error!("Checksum mismatch for applied migration {} ({})", record.version, record.name);
+                return Err(AppError::DatabaseError(format!(
+                    "Migration {} ({}) was modified after being applied", record.version, record.name
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Applies every pending migration, each one in its own transaction.
+///
+/// Everything runs on the one connection holding the advisory lock, so a pool
+/// of a single connection is enough.
+pub async fn migrate_up(pool: &PgPool) -> Result<usize, AppError> {
+    let mut conn = checkout(pool).await?;
+    conn.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+
+    let result = async {
+        ensure_migrations_table(&conn).await?;
+        let applied = applied_migrations(&conn).await?;
+        verify_checksums(&applied)?;
+
+        let mut count = 0;
+        for migration in MIGRATIONS {
+            if applied.iter().any(|a| a.version == migration.version) {
+                continue;
+            }
+
+            info!("Applying PostgreSQL migration {} ({})", migration.version, migration.name);
+            let tx = conn.transaction().await?;
+            tx.batch_execute(migration.up).await?;
+            tx.execute(
+                "INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)",
+                &[&migration.version, &migration.name, &migration.checksum()]
+            ).await?;
+            tx.commit().await?;
+            count += 1;
+        }
+        Ok::<usize, AppError>(count)
+    }.await;
+
+    conn.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+
+    let count = result?;
+    info!("PostgreSQL schema is up to date ({} migration(s) applied)", count);
+    Ok(count)
+}
+
+/// Reverts the `steps` most recently applied migrations.
+pub async fn migrate_down(pool: &PgPool, steps: usize) -> Result<usize, AppError> {
+    let mut conn = checkout(pool).await?;
+    conn.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+
+    let result = async {
+        ensure_migrations_table(&conn).await?;
+        let applied = applied_migrations(&conn).await?;
+
+        let mut count = 0;
+        for record in applied.iter().rev().take(steps) {
+            let migration = MIGRATIONS.iter().find(|m| m.version == record.version)
+                .ok_or_else(|| AppError::DatabaseError(format!(
+                    "Cannot revert unknown migration {} ({})", record.version, record.name
+                )))?;
+
+            warn!("Reverting PostgreSQL migration {} ({})", migration.version, migration.name);
+            let tx = conn.transaction().await?;
+            tx.batch_execute(migration.down).await?;
+            tx.execute("DELETE FROM schema_migrations WHERE version = $1", &[&migration.version]).await?;
+            tx.commit().await?;
+            count += 1;
+        }
+        Ok::<usize, AppError>(count)
+    }.await;
+
+    conn.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    result
+}
+
+pub async fn migration_status(pool: &PgPool) -> Result<Vec<MigrationStatus>, AppError> {
+    let conn = checkout(pool).await?;
+    ensure_migrations_table(&conn).await?;
+    let applied = applied_migrations(&conn).await?;
+
+    let mut statuses: Vec<MigrationStatus> = MIGRATIONS.iter().map(|migration| {
+        let record = applied.iter().find(|a| a.version == migration.version);
+        let state = match record {
+            Some(record) if record.checksum != migration.checksum() => MigrationState::ChecksumMismatch,
+            Some(_) => MigrationState::Applied,
+            None => MigrationState::Pending,
+        };
+        MigrationStatus {
+            version: migration.version,
+            name: migration.name.to_string(),
+            state,
+            applied_at: record.map(|r| r.applied_at.clone()),
+        }
+    }).collect();
+
+    for record in applied.iter().filter(|a| MIGRATIONS.iter().all(|m| m.version != a.version)) {
+        statuses.push(MigrationStatus {
+            version: record.version,
+            name: record.name.clone(),
+            state: MigrationState::Unknown,
+            applied_at: Some(record.applied_at.clone()),
+        });
+    }
+
+    Ok(statuses)
+}
@@ -1,2 +1,5 @@
+#[cfg(feature = "postgres")]
 pub mod postgres;
-pub mod mongo;
\ No newline at end of file
+#[cfg(feature = "mongo")]
+pub mod mongo;
+pub mod migrations;
\ No newline at end of file
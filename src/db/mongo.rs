@@ -1,26 +1,66 @@
-use mongodb::{Client, Database};
-use std::env;
+use mongodb::{Client, Database, bson::{doc, oid::ObjectId}};
+use mongodb::error::{Error as MongoError, ErrorKind, WriteFailure};
 use log::{info, error};
+use crate::config::settings::SettingsSource;
 use crate::errors::app_error::AppError;
 
-pub async fn mongo_connect() -> Result<Database, AppError> {
-    info!("Attempting to connect to MongoDB");
-    let mongo_uri = env::var("MONGODB_URI")
-        .map_err(|e| {
-            error!("Failed to read MONGODB_URI from environment: {}", e);
-            AppError::DatabaseError(format!("MongoDB URI not set: {}", e))
-        })?;
+/// Where the MongoDB data lives: `MONGODB_URI` and `MONGODB_DATABASE`.
+pub struct MongoConfig {
+    pub uri: String,
+    pub database: String,
+}
+
+impl MongoConfig {
+    pub fn from_settings(settings: &SettingsSource) -> Self {
+        MongoConfig {
+            uri: settings.require("MONGODB_URI", ""),
+            database: settings.get_or("MONGODB_DATABASE", "mydatabase"),
+        }
+    }
+}
 
-    info!("Establishing connection to MongoDB");
-    let client = Client::with_uri_str(&mongo_uri).await
+/// Opens a handle to the database. The driver connects on first use, so this
+/// succeeds while the server is down.
+pub async fn mongo_database(config: &MongoConfig) -> Result<Database, AppError> {
+    info!("Creating MongoDB client");
+    let client = Client::with_uri_str(&config.uri).await
         .map_err(|e| {
             error!("Failed to create MongoDB client: {}", e);
             AppError::DatabaseError(format!("MongoDB connection error: {}", e))
         })?;
 
-    info!("Connection to MongoDB established successfully");
-    let database = client.database("mydatabase");
-    info!("MongoDB database 'mydatabase' selected");
+    let database = client.database(&config.database);
+    info!("MongoDB database '{}' selected", config.database);
+
+    Ok(database)
+}
 
+/// Like `mongo_database`, but fails unless the server answers a `ping`.
+pub async fn mongo_connect(config: &MongoConfig) -> Result<Database, AppError> {
+    let database = mongo_database(config).await?;
+    ping(&database).await?;
+    info!("Connection to MongoDB established successfully");
     Ok(database)
-}
\ No newline at end of file
+}
+
+/// Runs the `ping` command, which needs a server to answer.
+pub async fn ping(database: &Database) -> Result<(), AppError> {
+    database.run_command(doc! { "ping": 1 }, None).await?;
+    Ok(())
+}
+
+pub fn parse_object_id(id: &str) -> Result<ObjectId, AppError> {
+    ObjectId::parse_str(id).map_err(|_| {
+        error!("Invalid ID format: {}", id);
+        AppError::BadRequest("Invalid ID format".to_string())
+    })
+}
+
+/// Whether a write was rejected by a unique index (server error code 11000).
+pub fn is_duplicate_key(error: &MongoError) -> bool {
+    match error.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(e)) => e.code == 11000,
+        ErrorKind::Command(e) => e.code == 11000,
+        _ => false,
+    }
+}
@@ -1,73 +1,93 @@
-use rocket::response::status::Custom;
-use rocket::http::Status;
-use tokio_postgres::{Client, NoTls};
+use std::time::Duration;
+use bb8::{Builder, Pool, PooledConnection};
+use bb8_postgres::PostgresConnectionManager;
+use tokio_postgres::{Config, NoTls};
+use tokio_postgres::error::SqlState;
 use rocket::State;
-use log::{info, error};
-
-use crate::models::user::User;
-
-pub async fn get_users_from_db(client: &DbClient) -> Result<Vec<User>, Custom<String>> {
-    info!("Fetching users from PostgreSQL database");
-    match client.query("SELECT id, name, email FROM users", &[]).await {
-        Ok(rows) => {
-            let users = rows
-                .iter()
-                .map(|row| User { id: Some(row.get(0)), name: row.get(1), email: row.get(2) })
-                .collect::<Vec<User>>();
-            info!("Successfully fetched {} users from database", users.len());
-            Ok(users)
-        }
-        Err(e) => {
-            error!("Failed to fetch users from database: {}", e);
-            Err(Custom(Status::InternalServerError, e.to_string()))
-        }
-    }
-}
+use log::info;
 
-pub async fn execute_query(
-    client: &DbClient,
-    query: &str,
-    params: &[&(dyn tokio_postgres::types::ToSql + Sync)]
-) -> Result<u64, Custom<String>> {
-    info!("Executing PostgreSQL query: {}", query);
-    match client.execute(query, params).await {
-        Ok(rows_affected) => {
-            info!("Query executed successfully. Rows affected: {}", rows_affected);
-            Ok(rows_affected)
-        }
-        Err(e) => {
-            error!("Failed to execute query: {}", e);
-            Err(Custom(Status::InternalServerError, e.to_string()))
-        }
-    }
-}
+use crate::config::settings::SettingsSource;
+use crate::errors::app_error::AppError;
+
+type PgManager = PostgresConnectionManager<NoTls>;
+pub type PgPool = Pool<PgManager>;
+pub type PgConnection<'a> = PooledConnection<'a, PgManager>;
 
+/// Settings for the PostgreSQL connection pool.
+///
+/// Broken connections are dropped by the pool and replaced on the next
+/// checkout, so a database restart only fails the requests in flight.
 pub struct PostgresConfig {
     pub connection_string: String,
+    pub max_size: u32,
+    pub min_idle: Option<u32>,
+    pub checkout_timeout: Duration,
+    pub test_on_checkout: bool,
 }
 
 impl PostgresConfig {
-    pub async fn connect(&self) -> Result<Client, tokio_postgres::Error> {
-        info!("Attempting to connect to PostgreSQL database");
-        let (client, connection) = tokio_postgres::connect(&self.connection_string, NoTls).await?;
-
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                error!("PostgreSQL connection error: {}", e);
-            }
-        });
-
-        info!("Successfully connected to PostgreSQL database");
-        Ok(client)
+    pub fn from_settings(settings: &SettingsSource) -> Self {
+        PostgresConfig {
+            connection_string: settings.require("DATABASE_URL", ""),
+            max_size: settings.parse("PG_POOL_MAX_SIZE", 16),
+            min_idle: Some(settings.parse("PG_POOL_MIN_IDLE", 1)),
+            checkout_timeout: Duration::from_secs(settings.parse("PG_POOL_CHECKOUT_TIMEOUT_SECS", 5)),
+            test_on_checkout: settings.parse("PG_POOL_TEST_ON_CHECKOUT", true),
+        }
+    }
+
+    pub async fn connect(&self) -> Result<PgPool, tokio_postgres::Error> {
+        info!("Creating PostgreSQL connection pool (max size {})", self.max_size);
+        let (builder, manager) = self.builder()?;
+        let pool = builder.build(manager).await?;
+
+        info!("PostgreSQL connection pool ready");
+        Ok(pool)
     }
+
+    /// A pool that opens no connection until one is checked out, so the
+    /// service can start while the database is down.
+    pub fn connect_lazily(&self) -> Result<PgPool, tokio_postgres::Error> {
+        let (builder, manager) = self.builder()?;
+        Ok(builder.build_unchecked(manager))
+    }
+
+    fn builder(&self) -> Result<(Builder<PgManager>, PgManager), tokio_postgres::Error> {
+        let config = self.connection_string.parse::<Config>()?;
+        let manager = PostgresConnectionManager::new(config, NoTls);
+
+        let builder = Pool::builder()
+            .max_size(self.max_size)
+            .min_idle(self.min_idle)
+            .connection_timeout(self.checkout_timeout)
+            .test_on_check_out(self.test_on_checkout);
+        Ok((builder, manager))
+    }
+}
+
+pub async fn create_postgres_pool(config: &PostgresConfig) -> Result<PgPool, AppError> {
+    let pool = config.connect().await?;
+
+    // Make sure the database is actually reachable before reporting success
+    drop(checkout(&pool).await?);
+
+    Ok(pool)
+}
+
+/// Checks out a connection from the pool, waiting at most the configured checkout timeout.
+pub async fn checkout(pool: &PgPool) -> Result<PgConnection<'_>, AppError> {
+    pool.get().await.map_err(AppError::from)
+}
+
+/// Runs `SELECT 1` on a pooled connection.
+pub async fn ping(pool: &PgPool) -> Result<(), AppError> {
+    checkout(pool).await?.simple_query("SELECT 1").await?;
+    Ok(())
 }
 
-pub async fn create_postgres_client() -> Result<Client, tokio_postgres::Error> {
-    let config = PostgresConfig {
-        connection_string: std::env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set"),
-    };
-    config.connect().await
+/// Whether a statement was rejected by a unique constraint or index.
+pub fn is_unique_violation(error: &tokio_postgres::Error) -> bool {
+    error.code() == Some(&SqlState::UNIQUE_VIOLATION)
 }
 
-pub type DbClient = State<Client>;
\ No newline at end of file
+pub type DbClient = State<PgPool>;
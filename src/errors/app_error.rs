@@ -1,5 +1,7 @@
 use thiserror::Error;
+#[cfg(feature = "mongo")]
 use mongodb::error::Error as MongoError;
+#[cfg(feature = "postgres")]
 use tokio_postgres::Error as PostgresError;
 use rocket::http::Status;
 use rocket::request::Request;
@@ -11,6 +13,8 @@ use schemars::JsonSchema;
 use rocket::response::status::Custom;
 use log::{error, warn};
 
+use crate::utils::validation::FieldError;
+
 #[derive(Error, Debug, JsonSchema)]
 pub enum AppError {
     #[error("Database error: {0}")]
@@ -23,8 +27,49 @@ pub enum AppError {
     Unauthorized(String),
     #[error("Forbidden: {0}")]
     Forbidden(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+    #[error("Validation failed: {0:?}")]
+    ValidationFailed(Vec<FieldError>),
     #[error("Internal server error: {0}")]
     InternalServerError(String),
+    /// An upstream service, such as an identity provider, failed or could not be reached.
+    #[error("Bad gateway: {0}")]
+    BadGateway(String),
+}
+
+/// Message of an `AppError` raised by a request guard. Rocket hands guard
+/// failures to the catchers with only a status, so the message is parked in
+/// the request-local cache until `json_catcher` renders it.
+pub struct GuardFailure(pub Option<String>);
+
+impl AppError {
+    pub fn status(&self) -> Status {
+        match self {
+            AppError::DatabaseError(_) | AppError::InternalServerError(_) => Status::InternalServerError,
+            AppError::NotFound(_) => Status::NotFound,
+            AppError::BadRequest(_) => Status::BadRequest,
+            AppError::Unauthorized(_) => Status::Unauthorized,
+            AppError::Forbidden(_) => Status::Forbidden,
+            AppError::Conflict(_) => Status::Conflict,
+            AppError::PreconditionFailed(_) => Status::PreconditionFailed,
+            AppError::ValidationFailed(_) => Status::UnprocessableEntity,
+            AppError::BadGateway(_) => Status::BadGateway,
+        }
+    }
+
+    /// The message clients see, without the `Display` prefix.
+    pub fn message(&self) -> String {
+        match self {
+            AppError::DatabaseError(msg) | AppError::NotFound(msg) | AppError::BadRequest(msg)
+            | AppError::Unauthorized(msg) | AppError::Forbidden(msg) | AppError::Conflict(msg)
+            | AppError::PreconditionFailed(msg) | AppError::InternalServerError(msg)
+            | AppError::BadGateway(msg) => msg.clone(),
+            AppError::ValidationFailed(_) => "Validation failed".to_string(),
+        }
+    }
 }
 
 impl OpenApiResponderInner for AppError {
@@ -53,6 +98,7 @@ impl OpenApiResponderInner for AppError {
 
 impl<'r> Responder<'r, 'static> for AppError {
     fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let mut field_errors = None;
         let (status, message) = match self {
             AppError::DatabaseError(msg) => {
                 error!("Database error: {}", msg);
@@ -74,16 +120,36 @@ impl<'r> Responder<'r, 'static> for AppError {
                 warn!("Forbidden: {}", msg);
                 (Status::Forbidden, msg)
             },
+            AppError::Conflict(msg) => {
+                warn!("Conflict: {}", msg);
+                (Status::Conflict, msg)
+            },
+            AppError::PreconditionFailed(msg) => {
+                warn!("Precondition failed: {}", msg);
+                (Status::PreconditionFailed, msg)
+            },
+            AppError::ValidationFailed(errors) => {
+                warn!("Validation failed: {:?}", errors);
+                field_errors = Some(errors);
+                (Status::UnprocessableEntity, "Validation failed".to_string())
+            },
             AppError::InternalServerError(msg) => {
                 error!("Internal server error: {}", msg);
                 (Status::InternalServerError, msg)
             },
+            AppError::BadGateway(msg) => {
+                error!("Bad gateway: {}", msg);
+                (Status::BadGateway, msg)
+            },
         };
 
-        let body = Json(json!({
+        let mut body = Json(json!({
             "error": status.to_string(),
             "message": message
         }));
+        if let Some(errors) = field_errors {
+            body["errors"] = json!(errors);
+        }
 
         Response::build()
             .status(status)
@@ -93,6 +159,7 @@ impl<'r> Responder<'r, 'static> for AppError {
     }
 }
 
+#[cfg(feature = "mongo")]
 impl From<MongoError> for AppError {
     fn from(error: MongoError) -> Self {
         error!("MongoDB error: {}", error);
@@ -100,6 +167,7 @@ impl From<MongoError> for AppError {
     }
 }
 
+#[cfg(feature = "postgres")]
 impl From<PostgresError> for AppError {
     fn from(error: PostgresError) -> Self {
         error!("PostgreSQL error: {}", error);
@@ -107,6 +175,19 @@ impl From<PostgresError> for AppError {
     }
 }
 
+#[cfg(feature = "postgres")]
+impl From<bb8::RunError<PostgresError>> for AppError {
+    fn from(error: bb8::RunError<PostgresError>) -> Self {
+        match error {
+            bb8::RunError::User(e) => e.into(),
+            bb8::RunError::TimedOut => {
+                error!("Timed out waiting for a PostgreSQL connection from the pool");
+                AppError::DatabaseError("Timed out waiting for a database connection".to_string())
+            },
+        }
+    }
+}
+
 impl From<Custom<String>> for AppError {
     fn from(custom: Custom<String>) -> Self {
         match custom.1.as_str() {
@@ -126,6 +207,10 @@ impl From<Custom<String>> for AppError {
                 warn!("Forbidden: {}", custom.1);
                 AppError::Forbidden(custom.1)
             },
+            "Conflict" => {
+                warn!("Conflict: {}", custom.1);
+                AppError::Conflict(custom.1)
+            },
             _ => {
                 error!("Internal Server Error: {}", custom.1);
                 AppError::InternalServerError(custom.1)
@@ -1 +1 @@
-pub mod app_error;
\ No newline at end of file
+pub mod app_error;pub mod oauth_error;
@@ -0,0 +1,103 @@
+use rocket::http::{Header, Status};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::{MediaType, RefOr, Response as OpenApiResponse, Responses};
+use rocket_okapi::response::OpenApiResponderInner;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::json;
+use log::{error, warn};
+
+use crate::errors::app_error::AppError;
+
+/// Body of an OAuth 2.0 error response (RFC 6749, section 5.2).
+#[derive(Serialize, JsonSchema)]
+struct OAuthErrorBody {
+    error: String,
+    error_description: String,
+}
+
+/// Error returned by the token endpoint, in the shape OAuth client libraries
+/// expect rather than the `AppError` one.
+#[derive(Debug)]
+pub struct OAuthError {
+    pub status: Status,
+    pub error: &'static str,
+    pub description: String,
+}
+
+impl OAuthError {
+    fn new(status: Status, error: &'static str, description: impl Into<String>) -> Self {
+        OAuthError { status, error, description: description.into() }
+    }
+
+    pub fn invalid_request(description: impl Into<String>) -> Self {
+        Self::new(Status::BadRequest, "invalid_request", description)
+    }
+
+    pub fn invalid_client() -> Self {
+        Self::new(Status::Unauthorized, "invalid_client", "Client authentication failed")
+    }
+
+    pub fn invalid_grant(description: impl Into<String>) -> Self {
+        Self::new(Status::BadRequest, "invalid_grant", description)
+    }
+
+    pub fn unauthorized_client(description: impl Into<String>) -> Self {
+        Self::new(Status::BadRequest, "unauthorized_client", description)
+    }
+
+    pub fn unsupported_grant_type(grant_type: &str) -> Self {
+        Self::new(Status::BadRequest, "unsupported_grant_type", format!("Unsupported grant_type {}", grant_type))
+    }
+
+    pub fn invalid_scope(description: impl Into<String>) -> Self {
+        Self::new(Status::BadRequest, "invalid_scope", description)
+    }
+}
+
+impl From<AppError> for OAuthError {
+    fn from(error: AppError) -> Self {
+        error!("Token request failed: {}", error);
+        Self::new(error.status(), "server_error", error.message())
+    }
+}
+
+impl<'r> Responder<'r, 'static> for OAuthError {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        warn!("OAuth error {}: {}", self.error, self.description);
+        let body = json!({ "error": self.error, "error_description": self.description }).to_string();
+
+        let mut response = Response::build();
+        response
+            .status(self.status)
+            .header(rocket::http::ContentType::JSON)
+            .header(Header::new("Cache-Control", "no-store"))
+            .sized_body(body.len(), std::io::Cursor::new(body));
+        if self.status == Status::Unauthorized {
+            response.header(Header::new("WWW-Authenticate", "Basic"));
+        }
+        response.ok()
+    }
+}
+
+impl OpenApiResponderInner for OAuthError {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let response = OpenApiResponse {
+            description: "OAuth 2.0 error".to_string(),
+            content: rocket_okapi::okapi::map! {
+                "application/json".to_string() => MediaType {
+                    schema: Some(gen.json_schema::<OAuthErrorBody>()),
+                    ..Default::default()
+                }
+            },
+            ..Default::default()
+        };
+
+        let mut responses = Responses::default();
+        responses.responses.insert("400".to_string(), RefOr::Object(response.clone()));
+        responses.responses.insert("401".to_string(), RefOr::Object(response));
+        Ok(responses)
+    }
+}
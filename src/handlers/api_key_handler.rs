@@ -0,0 +1,47 @@
+use rocket::serde::json::Json;
+use rocket::{ State, http::Status };
+use rocket::response::status::Created;
+use rocket_okapi::openapi;
+use log::info;
+
+use crate::models::api_key::{ApiKey, CreatedApiKey, NewApiKey};
+use crate::errors::app_error::AppError;
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::api_key_service::create_api_key;
+use crate::utils::authentication::{CanManageApiKeys, Permitted};
+use crate::utils::validation::validated;
+
+/// Creates an API key. The response is the only time the key is shown.
+#[openapi]
+#[post("/auth/api-keys", data = "<new_key>")]
+pub async fn add_api_key(
+    store: &State<AuthStore>,
+    new_key: Json<NewApiKey>,
+    caller: Permitted<CanManageApiKeys>
+) -> Result<Created<Json<CreatedApiKey>>, AppError> {
+    info!("Creating API key {:?} on behalf of {}", new_key, caller.id());
+    let new_key = validated(new_key.into_inner())?;
+    let created = create_api_key(store, &caller, new_key).await?;
+    info!("API key {} created", created.key.id);
+    Ok(Created::new(format!("/auth/api-keys/{}", created.key.id)).body(Json(created)))
+}
+
+#[openapi]
+#[get("/auth/api-keys")]
+pub async fn get_api_keys(store: &State<AuthStore>, _caller: Permitted<CanManageApiKeys>) -> Result<Json<Vec<ApiKey>>, AppError> {
+    info!("Fetching API keys");
+    Ok(Json(store.list_api_keys().await?))
+}
+
+/// Revokes an API key; it is rejected from the next request on.
+#[openapi]
+#[delete("/auth/api-keys/<id>")]
+pub async fn revoke_api_key(
+    store: &State<AuthStore>,
+    id: String,
+    caller: Permitted<CanManageApiKeys>
+) -> Result<Status, AppError> {
+    info!("Revoking API key {} on behalf of {}", id, caller.id());
+    store.revoke_api_key(&id).await?;
+    Ok(Status::NoContent)
+}
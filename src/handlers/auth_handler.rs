@@ -0,0 +1,292 @@
+use std::net::IpAddr;
+
+use rocket::serde::json::Json;
+use rocket::{ State, http::Status };
+use rocket_okapi::openapi;
+use log::{info, error};
+
+use crate::config::lockout_config::LockoutConfig;
+use crate::config::mail_config::MailConfig;
+use crate::models::auth::{
+    AccessToken, LoginRequest, LoginResponse, PasswordChangeRequest, PasswordResetConfirmation, PasswordResetRequest,
+    RecoveryCodes, RefreshRequest, TotpConfirmation, TotpDisableRequest, TotpEnrollment,
+};
+use crate::errors::app_error::AppError;
+use crate::config::jwt_config::JwtConfig;
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::login_throttle_service::{ensure_login_allowed, record_failed_login, record_successful_login};
+use crate::services::mailer::SharedMailer;
+use crate::services::password_reset_service::{confirm_password_reset, request_password_reset};
+use crate::services::password_service::{check_password_strength, hash_password, verify_password};
+use crate::services::session_service::{end_session, refresh_session, start_session};
+use crate::services::two_factor_service::{begin_enrollment, confirm_enrollment, verify_second_factor};
+use crate::models::role::{Permission, RolesUpdate};
+use crate::utils::authentication::{AuthenticatedUser, Caller, CanManageRoles, Permitted};
+use crate::utils::validation::normalize_email;
+
+/// Same message for an unknown email and a wrong password, so callers cannot
+/// probe which accounts exist.
+fn invalid_credentials() -> AppError {
+    AppError::Unauthorized("Invalid email or password".to_string())
+}
+
+/// Accounts with two-factor authentication also need `totp_code`: the
+/// current code from the authenticator app, or an unused recovery code.
+///
+/// Repeated failures lock the email and the client address for a while; see
+/// `LockoutConfig`.
+#[openapi]
+#[post("/auth/login", data = "<login>")]
+pub async fn login(
+    store: &State<AuthStore>,
+    jwt: &State<JwtConfig>,
+    lockout: &State<LockoutConfig>,
+    client_ip: Option<IpAddr>,
+    login: Json<LoginRequest>
+) -> Result<Json<LoginResponse>, AppError> {
+    info!("Login attempt: {:?}", login);
+    let login = login.into_inner();
+    let email = normalize_email(&login.email);
+    ensure_login_allowed(store, lockout, &email, client_ip).await?;
+
+    let credentials = store.find_credentials_by_email(&email).await?;
+    let password_hash = credentials.as_ref().and_then(|c| c.password_hash.as_ref()).map(|h| h.expose());
+    let authenticated = match credentials {
+        Some(credentials) if verify_password(&login.password, password_hash).await? => {
+            match verify_second_factor(store, &credentials, login.totp_code.as_ref()).await {
+                // Asking for the code is the first step of a two-factor login, not a failure.
+                Err(e) if login.totp_code.is_none() => return Err(e),
+                result => result.map(|_| credentials),
+            }
+        }
+        _ => Err(invalid_credentials()),
+    };
+
+    match authenticated {
+        Ok(credentials) => {
+            record_successful_login(store, &email).await?;
+            info!("User {} logged in", credentials.user_id);
+            let token = start_session(store, jwt, &credentials).await?;
+            Ok(Json(LoginResponse {
+                user_id: credentials.user_id,
+                name: credentials.name,
+                email: credentials.email,
+                token,
+            }))
+        }
+        Err(e) => {
+            error!("Rejected login attempt");
+            if matches!(e, AppError::Unauthorized(_)) {
+                record_failed_login(store, lockout, &email, client_ip).await?;
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Changes the caller's own password. Besides the access token it takes the
+/// current password and, with 2FA enabled, a code, checked like a login
+/// including its lockout.
+#[openapi]
+#[put("/auth/password", data = "<change>")]
+pub async fn change_password(
+    store: &State<AuthStore>,
+    lockout: &State<LockoutConfig>,
+    client_ip: Option<IpAddr>,
+    change: Json<PasswordChangeRequest>,
+    caller: AuthenticatedUser
+) -> Result<Status, AppError> {
+    info!("Password change request from user {}: {:?}", caller.user_id, change);
+    let change = change.into_inner();
+    let Some(credentials) = store.find_credentials_by_id(&caller.user_id).await? else {
+        error!("Rejected password change for unknown user {}", caller.user_id);
+        return Err(invalid_credentials());
+    };
+    let email = normalize_email(&credentials.email);
+    ensure_login_allowed(store, lockout, &email, client_ip).await?;
+
+    let password_hash = credentials.password_hash.as_ref().map(|h| h.expose());
+    let authenticated = if verify_password(&change.current_password, password_hash).await? {
+        verify_second_factor(store, &credentials, change.totp_code.as_ref()).await
+    } else {
+        Err(invalid_credentials())
+    };
+
+    if let Err(e) = authenticated {
+        error!("Rejected password change");
+        if matches!(e, AppError::Unauthorized(_)) {
+            record_failed_login(store, lockout, &email, client_ip).await?;
+        }
+        return Err(e);
+    }
+
+    check_password_strength("new_password", &change.new_password, &credentials.email)?;
+    let new_hash = hash_password(&change.new_password).await?;
+    store.set_password_hash(&credentials.user_id, &new_hash).await?;
+    store.revoke_user_refresh_tokens(&credentials.user_id).await?;
+    info!("Password changed for user {}", credentials.user_id);
+    Ok(Status::NoContent)
+}
+
+/// Returns the identity carried by the caller's access token.
+#[openapi]
+#[get("/auth/me")]
+pub async fn me(caller: AuthenticatedUser) -> Json<AuthenticatedUser> {
+    Json(caller)
+}
+
+/// Rotates a refresh token: the presented token stops working and a new pair is returned.
+#[openapi]
+#[post("/auth/refresh", data = "<refresh>")]
+pub async fn refresh(
+    store: &State<AuthStore>,
+    jwt: &State<JwtConfig>,
+    refresh: Json<RefreshRequest>
+) -> Result<Json<AccessToken>, AppError> {
+    info!("Refreshing session");
+    let token = refresh_session(store, jwt, refresh.refresh_token.expose()).await?;
+    Ok(Json(token))
+}
+
+/// Revokes the session of a refresh token. Access tokens already issued stay
+/// valid until they expire.
+#[openapi]
+#[post("/auth/logout", data = "<logout>")]
+pub async fn logout(store: &State<AuthStore>, logout: Json<RefreshRequest>) -> Result<Status, AppError> {
+    info!("Logging out");
+    end_session(store, logout.refresh_token.expose()).await?;
+    Ok(Status::NoContent)
+}
+
+/// Revokes every session of a user.
+#[openapi]
+#[delete("/auth/users/<user_id>/sessions")]
+pub async fn revoke_sessions(store: &State<AuthStore>, user_id: String, caller: Caller) -> Result<Status, AppError> {
+    info!("Revoking all sessions of user {} on behalf of {}", user_id, caller.id());
+    caller.require_self_or(&user_id, Permission::RevokeAnySessions)?;
+    let revoked = store.revoke_user_refresh_tokens(&user_id).await?;
+    info!("Revoked {} refresh tokens of user {}", revoked, user_id);
+    Ok(Status::NoContent)
+}
+
+/// Replaces a user's roles. They apply to access tokens issued from now on.
+#[openapi]
+#[put("/auth/users/<user_id>/roles", data = "<update>")]
+pub async fn set_roles(
+    store: &State<AuthStore>,
+    user_id: String,
+    update: Json<RolesUpdate>,
+    caller: Permitted<CanManageRoles>
+) -> Result<Status, AppError> {
+    info!("Setting roles of user {} to {:?} on behalf of {}", user_id, update.roles, caller.id());
+    store.set_roles(&user_id, &update.roles).await?;
+    Ok(Status::NoContent)
+}
+
+/// Emails a password reset link. Always answers `202 Accepted` right away,
+/// before the email is even looked up, so neither the answer nor its timing
+/// can be used to find accounts.
+#[openapi]
+#[post("/auth/password-reset/request", data = "<request>")]
+pub async fn password_reset_request(
+    store: &State<AuthStore>,
+    mailer: &State<SharedMailer>,
+    mail_config: &State<MailConfig>,
+    request: Json<PasswordResetRequest>
+) -> Status {
+    info!("Password reset requested");
+    let (store, mailer, mail_config) = (store.inner().clone(), mailer.inner().clone(), mail_config.inner().clone());
+    let email = request.into_inner().email;
+    tokio::spawn(async move {
+        if let Err(e) = request_password_reset(&store, &mailer, &mail_config, &email).await {
+            error!("Failed to process password reset request: {:?}", e);
+        }
+    });
+    Status::Accepted
+}
+
+/// Sets a new password with the token from the reset email. Every session of
+/// the user is revoked.
+#[openapi]
+#[post("/auth/password-reset/confirm", data = "<confirmation>")]
+pub async fn password_reset_confirm(
+    store: &State<AuthStore>,
+    confirmation: Json<PasswordResetConfirmation>
+) -> Result<Status, AppError> {
+    info!("Confirming password reset");
+    confirm_password_reset(store, &confirmation.token, &confirmation.new_password).await?;
+    Ok(Status::NoContent)
+}
+
+/// Starts two-factor enrollment. Add the returned secret or `otpauth://` URI
+/// to an authenticator app, then confirm with a code from it. Calling it again
+/// before confirming replaces the secret.
+#[openapi]
+#[post("/auth/2fa/enroll")]
+pub async fn enroll_two_factor(
+    store: &State<AuthStore>,
+    jwt: &State<JwtConfig>,
+    caller: AuthenticatedUser
+) -> Result<Json<TotpEnrollment>, AppError> {
+    info!("Starting two-factor enrollment for user {}", caller.user_id);
+    let enrollment = begin_enrollment(store, &jwt.issuer, &caller.user_id).await?;
+    Ok(Json(enrollment))
+}
+
+/// Enables two-factor authentication with a first code from the authenticator
+/// app. The recovery codes in the response are shown only this once.
+#[openapi]
+#[post("/auth/2fa/confirm", data = "<confirmation>")]
+pub async fn confirm_two_factor(
+    store: &State<AuthStore>,
+    confirmation: Json<TotpConfirmation>,
+    caller: AuthenticatedUser
+) -> Result<Json<RecoveryCodes>, AppError> {
+    info!("Confirming two-factor enrollment for user {}", caller.user_id);
+    let codes = confirm_enrollment(store, &caller.user_id, &confirmation.code).await?;
+    Ok(Json(codes))
+}
+
+/// Disables two-factor authentication. Requires the password and a current
+/// code (or recovery code), not just an access token, checked like a login
+/// including its lockout.
+#[openapi]
+#[post("/auth/2fa/disable", data = "<request>")]
+pub async fn disable_two_factor(
+    store: &State<AuthStore>,
+    lockout: &State<LockoutConfig>,
+    client_ip: Option<IpAddr>,
+    request: Json<TotpDisableRequest>,
+    caller: AuthenticatedUser
+) -> Result<Status, AppError> {
+    info!("Disabling two-factor authentication for user {}", caller.user_id);
+    let request = request.into_inner();
+    let Some(credentials) = store.find_credentials_by_id(&caller.user_id).await? else {
+        error!("Rejected two-factor disable for unknown user {}", caller.user_id);
+        return Err(invalid_credentials());
+    };
+    let email = normalize_email(&credentials.email);
+    ensure_login_allowed(store, lockout, &email, client_ip).await?;
+
+    let password_hash = credentials.password_hash.as_ref().map(|h| h.expose());
+    let authenticated = if verify_password(&request.password, password_hash).await? {
+        if !credentials.totp_enabled {
+            return Err(AppError::Conflict("Two-factor authentication is not enabled".to_string()));
+        }
+        verify_second_factor(store, &credentials, Some(&request.code)).await
+    } else {
+        Err(invalid_credentials())
+    };
+
+    if let Err(e) = authenticated {
+        error!("Rejected two-factor disable for user {}", caller.user_id);
+        if matches!(e, AppError::Unauthorized(_)) {
+            record_failed_login(store, lockout, &email, client_ip).await?;
+        }
+        return Err(e);
+    }
+
+    store.disable_totp(&credentials.user_id).await?;
+    info!("Disabled two-factor authentication for user {}", credentials.user_id);
+    Ok(Status::NoContent)
+}
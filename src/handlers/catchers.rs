@@ -0,0 +1,23 @@
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::Request;
+use serde_json::{json, Value};
+
+use crate::errors::app_error::GuardFailure;
+
+/// Renders errors raised before a handler runs (unparsable bodies, unknown
+/// routes, ...) in the same JSON shape as `AppError`.
+#[catch(default)]
+pub fn json_catcher(status: Status, req: &Request) -> (Status, Json<Value>) {
+    let guard_message = req.local_cache(|| GuardFailure(None)).0.as_deref();
+    let message = match (guard_message, status.code) {
+        (Some(message), _) => message,
+        (None, 422) => "Request body is malformed or missing required fields",
+        (None, _) => status.reason_lossy(),
+    };
+
+    (status, Json(json!({
+        "error": status.to_string(),
+        "message": message
+    })))
+}
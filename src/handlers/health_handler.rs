@@ -0,0 +1,22 @@
+use rocket::serde::json::Json;
+use rocket::{State, http::Status};
+
+use crate::config::health_config::HealthConfig;
+use crate::models::health::{Liveness, Readiness};
+use crate::services::health_service::{readiness, Dependencies};
+
+/// Liveness probe: answers as long as the server can handle requests, and
+/// never looks at the databases, so an outage does not restart the service.
+#[get("/live")]
+pub fn live() -> Json<Liveness> {
+    Json(Liveness { status: "alive" })
+}
+
+/// Readiness probe: 503 while a required database is down, so traffic is
+/// routed elsewhere until it is back.
+#[get("/ready")]
+pub async fn ready(dependencies: Dependencies, config: &State<HealthConfig>) -> (Status, Json<Readiness>) {
+    let readiness = readiness(dependencies, config).await;
+    let status = if readiness.is_ready() { Status::Ok } else { Status::ServiceUnavailable };
+    (status, Json(readiness))
+}
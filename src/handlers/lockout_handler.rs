@@ -0,0 +1,72 @@
+use std::net::IpAddr;
+
+use rocket::serde::json::Json;
+use rocket::{ State, http::Status };
+use rocket_okapi::openapi;
+use log::info;
+
+use crate::errors::app_error::AppError;
+use crate::models::lockout::{LockoutScope, LockoutStatus};
+use crate::repositories::auth_repository::AuthStore;
+use crate::utils::authentication::{CanManageLockouts, Permitted};
+
+/// Failed logins are counted against the email, so look it up from the id.
+async fn user_email(store: &AuthStore, user_id: &str) -> Result<String, AppError> {
+    store.find_credentials_by_id(user_id).await?
+        .map(|credentials| credentials.email)
+        .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", user_id)))
+}
+
+/// Shows recent failed logins for a user and whether their account is locked.
+#[openapi]
+#[get("/auth/users/<user_id>/lockout")]
+pub async fn get_user_lockout(
+    store: &State<AuthStore>,
+    user_id: String,
+    _caller: Permitted<CanManageLockouts>
+) -> Result<Json<LockoutStatus>, AppError> {
+    info!("Fetching lockout status of user {}", user_id);
+    let email = user_email(store, &user_id).await?;
+    let failures = store.find_login_failures(LockoutScope::Account, &email).await?;
+    Ok(Json(failures.into()))
+}
+
+/// Unlocks a user's account and forgets its failed logins.
+#[openapi]
+#[delete("/auth/users/<user_id>/lockout")]
+pub async fn unlock_user(
+    store: &State<AuthStore>,
+    user_id: String,
+    caller: Permitted<CanManageLockouts>
+) -> Result<Status, AppError> {
+    info!("Unlocking user {} on behalf of {}", user_id, caller.id());
+    let email = user_email(store, &user_id).await?;
+    store.clear_login_failures(LockoutScope::Account, &email).await?;
+    Ok(Status::NoContent)
+}
+
+/// Shows recent failed logins from a client address and whether it is locked.
+#[openapi]
+#[get("/auth/lockouts/ips/<ip>")]
+pub async fn get_ip_lockout(
+    store: &State<AuthStore>,
+    ip: IpAddr,
+    _caller: Permitted<CanManageLockouts>
+) -> Result<Json<LockoutStatus>, AppError> {
+    info!("Fetching lockout status of {}", ip);
+    let failures = store.find_login_failures(LockoutScope::Ip, &ip.to_string()).await?;
+    Ok(Json(failures.into()))
+}
+
+/// Unlocks a client address and forgets its failed logins.
+#[openapi]
+#[delete("/auth/lockouts/ips/<ip>")]
+pub async fn unlock_ip(
+    store: &State<AuthStore>,
+    ip: IpAddr,
+    caller: Permitted<CanManageLockouts>
+) -> Result<Status, AppError> {
+    info!("Unlocking {} on behalf of {}", ip, caller.id());
+    store.clear_login_failures(LockoutScope::Ip, &ip.to_string()).await?;
+    Ok(Status::NoContent)
+}
@@ -1,5 +1,15 @@
+#[cfg(feature = "postgres")]
 pub mod user_handler;
+#[cfg(feature = "mongo")]
 pub mod mongo_user_handler;
+pub mod catchers;
+pub mod auth_handler;
+pub mod api_key_handler;
+pub mod verification_handler;
+pub mod lockout_handler;
+pub mod oidc_handler;
+pub mod oauth_handler;
+pub mod health_handler;
 
 use rocket::get;
 
@@ -1,21 +1,35 @@
 use rocket::serde::json::Json;
 use rocket::{ State, http::Status };
+use rocket::response::status::Created;
 use mongodb::Database;
 use rocket_okapi::openapi;
 use log::{info, error};
 
-use crate::models::user::UserMongo;
-use crate::services::user_service;
+use crate::models::pagination::{Page, UserListQuery};
+use crate::models::user::{UserMongo, UserPatch};
+use crate::models::role::Permission;
+use crate::db::mongo::parse_object_id;
 use crate::errors::app_error::AppError;
+use crate::services::password_service::hash_new_password;
+use crate::utils::authentication::{Caller, CanListUsers, Permitted};
+use crate::utils::etag::{Conditional, IfMatch, IfNoneMatch, Tagged};
+use crate::utils::merge_patch::MergePatch;
+use crate::utils::validation::validated;
+use crate::repositories::user_repository::UserRepository;
 
 #[openapi]
 #[post("/v2/users", data = "<user>")]
-pub async fn adding_user(db: &State<Database>, user: Json<UserMongo>) -> Result<Json<UserMongo>, AppError> {
+pub async fn adding_user(db: &State<Database>, user: Json<UserMongo>) -> Result<Tagged<Created<Json<UserMongo>>>, AppError> {
     info!("Adding new user: {:?}", user);
-    match user_service::add_user(db, user.into_inner()).await {
+    let mut user = validated(user.into_inner())?;
+    let password_hash = hash_new_password(user.password.take(), &user.email).await?;
+    match db.create(user, password_hash).await {
         Ok(added_user) => {
             info!("User added successfully: {:?}", added_user);
-            Ok(Json(added_user))
+            let id = added_user.id.map(|id| id.to_hex()).unwrap_or_default();
+            let location = uri!("/mongo", getting_user(id = id));
+            let version = added_user.version.unwrap_or_default();
+            Ok(Tagged::new(version, Created::new(location.to_string()).body(Json(added_user))))
         }
         Err(e) => {
             error!("Failed to add user: {:?}", e);
@@ -25,12 +39,12 @@ pub async fn adding_user(db: &State<Database>, user: Json<UserMongo>) -> Result<
 }
 
 #[openapi]
-#[get("/v2/users")]
-pub async fn getting_users(db: &State<Database>) -> Result<Json<Vec<UserMongo>>, AppError> {
-    info!("Fetching all users");
-    match user_service::get_users(db).await {
+#[get("/v2/users?<query..>")]
+pub async fn getting_users(db: &State<Database>, query: UserListQuery, _caller: Permitted<CanListUsers>) -> Result<Json<Page<UserMongo>>, AppError> {
+    info!("Fetching users: {:?}", query);
+    match db.list(&query.into_options()?).await {
         Ok(users) => {
-            info!("Successfully fetched {} users", users.len());
+            info!("Successfully fetched {} of {} users", users.items.len(), users.total);
             Ok(Json(users))
         }
         Err(e) => {
@@ -40,14 +54,46 @@ pub async fn getting_users(db: &State<Database>) -> Result<Json<Vec<UserMongo>>,
     }
 }
 
+#[openapi]
+#[get("/v2/users/<id>")]
+pub async fn getting_user(
+    db: &State<Database>,
+    id: String,
+    if_none_match: IfNoneMatch,
+    caller: Caller
+) -> Result<Conditional<Json<UserMongo>>, AppError> {
+    info!("Fetching user with id: {}", id);
+    caller.require_self_or(&id, Permission::ReadAnyUser)?;
+    let id = parse_object_id(&id)?;
+    match db.get(id).await {
+        Ok(user) => {
+            info!("Successfully fetched user: {:?}", user);
+            Ok(if_none_match.respond(user.version.unwrap_or_default(), Json(user)))
+        }
+        Err(e) => {
+            error!("Failed to fetch user: {:?}", e);
+            Err(e)
+        }
+    }
+}
+
 #[openapi]
 #[put("/v2/users/<id>", data = "<user>")]
-pub async fn updating_user(db: &State<Database>, id: String, user: Json<UserMongo>) -> Result<Json<UserMongo>, AppError> {
+pub async fn updating_user(
+    db: &State<Database>,
+    id: String,
+    user: Json<UserMongo>,
+    if_match: IfMatch,
+    caller: Caller
+) -> Result<Tagged<Json<UserMongo>>, AppError> {
     info!("Updating user with id: {}", id);
-    match user_service::update_user(db, id, user.into_inner()).await {
+    caller.require_self_or(&id, Permission::WriteAnyUser)?;
+    let id = parse_object_id(&id)?;
+    let user = validated(user.into_inner())?;
+    match db.update(id, user, if_match.expected_versions()).await {
         Ok(updated_user) => {
             info!("User updated successfully: {:?}", updated_user);
-            Ok(Json(updated_user))
+            Ok(Tagged::new(updated_user.version.unwrap_or_default(), Json(updated_user)))
         }
         Err(e) => {
             error!("Failed to update user: {:?}", e);
@@ -56,14 +102,42 @@ pub async fn updating_user(db: &State<Database>, id: String, user: Json<UserMong
     }
 }
 
+/// Applies an RFC 7396 JSON Merge Patch: only the fields present in the body change.
+#[openapi]
+#[patch("/v2/users/<id>", data = "<patch>")]
+pub async fn patching_user(
+    db: &State<Database>,
+    id: String,
+    patch: MergePatch<UserPatch>,
+    if_match: IfMatch,
+    caller: Caller
+) -> Result<Tagged<Json<UserMongo>>, AppError> {
+    info!("Patching user with id: {}", id);
+    caller.require_self_or(&id, Permission::WriteAnyUser)?;
+    let id = parse_object_id(&id)?;
+    let patch = validated(patch.into_inner()?)?;
+    match db.patch(id, patch, if_match.expected_versions()).await {
+        Ok(patched_user) => {
+            info!("User patched successfully: {:?}", patched_user);
+            Ok(Tagged::new(patched_user.version.unwrap_or_default(), Json(patched_user)))
+        }
+        Err(e) => {
+            error!("Failed to patch user: {:?}", e);
+            Err(e)
+        }
+    }
+}
+
 #[openapi]
 #[delete("/v2/users/<id>")]
-pub async fn deleting_user(db: &State<Database>, id: String) -> Result<Status, AppError> {
+pub async fn deleting_user(db: &State<Database>, id: String, if_match: IfMatch, caller: Caller) -> Result<Status, AppError> {
     info!("Deleting user with id: {}", id);
-    match user_service::delete_user(db, id).await {
+    caller.require_self_or(&id, Permission::DeleteAnyUser)?;
+    let id = parse_object_id(&id)?;
+    match db.delete(id, if_match.expected_versions()).await {
         Ok(_) => {
             info!("User deleted successfully");
-            Ok(Status::Ok)
+            Ok(Status::NoContent)
         }
         Err(e) => {
             error!("Failed to delete user: {:?}", e);
@@ -0,0 +1,169 @@
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::status::Created;
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::Responses;
+use rocket_okapi::openapi;
+use rocket_okapi::response::OpenApiResponderInner;
+use serde_json::Value;
+use log::info;
+
+use crate::config::jwt_config::JwtConfig;
+use crate::config::oauth_provider_config::OAuthProviderConfig;
+use crate::errors::app_error::AppError;
+use crate::errors::oauth_error::OAuthError;
+use crate::models::oauth::{
+    AuthorizationRedirect, AuthorizationRequest, CreatedOAuthClient, NewOAuthClient, OAuthClient,
+    ProviderConfiguration, TokenRequest, TokenResponse, UserInfo, OPENID_SCOPES,
+};
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::oauth_service::{self, create_client, ensure_provider_enabled, issue_token, user_info};
+use crate::utils::authentication::{AuthenticatedUser, CanManageOAuthClients, ClientSecretBasic, OAuthBearer, Permitted};
+use crate::utils::validation::validated;
+
+/// Adds `Cache-Control: no-store`, required on responses carrying tokens.
+pub struct NoStore<R>(R);
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for NoStore<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = self.0.respond_to(req)?;
+        response.set_raw_header("Cache-Control", "no-store");
+        Ok(response)
+    }
+}
+
+impl<R: OpenApiResponderInner> OpenApiResponderInner for NoStore<R> {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        R::responses(gen)
+    }
+}
+
+/// OpenID Connect discovery document for relying parties.
+#[openapi]
+#[get("/.well-known/openid-configuration")]
+pub async fn openid_configuration(
+    jwt: &State<JwtConfig>,
+    config: &State<OAuthProviderConfig>
+) -> Result<Json<ProviderConfiguration>, AppError> {
+    ensure_provider_enabled(jwt)?;
+    Ok(Json(ProviderConfiguration {
+        issuer: config.issuer.clone(),
+        authorization_endpoint: config.authorization_page_url.clone(),
+        token_endpoint: config.endpoint("/oauth/token"),
+        userinfo_endpoint: config.endpoint("/oauth/userinfo"),
+        jwks_uri: config.endpoint("/.well-known/jwks.json"),
+        response_types_supported: vec!["code"],
+        grant_types_supported: vec!["authorization_code", "client_credentials"],
+        subject_types_supported: vec!["public"],
+        id_token_signing_alg_values_supported: vec!["RS256"],
+        scopes_supported: OPENID_SCOPES.to_vec(),
+        token_endpoint_auth_methods_supported: vec!["client_secret_basic", "client_secret_post", "none"],
+        code_challenge_methods_supported: vec!["S256"],
+        claims_supported: vec!["iss", "sub", "aud", "iat", "exp", "nonce", "name", "email", "email_verified"],
+    }))
+}
+
+/// Public keys tokens are signed with, including recently rotated ones.
+#[openapi]
+#[get("/.well-known/jwks.json")]
+pub async fn jwks(jwt: &State<JwtConfig>) -> Result<Json<Value>, AppError> {
+    ensure_provider_enabled(jwt)?;
+    serde_json::to_value(jwt.jwks())
+        .map(Json)
+        .map_err(|e| AppError::InternalServerError(format!("Failed to serialize JWKS: {}", e)))
+}
+
+/// Called by the authorization page once the user has signed in and agreed,
+/// with the query the client sent them with. Returns where to send the browser.
+#[openapi]
+#[post("/oauth/authorize", data = "<request>")]
+pub async fn authorize(
+    store: &State<AuthStore>,
+    jwt: &State<JwtConfig>,
+    config: &State<OAuthProviderConfig>,
+    request: Json<AuthorizationRequest>,
+    user: AuthenticatedUser
+) -> Result<Json<AuthorizationRedirect>, AppError> {
+    info!("User {} authorizing client {}", user.user_id, request.client_id);
+    ensure_provider_enabled(jwt)?;
+    let redirect_to = oauth_service::authorize(store, config, &user.user_id, request.into_inner()).await?;
+    Ok(Json(AuthorizationRedirect { redirect_to }))
+}
+
+/// Exchanges an authorization code, or client credentials, for tokens.
+#[openapi]
+#[post("/oauth/token", data = "<request>")]
+pub async fn token(
+    store: &State<AuthStore>,
+    jwt: &State<JwtConfig>,
+    config: &State<OAuthProviderConfig>,
+    basic: Option<ClientSecretBasic>,
+    request: Form<TokenRequest>
+) -> Result<NoStore<Json<TokenResponse>>, OAuthError> {
+    info!("Token request with grant_type {}", request.grant_type);
+    let response = issue_token(store, jwt, config, basic, request.into_inner()).await?;
+    Ok(NoStore(Json(response)))
+}
+
+/// Claims about the user an OAuth access token was issued for.
+#[openapi]
+#[get("/oauth/userinfo")]
+pub async fn userinfo(store: &State<AuthStore>, caller: OAuthBearer) -> Result<Json<UserInfo>, AppError> {
+    let credentials = store.find_credentials_by_id(&caller.claims.sub).await?
+        .ok_or_else(|| AppError::Unauthorized("The user no longer exists".to_string()))?;
+    Ok(Json(user_info(&credentials, &caller.claims.scope)))
+}
+
+/// Registers an OAuth client. The response is the only time its secret is shown.
+#[openapi]
+#[post("/oauth/clients", data = "<new_client>")]
+pub async fn add_client(
+    store: &State<AuthStore>,
+    new_client: Json<NewOAuthClient>,
+    caller: Permitted<CanManageOAuthClients>
+) -> Result<Created<Json<CreatedOAuthClient>>, AppError> {
+    info!("Registering OAuth client {:?} on behalf of {}", new_client, caller.id());
+    let new_client = validated(new_client.into_inner())?;
+    let created = create_client(store, new_client).await?;
+    info!("OAuth client {} registered", created.client.client_id);
+    Ok(Created::new(format!("/oauth/clients/{}", created.client.client_id)).body(Json(created)))
+}
+
+#[openapi]
+#[get("/oauth/clients")]
+pub async fn get_clients(
+    store: &State<AuthStore>,
+    _caller: Permitted<CanManageOAuthClients>
+) -> Result<Json<Vec<OAuthClient>>, AppError> {
+    info!("Fetching OAuth clients");
+    Ok(Json(store.list_oauth_clients().await?))
+}
+
+#[openapi]
+#[get("/oauth/clients/<client_id>")]
+pub async fn get_client(
+    store: &State<AuthStore>,
+    client_id: String,
+    _caller: Permitted<CanManageOAuthClients>
+) -> Result<Json<OAuthClient>, AppError> {
+    store.find_oauth_client(&client_id).await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("OAuth client not found".to_string()))
+}
+
+/// Deletes a client. Tokens already issued to it stay valid until they expire.
+#[openapi]
+#[delete("/oauth/clients/<client_id>")]
+pub async fn delete_client(
+    store: &State<AuthStore>,
+    client_id: String,
+    caller: Permitted<CanManageOAuthClients>
+) -> Result<Status, AppError> {
+    info!("Deleting OAuth client {} on behalf of {}", client_id, caller.id());
+    store.delete_oauth_client(&client_id).await?;
+    Ok(Status::NoContent)
+}
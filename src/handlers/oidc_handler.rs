@@ -0,0 +1,71 @@
+use rocket::response::Redirect;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_okapi::openapi;
+use log::{info, warn};
+
+use crate::config::jwt_config::JwtConfig;
+use crate::config::oidc_config::OidcConfig;
+use crate::errors::app_error::AppError;
+use crate::models::auth::LoginResponse;
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::oidc_client::OidcClient;
+use crate::services::oidc_service::{begin_oidc_login, complete_oidc_login};
+use crate::services::session_service::start_session;
+use crate::utils::secret::Secret;
+
+/// Names of the identity providers users can sign in with.
+#[openapi]
+#[get("/auth/oidc/providers")]
+pub async fn get_providers(config: &State<OidcConfig>) -> Json<Vec<String>> {
+    Json(config.providers.iter().map(|provider| provider.name.clone()).collect())
+}
+
+/// Starts signing in with an identity provider by redirecting to it.
+#[openapi]
+#[get("/auth/oidc/<provider>/authorize")]
+pub async fn authorize(
+    store: &State<AuthStore>,
+    client: &State<OidcClient>,
+    config: &State<OidcConfig>,
+    provider: String
+) -> Result<Redirect, AppError> {
+    info!("Starting login with identity provider {}", provider);
+    let url = begin_oidc_login(store, client, config, &provider).await?;
+    Ok(Redirect::to(url))
+}
+
+/// Where the identity provider sends the browser back. On success the user,
+/// created on first login, gets a session like with `POST /auth/login`.
+#[openapi]
+#[get("/auth/oidc/<provider>/callback?<code>&<state>&<error>&<error_description>")]
+pub async fn callback(
+    store: &State<AuthStore>,
+    client: &State<OidcClient>,
+    config: &State<OidcConfig>,
+    jwt: &State<JwtConfig>,
+    provider: String,
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>
+) -> Result<Json<LoginResponse>, AppError> {
+    info!("Completing login with identity provider {}", provider);
+    if let Some(error) = error {
+        warn!("Identity provider {} returned {}: {:?}", provider, error, error_description);
+        return Err(AppError::Unauthorized(format!("Identity provider returned an error: {}", error)));
+    }
+    let (Some(code), Some(state)) = (code.map(Secret::new), state) else {
+        return Err(AppError::BadRequest("code and state are required".to_string()));
+    };
+
+    let credentials = complete_oidc_login(store, client, config, &provider, code.expose(), &state).await?;
+    info!("User {} logged in with identity provider {}", credentials.user_id, provider);
+    let token = start_session(store, jwt, &credentials).await?;
+    Ok(Json(LoginResponse {
+        user_id: credentials.user_id,
+        name: credentials.name,
+        email: credentials.email,
+        token,
+    }))
+}
@@ -1,48 +1,78 @@
 use rocket::serde::json::Json;
 use rocket::http::Status;
+use rocket::response::status::Created;
 use rocket_okapi::openapi;
 use log::{info, error};
 
-use crate::models::user::User;
-use crate::db::postgres::{get_users_from_db, execute_query};
+use crate::models::pagination::{Page, UserListQuery};
+use crate::models::user::{User, UserPatch};
+use crate::models::role::Permission;
 use crate::db::postgres::DbClient;
 use crate::errors::app_error::AppError;
+use crate::services::password_service::hash_new_password;
+use crate::utils::authentication::{Caller, CanListUsers, Permitted};
+use crate::utils::etag::{Conditional, IfMatch, IfNoneMatch, Tagged};
+use crate::utils::merge_patch::MergePatch;
+use crate::utils::validation::validated;
+use crate::repositories::user_repository::UserRepository;
 
 #[openapi]
 #[post("/users", data = "<user>")]
 pub async fn add_user(
     conn: &DbClient,
     user: Json<User>
-) -> Result<Json<Vec<User>>, AppError> {
+) -> Result<Tagged<Created<Json<User>>>, AppError> {
     info!("Adding new user: {:?}", user);
-    match execute_query(
-        conn,
-        "INSERT INTO users (name, email) VALUES ($1, $2)",
-        &[&user.name, &user.email]
-    ).await {
-        Ok(_) => {
-            info!("User added successfully");
-            get_users(conn).await
+    let mut user = validated(user.into_inner())?;
+    let password_hash = hash_new_password(user.password.take(), &user.email).await?;
+    match conn.create(user, password_hash).await {
+        Ok(added_user) => {
+            info!("User added successfully: {:?}", added_user);
+            let location = uri!("/postgres", get_user(id = added_user.id.unwrap_or_default()));
+            let version = added_user.version.unwrap_or_default();
+            Ok(Tagged::new(version, Created::new(location.to_string()).body(Json(added_user))))
         }
         Err(e) => {
             error!("Failed to add user: {:?}", e);
-            Err(e.into())
+            Err(e)
         }
     }
 }
 
 #[openapi]
-#[get("/users")]
-pub async fn get_users(conn: &DbClient) -> Result<Json<Vec<User>>, AppError> {
-    info!("Fetching all users");
-    match get_users_from_db(conn).await {
+#[get("/users?<query..>")]
+pub async fn get_users(conn: &DbClient, query: UserListQuery, _caller: Permitted<CanListUsers>) -> Result<Json<Page<User>>, AppError> {
+    info!("Fetching users: {:?}", query);
+    match conn.list(&query.into_options()?).await {
         Ok(users) => {
-            info!("Successfully fetched {} users", users.len());
+            info!("Successfully fetched {} of {} users", users.items.len(), users.total);
             Ok(Json(users))
         }
         Err(e) => {
             error!("Failed to fetch users: {:?}", e);
-            Err(e.into())
+            Err(e)
+        }
+    }
+}
+
+#[openapi]
+#[get("/users/<id>")]
+pub async fn get_user(
+    conn: &DbClient,
+    id: i32,
+    if_none_match: IfNoneMatch,
+    caller: Caller
+) -> Result<Conditional<Json<User>>, AppError> {
+    info!("Fetching user with id: {}", id);
+    caller.require_self_or(&id.to_string(), Permission::ReadAnyUser)?;
+    match UserRepository::get(conn.inner(), id).await {
+        Ok(user) => {
+            info!("Successfully fetched user: {:?}", user);
+            Ok(if_none_match.respond(user.version.unwrap_or_default(), Json(user)))
+        }
+        Err(e) => {
+            error!("Failed to fetch user: {:?}", e);
+            Err(e)
         }
     }
 }
@@ -52,37 +82,63 @@ pub async fn get_users(conn: &DbClient) -> Result<Json<Vec<User>>, AppError> {
 pub async fn update_user(
     conn: &DbClient,
     id: i32,
-    user: Json<User>
-) -> Result<Json<Vec<User>>, AppError> {
+    user: Json<User>,
+    if_match: IfMatch,
+    caller: Caller
+) -> Result<Tagged<Json<User>>, AppError> {
     info!("Updating user with id: {}", id);
-    match execute_query(
-        conn,
-        "UPDATE users SET name = $1, email = $2 WHERE id = $3",
-        &[&user.name, &user.email, &id]
-    ).await {
-        Ok(_) => {
-            info!("User updated successfully");
-            get_users(conn).await
+    caller.require_self_or(&id.to_string(), Permission::WriteAnyUser)?;
+    let user = validated(user.into_inner())?;
+    match conn.update(id, user, if_match.expected_versions()).await {
+        Ok(updated_user) => {
+            info!("User updated successfully: {:?}", updated_user);
+            Ok(Tagged::new(updated_user.version.unwrap_or_default(), Json(updated_user)))
         }
         Err(e) => {
             error!("Failed to update user: {:?}", e);
-            Err(e.into())
+            Err(e)
+        }
+    }
+}
+
+/// Applies an RFC 7396 JSON Merge Patch: only the fields present in the body change.
+#[openapi]
+#[patch("/users/<id>", data = "<patch>")]
+pub async fn patch_user(
+    conn: &DbClient,
+    id: i32,
+    patch: MergePatch<UserPatch>,
+    if_match: IfMatch,
+    caller: Caller
+) -> Result<Tagged<Json<User>>, AppError> {
+    info!("Patching user with id: {}", id);
+    caller.require_self_or(&id.to_string(), Permission::WriteAnyUser)?;
+    let patch = validated(patch.into_inner()?)?;
+    match conn.patch(id, patch, if_match.expected_versions()).await {
+        Ok(patched_user) => {
+            info!("User patched successfully: {:?}", patched_user);
+            Ok(Tagged::new(patched_user.version.unwrap_or_default(), Json(patched_user)))
+        }
+        Err(e) => {
+            error!("Failed to patch user: {:?}", e);
+            Err(e)
         }
     }
 }
 
 #[openapi]
 #[delete("/users/<id>")]
-pub async fn delete_user(conn: &DbClient, id: i32) -> Result<Status, AppError> {
+pub async fn delete_user(conn: &DbClient, id: i32, if_match: IfMatch, caller: Caller) -> Result<Status, AppError> {
     info!("Deleting user with id: {}", id);
-    match execute_query(conn, "DELETE FROM users WHERE id = $1", &[&id]).await {
+    caller.require_self_or(&id.to_string(), Permission::DeleteAnyUser)?;
+    match conn.delete(id, if_match.expected_versions()).await {
         Ok(_) => {
             info!("User deleted successfully");
             Ok(Status::NoContent)
         }
         Err(e) => {
             error!("Failed to delete user: {:?}", e);
-            Err(e.into())
+            Err(e)
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,38 @@
+use rocket::serde::json::Json;
+use rocket::{ State, http::Status };
+use rocket_okapi::openapi;
+use log::info;
+
+use crate::config::mail_config::MailConfig;
+use crate::models::auth::EmailVerified;
+use crate::models::role::Permission;
+use crate::errors::app_error::AppError;
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::mailer::SharedMailer;
+use crate::services::verification_service::{confirm_email, request_email_verification};
+use crate::utils::authentication::Caller;
+use crate::utils::secret::Secret;
+
+/// Mails a verification link to the user's current address.
+#[openapi]
+#[post("/users/<id>/verify-email/request")]
+pub async fn request_verification(
+    store: &State<AuthStore>,
+    mailer: &State<SharedMailer>,
+    mail_config: &State<MailConfig>,
+    id: String,
+    caller: Caller
+) -> Result<Status, AppError> {
+    info!("Email verification requested for user {} by {}", id, caller.id());
+    caller.require_self_or(&id, Permission::WriteAnyUser)?;
+    request_email_verification(store, mailer, mail_config, &id).await?;
+    Ok(Status::Accepted)
+}
+
+/// Target of the link sent by email.
+#[openapi]
+#[get("/verify-email?<token>")]
+pub async fn verify_email(store: &State<AuthStore>, token: String) -> Result<Json<EmailVerified>, AppError> {
+    info!("Verifying email with token {:?}", Secret::new(token.as_str()));
+    Ok(Json(confirm_email(store, &token).await?))
+}
@@ -11,45 +11,119 @@ mod routes;
 mod services;
 mod errors;
 mod openapi;
+mod repositories;
+mod cli;
+mod utils;
+
+#[cfg(not(any(feature = "postgres", feature = "mongo")))]
+compile_error!("enable at least one database backend: the `postgres` or `mongo` feature");
 
 use rocket_okapi::swagger_ui::make_swagger_ui;
-use routes::user_routes::{user_routes, user_mongo_routes};
-use config::{cors::cors_configuration, app_config::AppConfig};
+#[cfg(feature = "postgres")]
+use routes::user_routes::user_routes;
+#[cfg(feature = "mongo")]
+use routes::user_routes::user_mongo_routes;
+use config::{app_config::AppConfig, settings::Settings};
+use services::mailer::build_mailer;
+use services::oidc_client::OidcClient;
 use openapi::swagger_ui::{openapi_routes, swagger_ui};
-use handlers::hello;
+use handlers::{hello, health_handler, catchers::json_catcher};
 use env_logger::Env;
+use rocket::{Build, Rocket};
 
-#[launch]
-async fn rocket() -> _ {
+#[rocket::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
   // Initialize the logger
   env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  match args.first().map(String::as_str) {
+    Some("migrate") => return cli::migrate::run(&args[1..]).await,
+    Some("roles") => return cli::roles::run(&args[1..]).await,
+    _ => {}
+  }
+
+  let rocket_instance = rocket().await?;
+  let _ = rocket_instance.launch().await?;
+  Ok(())
+}
+
+/// Builds the server from validated settings. Startup failures are returned,
+/// and reported by `main`, rather than panicking halfway through.
+async fn rocket() -> Result<Rocket<Build>, Box<dyn std::error::Error>> {
   info!("Starting application...");
 
-  let app_config = match AppConfig::new().await {
-    Ok(config) => config,
-    Err(e) => {
-      error!("Failed to initialize application config: {}", e);
-      panic!("Application startup failed");
-    }
-  };
+  let settings = Settings::load().map_err(|e| {
+    error!("Failed to load settings: {}", e);
+    e
+  })?;
+  info!("Loaded settings for the {} environment", settings.environment);
+
+  let app_config = AppConfig::new(&settings.database).await.map_err(|e| {
+    error!("Failed to initialize application config: {}", e);
+    e
+  })?;
+
+  let mailer = build_mailer(&settings.mail).map_err(|e| {
+    error!("Failed to set up the mailer: {}", e);
+    e
+  })?;
+
+  let oidc_client = OidcClient::new().map_err(|e| {
+    error!("Failed to set up the OIDC client: {}", e);
+    e
+  })?;
 
   info!("Application config initialized successfully");
 
-  let rocket_instance = rocket::build()
-    .manage(app_config.postgres_client)
-    .manage(app_config.mongo_db)
-    .mount("/health", routes![hello])
-    .mount("/postgres", user_routes())
-    .mount("/mongo", user_mongo_routes())
-    .mount("/", openapi_routes())
+  let openapi_routes = openapi_routes(&app_config);
+  let mut rocket_instance = rocket::build();
+
+  #[cfg(feature = "postgres")]
+  if let Some(postgres_pool) = app_config.postgres_pool {
+    rocket_instance = rocket_instance
+      .manage(postgres_pool)
+      .mount("/postgres", user_routes());
+  }
+
+  #[cfg(feature = "mongo")]
+  if let Some(mongo_db) = app_config.mongo_db {
+    rocket_instance = rocket_instance
+      .manage(mongo_db)
+      .mount("/mongo", user_mongo_routes());
+  }
+
+  let rocket_instance = rocket_instance
+    .manage(app_config.auth_store)
+    .manage(app_config.pending_migrations)
+    .manage(settings.jwt)
+    .manage(settings.mail)
+    .manage(mailer)
+    .manage(settings.lockout)
+    .manage(settings.oidc)
+    .manage(oidc_client)
+    .manage(settings.oauth_provider)
+    .manage(settings.health)
+    .mount("/health", routes![hello, health_handler::live, health_handler::ready])
+    .mount("/", openapi_routes)
     .mount("/doc", make_swagger_ui(&swagger_ui()))
-    .attach(cors_configuration())
+    .register("/", catchers![json_catcher])
+    .attach(settings.cors)
     .configure(rocket::Config::figment()
-    .merge(("port", std::env::var("PORT").unwrap_or_else(|_| "8000".to_string()).parse::<u16>().unwrap()))
-    .merge(("address", "0.0.0.0")));
+    .merge(("port", settings.port))
+    .merge(("address", "0.0.0.0"))
+    .merge(("ip_header", client_ip_header(settings.client_ip_header))));
 
   info!("Rocket instance configured, ready to launch!");
 
-  rocket_instance
-}
\ No newline at end of file
+  Ok(rocket_instance)
+}
+
+/// Rocket's `ip_header`: the configured header, or `false` to ignore
+/// `X-Real-IP`, which Rocket trusts by default.
+fn client_ip_header(header: Option<String>) -> rocket::figment::value::Value {
+  match header {
+    Some(header) => header.into(),
+    None => false.into(),
+  }
+}
@@ -0,0 +1,63 @@
+use std::time::SystemTime;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::role::Permission;
+use crate::utils::timestamp;
+use crate::utils::validation::Normalize;
+
+#[derive(Debug, Deserialize, JsonSchema, Validate)]
+pub struct NewApiKey {
+    #[validate(length(min = 1, max = 100, message = "name must be between 1 and 100 characters"))]
+    pub name: String,
+    /// Permissions granted to the key; without any it can only reach open routes.
+    #[serde(default)]
+    pub scopes: Vec<Permission>,
+    /// The key never expires when omitted.
+    #[validate(range(min = 1, max = 3650, message = "expires_in_days must be between 1 and 3650"))]
+    pub expires_in_days: Option<u32>,
+}
+
+impl Normalize for NewApiKey {
+    fn normalize(mut self) -> Self {
+        self.name = self.name.trim().to_string();
+        self
+    }
+}
+
+/// An API key as listed to admins. The key itself is only stored hashed;
+/// `prefix` is enough to tell keys apart.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<Permission>,
+    #[serde(serialize_with = "timestamp::serialize")]
+    #[schemars(with = "String")]
+    pub created_at: SystemTime,
+    #[serde(serialize_with = "timestamp::serialize_option")]
+    #[schemars(with = "Option<String>")]
+    pub expires_at: Option<SystemTime>,
+    #[serde(serialize_with = "timestamp::serialize_option")]
+    #[schemars(with = "Option<String>")]
+    pub last_used_at: Option<SystemTime>,
+    pub revoked: bool,
+}
+
+impl ApiKey {
+    pub fn is_usable(&self) -> bool {
+        !self.revoked && self.expires_at.is_none_or(|expires_at| expires_at > SystemTime::now())
+    }
+}
+
+/// Returned once, when the key is created; the secret cannot be retrieved later.
+#[derive(Serialize, JsonSchema)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    /// Send as the `X-Api-Key` header.
+    pub api_key: String,
+}
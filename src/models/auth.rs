@@ -0,0 +1,175 @@
+use std::time::SystemTime;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::role::Role;
+use crate::utils::secret::Secret;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LoginRequest {
+    pub email: String,
+    pub password: Secret,
+    /// Current TOTP code or an unused recovery code, required once 2FA is enabled.
+    #[serde(default)]
+    pub totp_code: Option<Secret>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LoginResponse {
+    pub user_id: String,
+    pub name: String,
+    pub email: String,
+    #[serde(flatten)]
+    pub token: AccessToken,
+}
+
+/// A signed JWT to send as `Authorization: Bearer <access_token>`.
+#[derive(Serialize, JsonSchema)]
+pub struct AccessToken {
+    pub access_token: String,
+    pub token_type: String,
+    /// Lifetime in seconds.
+    pub expires_in: u64,
+    /// Opaque token to exchange at `POST /auth/refresh` once the access token expires.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+impl std::fmt::Debug for AccessToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccessToken")
+            .field("token_type", &self.token_type)
+            .field("expires_in", &self.expires_in)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Claims carried by an access token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub email: String,
+    #[serde(default)]
+    pub roles: Vec<Role>,
+    pub iss: String,
+    pub aud: String,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PasswordChangeRequest {
+    pub current_password: Secret,
+    pub new_password: Secret,
+    /// Current TOTP code or an unused recovery code, required once 2FA is enabled.
+    #[serde(default)]
+    pub totp_code: Option<Secret>,
+}
+
+/// Stored credentials of a user, as loaded by a `CredentialRepository`.
+///
+/// User ids are strings so that the same auth flow serves both backends.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub user_id: String,
+    pub name: String,
+    pub email: String,
+    pub password_hash: Option<Secret>,
+    pub roles: Vec<Role>,
+    pub email_verified: bool,
+    /// Base32 TOTP secret, set from enrollment until 2FA is disabled.
+    pub totp_secret: Option<Secret>,
+    pub totp_enabled: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: Secret,
+}
+
+/// A refresh token as stored server-side; only the SHA-256 of the token is kept.
+///
+/// Every rotation issues a new token in the same family, so reusing an old
+/// token can revoke the whole session.
+#[derive(Debug, Clone)]
+pub struct RefreshToken {
+    pub token_hash: String,
+    pub user_id: String,
+    pub family_id: String,
+    pub expires_at: SystemTime,
+    pub used: bool,
+    pub revoked: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    EmailVerification,
+    PasswordReset,
+}
+
+impl TokenPurpose {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TokenPurpose::EmailVerification => "email_verification",
+            TokenPurpose::PasswordReset => "password_reset",
+        }
+    }
+}
+
+/// A single-use token mailed to a user, stored as its SHA-256.
+///
+/// `email` is the address the token was sent to; the token stops being
+/// useful once the user's email changes.
+#[derive(Debug, Clone)]
+pub struct OneTimeToken {
+    pub token_hash: String,
+    pub purpose: TokenPurpose,
+    pub user_id: String,
+    pub email: String,
+    pub expires_at: SystemTime,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct EmailVerified {
+    pub user_id: String,
+    pub email: String,
+    pub email_verified: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PasswordResetConfirmation {
+    pub token: Secret,
+    pub new_password: Secret,
+}
+
+/// What an authenticator app needs to start producing codes.
+#[derive(Serialize, JsonSchema)]
+pub struct TotpEnrollment {
+    /// Base32 secret, for manual entry.
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TotpConfirmation {
+    pub code: Secret,
+}
+
+/// Shown once, when 2FA is enabled. Each code replaces a TOTP code once.
+#[derive(Serialize, JsonSchema)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TotpDisableRequest {
+    pub password: Secret,
+    /// Current TOTP code or an unused recovery code.
+    pub code: Secret,
+}
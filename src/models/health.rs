@@ -0,0 +1,46 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// Body of `GET /health/live`: the process is up and serving requests.
+#[derive(Debug, Serialize)]
+pub struct Liveness {
+    pub status: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+/// The outcome of probing one dependency. `error` says why it is down
+/// without exposing connection details, which are logged instead.
+#[derive(Debug, Serialize)]
+pub struct DependencyHealth {
+    pub status: CheckStatus,
+    pub required: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'static str>,
+}
+
+/// Body of `GET /health/ready`, keyed by dependency name (`postgres`, `mongo`).
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub status: &'static str,
+    pub checks: BTreeMap<&'static str, DependencyHealth>,
+}
+
+impl Readiness {
+    /// Ready unless a required dependency is down.
+    pub fn new(checks: BTreeMap<&'static str, DependencyHealth>) -> Self {
+        let ready = checks.values().all(|check| !check.required || check.status == CheckStatus::Up);
+        Readiness { status: if ready { "ready" } else { "not_ready" }, checks }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.status == "ready"
+    }
+}
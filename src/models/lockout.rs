@@ -0,0 +1,64 @@
+use std::time::SystemTime;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::utils::timestamp;
+
+/// What failed logins are counted against: the email tried, or the client address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockoutScope {
+    Account,
+    Ip,
+}
+
+impl LockoutScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LockoutScope::Account => "account",
+            LockoutScope::Ip => "ip",
+        }
+    }
+}
+
+/// Recent failed logins for one account or address.
+#[derive(Debug, Clone)]
+pub struct LoginFailures {
+    pub failures: u32,
+    pub last_failure_at: SystemTime,
+    pub locked_until: Option<SystemTime>,
+}
+
+impl LoginFailures {
+    pub fn is_locked(&self) -> bool {
+        self.locked_until.is_some_and(|until| until > SystemTime::now())
+    }
+}
+
+/// Lock state as shown to admins.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LockoutStatus {
+    /// Failed logins in the current window.
+    pub failures: u32,
+    #[serde(serialize_with = "timestamp::serialize_option")]
+    #[schemars(with = "Option<String>")]
+    pub last_failure_at: Option<SystemTime>,
+    pub locked: bool,
+    #[serde(serialize_with = "timestamp::serialize_option")]
+    #[schemars(with = "Option<String>")]
+    pub locked_until: Option<SystemTime>,
+}
+
+impl From<Option<LoginFailures>> for LockoutStatus {
+    fn from(failures: Option<LoginFailures>) -> Self {
+        match failures {
+            Some(failures) => LockoutStatus {
+                failures: failures.failures,
+                last_failure_at: Some(failures.last_failure_at),
+                locked: failures.is_locked(),
+                locked_until: failures.locked_until.filter(|_| failures.is_locked()),
+            },
+            None => LockoutStatus { failures: 0, last_failure_at: None, locked: false, locked_until: None },
+        }
+    }
+}
@@ -1 +1,9 @@
-pub mod user;
\ No newline at end of file
+pub mod user;
+pub mod pagination;
+pub mod auth;
+pub mod role;
+pub mod api_key;
+pub mod lockout;
+pub mod oidc;
+pub mod oauth;
+pub mod health;
\ No newline at end of file
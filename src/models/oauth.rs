@@ -0,0 +1,249 @@
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use rocket::FromForm;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::utils::timestamp;
+use crate::utils::validation::Normalize;
+
+/// Scopes clients may request on behalf of users, and the only ones that are
+/// not free-form service scopes.
+pub const OPENID_SCOPES: &[&str] = &["openid", "email", "profile"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantType {
+    AuthorizationCode,
+    ClientCredentials,
+}
+
+impl GrantType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GrantType::AuthorizationCode => "authorization_code",
+            GrantType::ClientCredentials => "client_credentials",
+        }
+    }
+}
+
+impl FromStr for GrantType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "authorization_code" => Ok(GrantType::AuthorizationCode),
+            "client_credentials" => Ok(GrantType::ClientCredentials),
+            other => Err(format!("Unknown grant type: {}", other)),
+        }
+    }
+}
+
+fn validate_redirect_uris(uris: &[String]) -> Result<(), ValidationError> {
+    let valid = |uri: &String| reqwest::Url::parse(uri)
+        .is_ok_and(|url| matches!(url.scheme(), "https" | "http") && url.fragment().is_none());
+    if uris.iter().all(valid) {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("url");
+        error.message = Some("redirect_uris must be absolute http(s) URLs without a fragment".into());
+        Err(error)
+    }
+}
+
+fn validate_scopes(scopes: &[String]) -> Result<(), ValidationError> {
+    let valid = |scope: &String| !scope.is_empty()
+        && scope.chars().all(|c| c.is_ascii_graphic() && c != '"' && c != '\\');
+    if scopes.iter().all(valid) {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("scope");
+        error.message = Some("scopes must be non-empty and contain no spaces or quotes".into());
+        Err(error)
+    }
+}
+
+fn default_confidential() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, JsonSchema, Validate)]
+pub struct NewOAuthClient {
+    #[validate(length(min = 1, max = 100, message = "name must be between 1 and 100 characters"))]
+    pub name: String,
+    /// Exact URLs users may be sent back to; required for `authorization_code`.
+    #[serde(default)]
+    #[validate(custom = "validate_redirect_uris")]
+    pub redirect_uris: Vec<String>,
+    #[validate(length(min = 1, message = "grant_types must not be empty"))]
+    pub grant_types: Vec<GrantType>,
+    /// Scopes the client may request. Defaults to `openid email profile` for
+    /// `authorization_code` clients.
+    #[serde(default)]
+    #[validate(custom = "validate_scopes")]
+    pub scopes: Vec<String>,
+    /// Public clients (browser or mobile apps) get no secret, must use PKCE
+    /// and cannot use `client_credentials`.
+    #[serde(default = "default_confidential")]
+    pub confidential: bool,
+}
+
+impl Normalize for NewOAuthClient {
+    fn normalize(mut self) -> Self {
+        self.name = self.name.trim().to_string();
+        self.redirect_uris = self.redirect_uris.iter().map(|uri| uri.trim().to_string()).collect();
+        self.scopes = self.scopes.iter().map(|scope| scope.trim().to_string()).collect();
+        self.scopes.dedup();
+        self.grant_types.dedup();
+        self
+    }
+}
+
+/// A client registered to use this service as its identity provider.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct OAuthClient {
+    pub client_id: String,
+    /// Only the hash of the secret is kept; public clients have none.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub client_secret_hash: Option<String>,
+    pub name: String,
+    pub redirect_uris: Vec<String>,
+    pub grant_types: Vec<GrantType>,
+    pub scopes: Vec<String>,
+    pub confidential: bool,
+    #[serde(serialize_with = "timestamp::serialize")]
+    #[schemars(with = "String")]
+    pub created_at: SystemTime,
+}
+
+/// Returned once, when the client is registered; the secret cannot be retrieved later.
+#[derive(Serialize, JsonSchema)]
+pub struct CreatedOAuthClient {
+    #[serde(flatten)]
+    pub client: OAuthClient,
+    pub client_secret: Option<String>,
+}
+
+/// The query a client sent the user to the authorization page with, passed
+/// on by the page together with the user's access token.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AuthorizationRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    /// Space separated; defaults to `openid`.
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    /// PKCE challenge; required, with `code_challenge_method` `S256`.
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+/// Where the authorization page should send the browser: the client's
+/// redirect URI carrying the code and state.
+#[derive(Serialize, JsonSchema)]
+pub struct AuthorizationRedirect {
+    pub redirect_to: String,
+}
+
+/// An issued authorization code, stored hashed until the client redeems it.
+#[derive(Debug, Clone)]
+pub struct AuthorizationCode {
+    pub code_hash: String,
+    pub client_id: String,
+    pub user_id: String,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub nonce: Option<String>,
+    pub code_challenge: String,
+    pub expires_at: SystemTime,
+}
+
+/// Form body of `POST /oauth/token`. Confidential clients authenticate with
+/// HTTP Basic or `client_id` plus `client_secret`.
+#[derive(Debug, FromForm, JsonSchema)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub scope: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+/// Claims of access tokens issued to OAuth clients. `sub` is the user, or
+/// the client itself for `client_credentials`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub client_id: String,
+    pub scope: String,
+    pub iat: u64,
+    pub exp: u64,
+}
+
+impl OAuthClaims {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope.split(' ').any(|granted| granted == scope)
+    }
+}
+
+/// Claims of ID tokens issued to relying parties; `sub` comes with the user info.
+#[derive(Debug, Serialize)]
+pub struct IdToken {
+    pub iss: String,
+    pub aud: String,
+    pub iat: u64,
+    pub exp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(flatten)]
+    pub user_info: UserInfo,
+}
+
+/// Standard claims about a user, limited to the scopes granted.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UserInfo {
+    pub sub: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+/// The discovery document at `/.well-known/openid-configuration`.
+#[derive(Serialize, JsonSchema)]
+pub struct ProviderConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    pub jwks_uri: String,
+    pub response_types_supported: Vec<&'static str>,
+    pub grant_types_supported: Vec<&'static str>,
+    pub subject_types_supported: Vec<&'static str>,
+    pub id_token_signing_alg_values_supported: Vec<&'static str>,
+    pub scopes_supported: Vec<&'static str>,
+    pub token_endpoint_auth_methods_supported: Vec<&'static str>,
+    pub code_challenge_methods_supported: Vec<&'static str>,
+    pub claims_supported: Vec<&'static str>,
+}
@@ -0,0 +1,48 @@
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+/// A login started with a provider, looked up by the hash of its `state`
+/// parameter when the provider redirects back.
+#[derive(Debug, Clone)]
+pub struct OidcLoginState {
+    pub state_hash: String,
+    pub provider: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub expires_at: SystemTime,
+}
+
+/// Who a provider says the user is, taken from a verified ID token.
+#[derive(Debug, Clone)]
+pub struct ExternalIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+/// The parts of a provider's discovery document the login flow uses.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+    #[serde(default)]
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+}
+
+/// ID token claims checked or used on top of those `jsonwebtoken` validates.
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub azp: Option<String>,
+    pub email: Option<String>,
+    /// Some providers send this as a string.
+    pub email_verified: Option<serde_json::Value>,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+}
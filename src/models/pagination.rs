@@ -0,0 +1,206 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rocket::form::FromFormField;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::app_error::AppError;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, FromFormField, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UserSortField {
+    #[default]
+    Id,
+    Name,
+    Email,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, FromFormField, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Query string accepted by the user listing endpoints.
+///
+/// `limit` is clamped to between 1 and `MAX_PAGE_SIZE`. `cursor` takes
+/// precedence over `offset`; it is the opaque `next_cursor` returned with the
+/// previous page and must be used with the same sort.
+#[derive(Debug, Default, FromForm, JsonSchema)]
+pub struct UserListQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub cursor: Option<String>,
+    pub sort: Option<UserSortField>,
+    pub direction: Option<SortDirection>,
+    pub email_contains: Option<String>,
+    pub name_prefix: Option<String>,
+}
+
+/// Position after the last item of a page: the sort key and the id breaking ties.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageCursor {
+    #[serde(rename = "s")]
+    pub sort: UserSortField,
+    #[serde(rename = "d")]
+    pub direction: SortDirection,
+    #[serde(rename = "k", default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub id: String,
+}
+
+impl PageCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        URL_SAFE_NO_PAD.decode(cursor).ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))
+    }
+}
+
+/// Validated listing options shared by every `UserRepository` implementation.
+#[derive(Debug)]
+pub struct ListOptions {
+    pub limit: i64,
+    pub offset: i64,
+    pub cursor: Option<PageCursor>,
+    pub sort: UserSortField,
+    pub direction: SortDirection,
+    pub email_contains: Option<String>,
+    pub name_prefix: Option<String>,
+}
+
+impl UserListQuery {
+    pub fn into_options(self) -> Result<ListOptions, AppError> {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+        let offset = self.offset.unwrap_or(0);
+        if offset < 0 {
+            return Err(AppError::BadRequest("offset must not be negative".to_string()));
+        }
+
+        let sort = self.sort.unwrap_or_default();
+        let direction = self.direction.unwrap_or_default();
+        let cursor = self.cursor.as_deref().map(PageCursor::decode).transpose()?;
+        if let Some(cursor) = &cursor {
+            if cursor.sort != sort || cursor.direction != direction {
+                return Err(AppError::BadRequest("cursor does not match the requested sort".to_string()));
+            }
+        }
+
+        Ok(ListOptions {
+            limit,
+            offset,
+            cursor,
+            sort,
+            direction,
+            email_contains: self.email_contains.filter(|s| !s.is_empty()),
+            name_prefix: self.name_prefix.filter(|s| !s.is_empty()),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from `limit + 1` fetched rows, using the extra row to
+    /// detect whether another page follows.
+    pub fn from_rows(
+        mut items: Vec<T>,
+        total: i64,
+        options: &ListOptions,
+        cursor_for: impl Fn(&T) -> PageCursor,
+    ) -> Self {
+        let has_more = items.len() as i64 > options.limit;
+        items.truncate(options.limit as usize);
+        let next_cursor = if has_more { items.last().map(|last| cursor_for(last).encode()) } else { None };
+
+        Page {
+            items,
+            total,
+            limit: options.limit,
+            offset: if options.cursor.is_some() { 0 } else { options.offset },
+            next_cursor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(sort: UserSortField, direction: SortDirection) -> PageCursor {
+        PageCursor { sort, direction, key: Some("alice@example.com".to_string()), id: "42".to_string() }
+    }
+
+    fn options(limit: i64) -> ListOptions {
+        UserListQuery { limit: Some(limit), ..UserListQuery::default() }.into_options().unwrap()
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        let encoded = cursor(UserSortField::Email, SortDirection::Desc).encode();
+        assert!(!encoded.contains(['+', '/', '=']));
+
+        let decoded = PageCursor::decode(&encoded).unwrap();
+        assert_eq!(decoded.sort, UserSortField::Email);
+        assert_eq!(decoded.direction, SortDirection::Desc);
+        assert_eq!(decoded.key.as_deref(), Some("alice@example.com"));
+        assert_eq!(decoded.id, "42");
+
+        assert!(matches!(PageCursor::decode("not a cursor"), Err(AppError::BadRequest(_))));
+        assert!(matches!(PageCursor::decode(&URL_SAFE_NO_PAD.encode("{}")), Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn rejects_a_cursor_for_another_sort() {
+        let encoded = cursor(UserSortField::Email, SortDirection::Asc).encode();
+        let query = |sort, direction| UserListQuery {
+            cursor: Some(encoded.clone()),
+            sort: Some(sort),
+            direction: Some(direction),
+            ..UserListQuery::default()
+        };
+
+        assert!(query(UserSortField::Email, SortDirection::Asc).into_options().is_ok());
+        assert!(matches!(query(UserSortField::Name, SortDirection::Asc).into_options(), Err(AppError::BadRequest(_))));
+        assert!(matches!(query(UserSortField::Email, SortDirection::Desc).into_options(), Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn clamps_the_limit() {
+        assert_eq!(UserListQuery::default().into_options().unwrap().limit, DEFAULT_PAGE_SIZE);
+        assert_eq!(options(50).limit, 50);
+        assert_eq!(options(MAX_PAGE_SIZE + 1).limit, MAX_PAGE_SIZE);
+        assert_eq!(options(0).limit, 1);
+        assert_eq!(options(-5).limit, 1);
+    }
+
+    #[test]
+    fn has_more_only_with_the_extra_row() {
+        let options = options(2);
+        let cursor_for = |id: &i32| PageCursor { id: id.to_string(), ..cursor(UserSortField::Id, SortDirection::Asc) };
+
+        let page = Page::from_rows(vec![1, 2, 3], 3, &options, cursor_for);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(PageCursor::decode(&page.next_cursor.unwrap()).unwrap().id, "2");
+
+        let page = Page::from_rows(vec![1, 2], 2, &options, cursor_for);
+        assert_eq!(page.items, vec![1, 2]);
+        assert!(page.next_cursor.is_none());
+    }
+}
@@ -0,0 +1,110 @@
+use std::str::FromStr;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    User,
+}
+
+/// What a caller may do beyond managing their own account. Roles grant sets
+/// of permissions; API keys are granted them directly as scopes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    ListUsers,
+    ReadAnyUser,
+    WriteAnyUser,
+    DeleteAnyUser,
+    RevokeAnySessions,
+    ManageRoles,
+    ManageApiKeys,
+    ManageLockouts,
+    ManageOAuthClients,
+}
+
+impl Permission {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::ListUsers => "list_users",
+            Permission::ReadAnyUser => "read_any_user",
+            Permission::WriteAnyUser => "write_any_user",
+            Permission::DeleteAnyUser => "delete_any_user",
+            Permission::RevokeAnySessions => "revoke_any_sessions",
+            Permission::ManageRoles => "manage_roles",
+            Permission::ManageApiKeys => "manage_api_keys",
+            Permission::ManageLockouts => "manage_lockouts",
+            Permission::ManageOAuthClients => "manage_oauth_clients",
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "list_users" => Ok(Permission::ListUsers),
+            "read_any_user" => Ok(Permission::ReadAnyUser),
+            "write_any_user" => Ok(Permission::WriteAnyUser),
+            "delete_any_user" => Ok(Permission::DeleteAnyUser),
+            "revoke_any_sessions" => Ok(Permission::RevokeAnySessions),
+            "manage_roles" => Ok(Permission::ManageRoles),
+            "manage_api_keys" => Ok(Permission::ManageApiKeys),
+            "manage_lockouts" => Ok(Permission::ManageLockouts),
+            "manage_oauth_clients" => Ok(Permission::ManageOAuthClients),
+            other => Err(format!("Unknown permission: {}", other)),
+        }
+    }
+}
+
+impl Role {
+    pub fn permissions(self) -> &'static [Permission] {
+        match self {
+            Role::Admin => &[
+                Permission::ListUsers,
+                Permission::ReadAnyUser,
+                Permission::WriteAnyUser,
+                Permission::DeleteAnyUser,
+                Permission::RevokeAnySessions,
+                Permission::ManageRoles,
+                Permission::ManageApiKeys,
+                Permission::ManageLockouts,
+                Permission::ManageOAuthClients,
+            ],
+            Role::User => &[],
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::User => "user",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "admin" => Ok(Role::Admin),
+            "user" => Ok(Role::User),
+            other => Err(format!("Unknown role: {}", other)),
+        }
+    }
+}
+
+/// Parses stored role or permission names, skipping any this build does not know.
+pub fn parse_names<'a, T: FromStr>(names: impl IntoIterator<Item = &'a str>) -> Vec<T> {
+    names.into_iter().filter_map(|name| name.parse().ok()).collect()
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RolesUpdate {
+    pub roles: Vec<Role>,
+}
@@ -1,18 +1,92 @@
+#[cfg(feature = "mongo")]
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
+use validator::Validate;
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+use crate::utils::secret::Secret;
+use crate::utils::validation::{normalize_email, Normalize};
+
+#[cfg(feature = "postgres")]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Validate)]
 pub struct User {
     pub id: Option<i32>,
+    #[validate(length(min = 1, max = 100, message = "name must be between 1 and 100 characters"))]
     pub name: String,
+    #[validate(email, length(max = 254, message = "email must be at most 254 characters"))]
     pub email: String,
+    /// Incremented on every write; exposed as the `ETag` of the user.
+    pub version: Option<i64>,
+    /// Set once the address is confirmed through `/verify-email` and cleared
+    /// when it changes; ignored in requests.
+    #[serde(default)]
+    pub email_verified: Option<bool>,
+    /// Initial password, only read when the user is created. Change it
+    /// through `/auth/password`; it is stored hashed and never returned.
+    #[serde(default, skip_serializing)]
+    pub password: Option<Secret>,
 }
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[cfg(feature = "mongo")]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Validate)]
 pub struct UserMongo {
+    /// Read from the document's `_id`; written back to clients as `id`.
+    #[serde(alias = "_id")]
     #[schemars(skip)]
     pub id: Option<ObjectId>,
+    #[validate(length(min = 1, max = 100, message = "name must be between 1 and 100 characters"))]
     pub name: String,
+    #[validate(email, length(max = 254, message = "email must be at most 254 characters"))]
     pub email: String,
-}
\ No newline at end of file
+    /// Incremented on every write; exposed as the `ETag` of the user.
+    pub version: Option<i64>,
+    /// Set once the address is confirmed through `/verify-email` and cleared
+    /// when it changes; ignored in requests.
+    #[serde(default)]
+    pub email_verified: Option<bool>,
+    /// Initial password, only read when the user is created. Change it
+    /// through `/auth/password`; it is stored hashed and never returned.
+    #[serde(default, skip_serializing)]
+    pub password: Option<Secret>,
+}
+
+#[cfg(feature = "postgres")]
+impl Normalize for User {
+    fn normalize(self) -> Self {
+        User {
+            name: self.name.trim().to_string(),
+            email: normalize_email(&self.email),
+            ..self
+        }
+    }
+}
+
+#[cfg(feature = "mongo")]
+impl Normalize for UserMongo {
+    fn normalize(self) -> Self {
+        UserMongo {
+            name: self.name.trim().to_string(),
+            email: normalize_email(&self.email),
+            ..self
+        }
+    }
+}
+
+/// Fields a client may change through `PATCH`; absent fields are left untouched.
+#[derive(Debug, Default, Deserialize, JsonSchema, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct UserPatch {
+    #[validate(length(min = 1, max = 100, message = "name must be between 1 and 100 characters"))]
+    pub name: Option<String>,
+    #[validate(email, length(max = 254, message = "email must be at most 254 characters"))]
+    pub email: Option<String>,
+}
+
+impl Normalize for UserPatch {
+    fn normalize(self) -> Self {
+        UserPatch {
+            name: self.name.map(|name| name.trim().to_string()),
+            email: self.email.as_deref().map(normalize_email),
+        }
+    }
+}
@@ -1,20 +1,118 @@
-use rocket_okapi::openapi_get_routes;
+use rocket_okapi::handlers::OpenApiHandler;
+use rocket_okapi::okapi::merge::merge_specs;
+use rocket_okapi::okapi::openapi3::{Components, OpenApi, RefOr, SecurityScheme, SecuritySchemeData};
+use rocket_okapi::okapi::Map;
+use rocket_okapi::openapi_get_routes_spec;
+use rocket_okapi::settings::OpenApiSettings;
 use rocket_okapi::swagger_ui::SwaggerUIConfig;
 use rocket::Route;
 
-use crate::handlers::{user_handler, mongo_user_handler};
-
-pub fn openapi_routes() -> Vec<Route> {
-    openapi_get_routes![
-        user_handler::add_user,
-        user_handler::get_users,
-        user_handler::update_user,
-        user_handler::delete_user,
-        mongo_user_handler::adding_user,
-        mongo_user_handler::getting_users,
-        mongo_user_handler::updating_user,
-        mongo_user_handler::deleting_user
-    ]
+use crate::config::app_config::AppConfig;
+#[cfg(feature = "mongo")]
+use crate::handlers::mongo_user_handler;
+#[cfg(feature = "postgres")]
+use crate::handlers::user_handler;
+use crate::handlers::{
+    api_key_handler, auth_handler, lockout_handler, oauth_handler, oidc_handler, verification_handler,
+};
+use crate::utils::authentication::{API_KEY_AUTH, BEARER_AUTH};
+
+/// The documented routes, with the user routes of each connected database.
+pub fn openapi_routes(app_config: &AppConfig) -> Vec<Route> {
+    let settings = OpenApiSettings::new();
+    let (mut routes, mut spec) = openapi_get_routes_spec![settings:
+        auth_handler::login,
+        auth_handler::change_password,
+        auth_handler::me,
+        auth_handler::refresh,
+        auth_handler::logout,
+        auth_handler::revoke_sessions,
+        auth_handler::set_roles,
+        auth_handler::password_reset_request,
+        auth_handler::password_reset_confirm,
+        auth_handler::enroll_two_factor,
+        auth_handler::confirm_two_factor,
+        auth_handler::disable_two_factor,
+        api_key_handler::add_api_key,
+        api_key_handler::get_api_keys,
+        api_key_handler::revoke_api_key,
+        verification_handler::request_verification,
+        verification_handler::verify_email,
+        lockout_handler::get_user_lockout,
+        lockout_handler::unlock_user,
+        lockout_handler::get_ip_lockout,
+        lockout_handler::unlock_ip,
+        oidc_handler::get_providers,
+        oidc_handler::authorize,
+        oidc_handler::callback,
+        oauth_handler::openid_configuration,
+        oauth_handler::jwks,
+        oauth_handler::authorize,
+        oauth_handler::token,
+        oauth_handler::userinfo,
+        oauth_handler::add_client,
+        oauth_handler::get_clients,
+        oauth_handler::get_client,
+        oauth_handler::delete_client
+    ];
+
+    #[cfg(feature = "postgres")]
+    if app_config.postgres_pool.is_some() {
+        let (user_routes, user_spec) = openapi_get_routes_spec![settings:
+            user_handler::add_user,
+            user_handler::get_users,
+            user_handler::get_user,
+            user_handler::update_user,
+            user_handler::patch_user,
+            user_handler::delete_user
+        ];
+        routes.extend(user_routes);
+        merge_specs(&mut spec, &"/", &user_spec).expect("user routes do not clash with the common ones");
+    }
+
+    #[cfg(feature = "mongo")]
+    if app_config.mongo_db.is_some() {
+        let (user_routes, user_spec) = openapi_get_routes_spec![settings:
+            mongo_user_handler::adding_user,
+            mongo_user_handler::getting_users,
+            mongo_user_handler::getting_user,
+            mongo_user_handler::updating_user,
+            mongo_user_handler::patching_user,
+            mongo_user_handler::deleting_user
+        ];
+        routes.extend(user_routes);
+        merge_specs(&mut spec, &"/", &user_spec).expect("user routes do not clash with the common ones");
+    }
+
+    allow_bearer_where_api_key_is_allowed(&mut spec);
+    routes.push(OpenApiHandler::new(spec).into_route(&settings.json_path));
+    routes
+}
+
+/// A request guard documents a single security scheme, but `Caller` accepts
+/// an API key or a bearer token; add the bearer alternative to its operations.
+fn allow_bearer_where_api_key_is_allowed(spec: &mut OpenApi) {
+    let components = spec.components.get_or_insert_with(Components::default);
+    components.security_schemes.entry(BEARER_AUTH.to_owned()).or_insert_with(|| RefOr::Object(SecurityScheme {
+        description: Some("Access token returned by `POST /auth/login`".to_owned()),
+        data: SecuritySchemeData::Http {
+            scheme: "bearer".to_owned(),
+            bearer_format: Some("JWT".to_owned()),
+        },
+        extensions: Default::default(),
+    }));
+
+    for path in spec.paths.values_mut() {
+        let operations = [&mut path.get, &mut path.put, &mut path.post, &mut path.delete, &mut path.patch];
+        for operation in operations.into_iter().flatten() {
+            let Some(security) = operation.security.as_mut() else { continue };
+            if security.iter().any(|requirement| requirement.contains_key(API_KEY_AUTH)) {
+                let mut bearer = Map::new();
+                bearer.insert(BEARER_AUTH.to_owned(), Vec::new());
+                security.push(bearer);
+            }
+        }
+    }
 }
 
 pub fn swagger_ui() -> SwaggerUIConfig {
@@ -22,4 +120,4 @@ pub fn swagger_ui() -> SwaggerUIConfig {
         url: "/openapi.json".to_string(),
         ..Default::default()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,23 @@
+use std::time::SystemTime;
+use rocket::async_trait;
+
+use crate::errors::app_error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::role::Permission;
+
+/// Hashed API keys for service-to-service callers.
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn insert_api_key(
+        &self,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &[Permission],
+        expires_at: Option<SystemTime>
+    ) -> Result<ApiKey, AppError>;
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, AppError>;
+    async fn find_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError>;
+    async fn touch_api_key(&self, id: &str) -> Result<(), AppError>;
+    async fn revoke_api_key(&self, id: &str) -> Result<(), AppError>;
+}
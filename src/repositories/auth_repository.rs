@@ -0,0 +1,25 @@
+use std::sync::Arc;
+
+use crate::repositories::api_key_repository::ApiKeyRepository;
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::login_failure_repository::LoginFailureRepository;
+use crate::repositories::oauth_repository::OAuthRepository;
+use crate::repositories::oidc_repository::OidcRepository;
+use crate::repositories::one_time_token_repository::OneTimeTokenRepository;
+use crate::repositories::refresh_token_repository::RefreshTokenRepository;
+use crate::repositories::two_factor_repository::TwoFactorRepository;
+
+/// Everything the authentication endpoints persist. Each backend implements
+/// the individual repositories and gets this trait for free.
+pub trait AuthRepository:
+    CredentialRepository + RefreshTokenRepository + ApiKeyRepository + OneTimeTokenRepository
+    + TwoFactorRepository + LoginFailureRepository + OidcRepository + OAuthRepository {}
+
+impl<T> AuthRepository for T
+where
+    T: CredentialRepository + RefreshTokenRepository + ApiKeyRepository + OneTimeTokenRepository
+    + TwoFactorRepository + LoginFailureRepository + OidcRepository + OAuthRepository {}
+
+/// The store backing authentication, selected with `AUTH_BACKEND`. Shared so
+/// that work finishing after the response can hold on to it.
+pub type AuthStore = Arc<dyn AuthRepository>;
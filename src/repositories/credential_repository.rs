@@ -0,0 +1,17 @@
+use rocket::async_trait;
+
+use crate::errors::app_error::AppError;
+use crate::models::auth::Credentials;
+use crate::models::role::Role;
+
+/// Access to the password hashes stored alongside users.
+#[async_trait]
+pub trait CredentialRepository: Send + Sync {
+    /// Looks a user up by email, case-insensitively.
+    async fn find_credentials_by_email(&self, email: &str) -> Result<Option<Credentials>, AppError>;
+    async fn find_credentials_by_id(&self, user_id: &str) -> Result<Option<Credentials>, AppError>;
+    async fn set_password_hash(&self, user_id: &str, password_hash: &str) -> Result<(), AppError>;
+    async fn set_roles(&self, user_id: &str, roles: &[Role]) -> Result<(), AppError>;
+    /// Marks `email` as verified if it is still the user's email.
+    async fn mark_email_verified(&self, user_id: &str, email: &str) -> Result<bool, AppError>;
+}
@@ -0,0 +1,22 @@
+use std::time::{Duration, SystemTime};
+
+use rocket::async_trait;
+
+use crate::errors::app_error::AppError;
+use crate::models::lockout::{LockoutScope, LoginFailures};
+
+/// Failed login counters, keyed by scope and the email or address they count.
+#[async_trait]
+pub trait LoginFailureRepository: Send + Sync {
+    async fn find_login_failures(&self, scope: LockoutScope, key: &str) -> Result<Option<LoginFailures>, AppError>;
+    /// Counts a failure, starting over if the previous one is older than `window`.
+    async fn record_login_failure(
+        &self,
+        scope: LockoutScope,
+        key: &str,
+        window: Duration
+    ) -> Result<LoginFailures, AppError>;
+    async fn lock_login(&self, scope: LockoutScope, key: &str, until: SystemTime) -> Result<(), AppError>;
+    /// Forgets the failures and any lock. Returns `false` if there were none.
+    async fn clear_login_failures(&self, scope: LockoutScope, key: &str) -> Result<bool, AppError>;
+}
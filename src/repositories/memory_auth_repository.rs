@@ -0,0 +1,449 @@
+//! An in-memory `AuthRepository` for unit tests of the authentication
+//! services, following the semantics of the database implementations.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use rocket::async_trait;
+
+use crate::errors::app_error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::auth::{Credentials, OneTimeToken, RefreshToken, TokenPurpose};
+use crate::models::lockout::{LockoutScope, LoginFailures};
+use crate::models::oauth::{AuthorizationCode, OAuthClient};
+use crate::models::oidc::{ExternalIdentity, OidcLoginState};
+use crate::models::role::{Permission, Role};
+use crate::repositories::api_key_repository::ApiKeyRepository;
+use crate::repositories::auth_repository::AuthStore;
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::login_failure_repository::LoginFailureRepository;
+use crate::repositories::oauth_repository::OAuthRepository;
+use crate::repositories::oidc_repository::OidcRepository;
+use crate::repositories::one_time_token_repository::OneTimeTokenRepository;
+use crate::repositories::refresh_token_repository::RefreshTokenRepository;
+use crate::repositories::two_factor_repository::TwoFactorRepository;
+use crate::utils::secret::Secret;
+
+#[derive(Default)]
+struct TwoFactorState {
+    last_step: Option<i64>,
+    recovery_code_hashes: Vec<String>,
+}
+
+struct StoredOneTimeToken {
+    token: OneTimeToken,
+    created_at: SystemTime,
+    used: bool,
+}
+
+#[derive(Default)]
+struct State {
+    users: Vec<Credentials>,
+    two_factor: HashMap<String, TwoFactorState>,
+    refresh_tokens: HashMap<String, RefreshToken>,
+    api_keys: Vec<(ApiKey, String)>,
+    one_time_tokens: Vec<StoredOneTimeToken>,
+    login_failures: HashMap<(&'static str, String), LoginFailures>,
+    oidc_states: HashMap<String, OidcLoginState>,
+    identities: HashMap<(String, String), String>,
+    oauth_clients: Vec<OAuthClient>,
+    authorization_codes: HashMap<String, AuthorizationCode>,
+    next_id: u64,
+}
+
+impl State {
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        self.next_id.to_string()
+    }
+
+    fn user(&mut self, user_id: &str) -> Option<&mut Credentials> {
+        self.users.iter_mut().find(|user| user.user_id == user_id)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryStore {
+    state: Mutex<State>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a user and returns its id.
+    pub fn add_user(&self, email: &str, password_hash: Option<&str>, roles: &[Role]) -> String {
+        let mut state = self.state.lock().unwrap();
+        let user_id = state.next_id();
+        state.users.push(Credentials {
+            user_id: user_id.clone(),
+            name: email.split('@').next().unwrap_or_default().to_string(),
+            email: email.to_string(),
+            password_hash: password_hash.map(Secret::new),
+            roles: roles.to_vec(),
+            email_verified: false,
+            totp_secret: None,
+            totp_enabled: false,
+        });
+        user_id
+    }
+
+    /// Changes a user directly, for state the tests do not reach through a service.
+    pub fn update_user(&self, user_id: &str, update: impl FnOnce(&mut Credentials)) {
+        let mut state = self.state.lock().unwrap();
+        update(state.user(user_id).expect("unknown user"));
+    }
+
+    pub fn into_store(self) -> AuthStore {
+        Arc::new(self)
+    }
+}
+
+#[async_trait]
+impl CredentialRepository for MemoryStore {
+    async fn find_credentials_by_email(&self, email: &str) -> Result<Option<Credentials>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.users.iter().find(|user| user.email.eq_ignore_ascii_case(email)).cloned())
+    }
+
+    async fn find_credentials_by_id(&self, user_id: &str) -> Result<Option<Credentials>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.users.iter().find(|user| user.user_id == user_id).cloned())
+    }
+
+    async fn set_password_hash(&self, user_id: &str, password_hash: &str) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        let user = state.user(user_id).ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        user.password_hash = Some(Secret::new(password_hash));
+        Ok(())
+    }
+
+    async fn set_roles(&self, user_id: &str, roles: &[Role]) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        let user = state.user(user_id).ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        user.roles = roles.to_vec();
+        Ok(())
+    }
+
+    async fn mark_email_verified(&self, user_id: &str, email: &str) -> Result<bool, AppError> {
+        let mut state = self.state.lock().unwrap();
+        match state.user(user_id) {
+            Some(user) if user.email == email => {
+                user.email_verified = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepository for MemoryStore {
+    async fn insert_refresh_token(&self, token: &RefreshToken) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        state.refresh_tokens.insert(token.token_hash.clone(), token.clone());
+        Ok(())
+    }
+
+    async fn find_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshToken>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.refresh_tokens.get(token_hash).cloned())
+    }
+
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<bool, AppError> {
+        let mut state = self.state.lock().unwrap();
+        match state.refresh_tokens.get_mut(token_hash) {
+            Some(token) if !token.used && !token.revoked => {
+                token.used = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id: &str) -> Result<u64, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let mut revoked = 0;
+        for token in state.refresh_tokens.values_mut().filter(|token| token.family_id == family_id && !token.revoked) {
+            token.revoked = true;
+            revoked += 1;
+        }
+        Ok(revoked)
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: &str) -> Result<u64, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let mut revoked = 0;
+        for token in state.refresh_tokens.values_mut().filter(|token| token.user_id == user_id && !token.revoked) {
+            token.revoked = true;
+            revoked += 1;
+        }
+        Ok(revoked)
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for MemoryStore {
+    async fn insert_api_key(
+        &self,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &[Permission],
+        expires_at: Option<SystemTime>
+    ) -> Result<ApiKey, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let key = ApiKey {
+            id: state.next_id(),
+            name: name.to_string(),
+            prefix: prefix.to_string(),
+            scopes: scopes.to_vec(),
+            created_at: SystemTime::now(),
+            expires_at,
+            last_used_at: None,
+            revoked: false,
+        };
+        state.api_keys.push((key.clone(), key_hash.to_string()));
+        Ok(key)
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.api_keys.iter().map(|(key, _)| key.clone()).collect())
+    }
+
+    async fn find_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.api_keys.iter().find(|(_, hash)| hash == key_hash).map(|(key, _)| key.clone()))
+    }
+
+    async fn touch_api_key(&self, id: &str) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        if let Some((key, _)) = state.api_keys.iter_mut().find(|(key, _)| key.id == id) {
+            key.last_used_at = Some(SystemTime::now());
+        }
+        Ok(())
+    }
+
+    async fn revoke_api_key(&self, id: &str) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        let (key, _) = state.api_keys.iter_mut().find(|(key, _)| key.id == id)
+            .ok_or_else(|| AppError::NotFound("API key not found".to_string()))?;
+        key.revoked = true;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OneTimeTokenRepository for MemoryStore {
+    async fn insert_one_time_token(&self, token: &OneTimeToken) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        state.one_time_tokens.push(StoredOneTimeToken { token: token.clone(), created_at: SystemTime::now(), used: false });
+        Ok(())
+    }
+
+    async fn consume_one_time_token(&self, token_hash: &str, purpose: TokenPurpose) -> Result<Option<OneTimeToken>, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let stored = state.one_time_tokens.iter_mut().find(|stored| {
+            stored.token.token_hash == token_hash && stored.token.purpose == purpose
+                && !stored.used && stored.token.expires_at > SystemTime::now()
+        });
+        Ok(stored.map(|stored| {
+            stored.used = true;
+            stored.token.clone()
+        }))
+    }
+
+    async fn revoke_user_one_time_tokens(&self, user_id: &str, purpose: TokenPurpose) -> Result<u64, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let mut revoked = 0;
+        for stored in state.one_time_tokens.iter_mut() {
+            if stored.token.user_id == user_id && stored.token.purpose == purpose && !stored.used {
+                stored.used = true;
+                revoked += 1;
+            }
+        }
+        Ok(revoked)
+    }
+
+    async fn count_one_time_tokens_since(&self, email: &str, purpose: TokenPurpose, since: SystemTime) -> Result<u64, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.one_time_tokens.iter()
+            .filter(|stored| stored.token.email == email && stored.token.purpose == purpose && stored.created_at >= since)
+            .count() as u64)
+    }
+}
+
+#[async_trait]
+impl TwoFactorRepository for MemoryStore {
+    async fn set_pending_totp_secret(&self, user_id: &str, secret: &str) -> Result<bool, AppError> {
+        let mut state = self.state.lock().unwrap();
+        match state.user(user_id) {
+            Some(user) if !user.totp_enabled => {
+                user.totp_secret = Some(Secret::new(secret));
+                state.two_factor.insert(user_id.to_string(), TwoFactorState::default());
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn enable_totp(&self, user_id: &str, recovery_code_hashes: &[String]) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(user) = state.user(user_id) {
+            user.totp_enabled = true;
+        }
+        state.two_factor.entry(user_id.to_string()).or_default().recovery_code_hashes = recovery_code_hashes.to_vec();
+        Ok(())
+    }
+
+    async fn disable_totp(&self, user_id: &str) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(user) = state.user(user_id) {
+            user.totp_enabled = false;
+            user.totp_secret = None;
+        }
+        state.two_factor.remove(user_id);
+        Ok(())
+    }
+
+    async fn record_totp_step(&self, user_id: &str, step: i64) -> Result<bool, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let two_factor = state.two_factor.entry(user_id.to_string()).or_default();
+        if two_factor.last_step.is_some_and(|last| last >= step) {
+            return Ok(false);
+        }
+        two_factor.last_step = Some(step);
+        Ok(true)
+    }
+
+    async fn consume_recovery_code(&self, user_id: &str, code_hash: &str) -> Result<bool, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let Some(two_factor) = state.two_factor.get_mut(user_id) else { return Ok(false) };
+        let before = two_factor.recovery_code_hashes.len();
+        two_factor.recovery_code_hashes.retain(|hash| hash != code_hash);
+        Ok(two_factor.recovery_code_hashes.len() < before)
+    }
+}
+
+#[async_trait]
+impl LoginFailureRepository for MemoryStore {
+    async fn find_login_failures(&self, scope: LockoutScope, key: &str) -> Result<Option<LoginFailures>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.login_failures.get(&(scope.as_str(), key.to_string())).cloned())
+    }
+
+    async fn record_login_failure(
+        &self,
+        scope: LockoutScope,
+        key: &str,
+        window: Duration
+    ) -> Result<LoginFailures, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let now = SystemTime::now();
+        let failures = state.login_failures.entry((scope.as_str(), key.to_string())).or_insert(LoginFailures {
+            failures: 0,
+            last_failure_at: now,
+            locked_until: None,
+        });
+        if failures.last_failure_at + window < now {
+            failures.failures = 0;
+        }
+        failures.failures += 1;
+        failures.last_failure_at = now;
+        Ok(failures.clone())
+    }
+
+    async fn lock_login(&self, scope: LockoutScope, key: &str, until: SystemTime) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(failures) = state.login_failures.get_mut(&(scope.as_str(), key.to_string())) {
+            failures.locked_until = Some(until);
+        }
+        Ok(())
+    }
+
+    async fn clear_login_failures(&self, scope: LockoutScope, key: &str) -> Result<bool, AppError> {
+        let mut state = self.state.lock().unwrap();
+        Ok(state.login_failures.remove(&(scope.as_str(), key.to_string())).is_some())
+    }
+}
+
+#[async_trait]
+impl OidcRepository for MemoryStore {
+    async fn insert_oidc_state(&self, login_state: &OidcLoginState) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        state.oidc_states.insert(login_state.state_hash.clone(), login_state.clone());
+        Ok(())
+    }
+
+    async fn consume_oidc_state(&self, state_hash: &str) -> Result<Option<OidcLoginState>, AppError> {
+        let mut state = self.state.lock().unwrap();
+        Ok(state.oidc_states.remove(state_hash))
+    }
+
+    async fn find_identity_user(&self, provider: &str, subject: &str) -> Result<Option<String>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.identities.get(&(provider.to_string(), subject.to_string())).cloned())
+    }
+
+    async fn link_identity(&self, identity: &ExternalIdentity, user_id: &str) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        state.identities.insert((identity.provider.clone(), identity.subject.clone()), user_id.to_string());
+        Ok(())
+    }
+
+    async fn create_user_with_identity(&self, identity: &ExternalIdentity, name: &str, email: &str) -> Result<String, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let user_id = state.next_id();
+        state.users.push(Credentials {
+            user_id: user_id.clone(),
+            name: name.to_string(),
+            email: email.to_string(),
+            password_hash: None,
+            roles: vec![Role::User],
+            email_verified: identity.email_verified,
+            totp_secret: None,
+            totp_enabled: false,
+        });
+        state.identities.insert((identity.provider.clone(), identity.subject.clone()), user_id.clone());
+        Ok(user_id)
+    }
+}
+
+#[async_trait]
+impl OAuthRepository for MemoryStore {
+    async fn insert_oauth_client(&self, client: &OAuthClient) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        state.oauth_clients.push(client.clone());
+        Ok(())
+    }
+
+    async fn list_oauth_clients(&self) -> Result<Vec<OAuthClient>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.oauth_clients.clone())
+    }
+
+    async fn find_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>, AppError> {
+        let state = self.state.lock().unwrap();
+        Ok(state.oauth_clients.iter().find(|client| client.client_id == client_id).cloned())
+    }
+
+    async fn delete_oauth_client(&self, client_id: &str) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        state.oauth_clients.retain(|client| client.client_id != client_id);
+        Ok(())
+    }
+
+    async fn insert_authorization_code(&self, code: &AuthorizationCode) -> Result<(), AppError> {
+        let mut state = self.state.lock().unwrap();
+        state.authorization_codes.insert(code.code_hash.clone(), code.clone());
+        Ok(())
+    }
+
+    async fn consume_authorization_code(&self, code_hash: &str) -> Result<Option<AuthorizationCode>, AppError> {
+        let mut state = self.state.lock().unwrap();
+        Ok(state.authorization_codes.remove(code_hash))
+    }
+}
@@ -0,0 +1,48 @@
+pub mod user_repository;
+#[cfg(feature = "postgres")]
+pub mod postgres_user_repository;
+#[cfg(feature = "mongo")]
+pub mod mongo_user_repository;
+pub mod credential_repository;
+#[cfg(feature = "postgres")]
+pub mod postgres_credential_repository;
+#[cfg(feature = "mongo")]
+pub mod mongo_credential_repository;
+pub mod auth_repository;
+#[cfg(test)]
+pub mod memory_auth_repository;
+pub mod refresh_token_repository;
+#[cfg(feature = "postgres")]
+pub mod postgres_refresh_token_repository;
+#[cfg(feature = "mongo")]
+pub mod mongo_refresh_token_repository;
+pub mod api_key_repository;
+#[cfg(feature = "postgres")]
+pub mod postgres_api_key_repository;
+#[cfg(feature = "mongo")]
+pub mod mongo_api_key_repository;
+pub mod one_time_token_repository;
+#[cfg(feature = "postgres")]
+pub mod postgres_one_time_token_repository;
+#[cfg(feature = "mongo")]
+pub mod mongo_one_time_token_repository;
+pub mod two_factor_repository;
+#[cfg(feature = "postgres")]
+pub mod postgres_two_factor_repository;
+#[cfg(feature = "mongo")]
+pub mod mongo_two_factor_repository;
+pub mod login_failure_repository;
+#[cfg(feature = "postgres")]
+pub mod postgres_login_failure_repository;
+#[cfg(feature = "mongo")]
+pub mod mongo_login_failure_repository;
+pub mod oidc_repository;
+#[cfg(feature = "postgres")]
+pub mod postgres_oidc_repository;
+#[cfg(feature = "mongo")]
+pub mod mongo_oidc_repository;
+pub mod oauth_repository;
+#[cfg(feature = "postgres")]
+pub mod postgres_oauth_repository;
+#[cfg(feature = "mongo")]
+pub mod mongo_oauth_repository;
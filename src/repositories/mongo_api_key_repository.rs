@@ -0,0 +1,99 @@
+use std::time::SystemTime;
+use mongodb::bson::{doc, oid::ObjectId, DateTime, Document};
+use mongodb::{Collection, Database};
+use rocket::async_trait;
+use rocket::futures::TryStreamExt;
+use log::{info, error};
+
+use crate::errors::app_error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::role::{parse_names, Permission};
+use crate::repositories::api_key_repository::ApiKeyRepository;
+
+fn api_keys(db: &Database) -> Collection<Document> {
+    db.collection::<Document>("api_keys")
+}
+
+fn optional_time(document: &Document, key: &str) -> Option<SystemTime> {
+    document.get_datetime(key).ok().map(|time| time.to_system_time())
+}
+
+fn document_to_api_key(document: &Document) -> ApiKey {
+    ApiKey {
+        id: document.get_object_id("_id").map(|id| id.to_hex()).unwrap_or_default(),
+        name: document.get_str("name").unwrap_or_default().to_string(),
+        prefix: document.get_str("prefix").unwrap_or_default().to_string(),
+        scopes: document.get_array("scopes")
+            .map(|scopes| parse_names(scopes.iter().filter_map(|scope| scope.as_str())))
+            .unwrap_or_default(),
+        created_at: optional_time(document, "created_at").unwrap_or(std::time::UNIX_EPOCH),
+        expires_at: optional_time(document, "expires_at"),
+        last_used_at: optional_time(document, "last_used_at"),
+        revoked: document.get_datetime("revoked_at").is_ok(),
+    }
+}
+
+fn parse_key_id(id: &str) -> Result<ObjectId, AppError> {
+    ObjectId::parse_str(id).map_err(|_| AppError::NotFound("API key not found".to_string()))
+}
+
+#[async_trait]
+impl ApiKeyRepository for Database {
+    async fn insert_api_key(
+        &self,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &[Permission],
+        expires_at: Option<SystemTime>
+    ) -> Result<ApiKey, AppError> {
+        info!("Inserting API key {} into MongoDB", name);
+        let document = doc! {
+            "_id": ObjectId::new(),
+            "name": name,
+            "prefix": prefix,
+            "key_hash": key_hash,
+            "scopes": scopes.iter().map(|scope| scope.as_str()).collect::<Vec<_>>(),
+            "created_at": DateTime::now(),
+            "expires_at": expires_at.map(DateTime::from_system_time),
+        };
+        api_keys(self).insert_one(&document, None).await?;
+        Ok(document_to_api_key(&document))
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, AppError> {
+        let documents: Vec<Document> = api_keys(self).find(None, None).await?.try_collect().await?;
+        Ok(documents.iter().map(document_to_api_key).collect())
+    }
+
+    async fn find_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError> {
+        let document = api_keys(self).find_one(doc! { "key_hash": key_hash }, None).await?;
+        Ok(document.as_ref().map(document_to_api_key))
+    }
+
+    async fn touch_api_key(&self, id: &str) -> Result<(), AppError> {
+        api_keys(self).update_one(
+            doc! { "_id": parse_key_id(id)? },
+            doc! { "$set": { "last_used_at": DateTime::now() } },
+            None
+        ).await?;
+        Ok(())
+    }
+
+    async fn revoke_api_key(&self, id: &str) -> Result<(), AppError> {
+        info!("Revoking API key {} in MongoDB", id);
+        let key_id = parse_key_id(id)?;
+        let keys = api_keys(self);
+        let result = keys.update_one(
+            doc! { "_id": key_id, "revoked_at": null },
+            doc! { "$set": { "revoked_at": DateTime::now() } },
+            None
+        ).await?;
+
+        if result.matched_count == 0 && keys.find_one(doc! { "_id": key_id }, None).await?.is_none() {
+            error!("API key not found: {}", id);
+            return Err(AppError::NotFound("API key not found".to_string()));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::options::{Collation, CollationStrength, FindOneOptions, UpdateOptions};
+use mongodb::Database;
+use rocket::async_trait;
+use log::{info, error};
+
+use crate::errors::app_error::AppError;
+use crate::models::auth::Credentials;
+use crate::models::role::{parse_names, Role};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::utils::secret::Secret;
+
+/// Parses a string user id; ids that are not ObjectIds cannot exist in MongoDB.
+pub fn parse_user_id(user_id: &str) -> Result<ObjectId, AppError> {
+    ObjectId::parse_str(user_id).map_err(|_| {
+        error!("Invalid MongoDB user id: {}", user_id);
+        AppError::NotFound("User not found".to_string())
+    })
+}
+
+/// Same collation as the unique email index, so lookups ignore case.
+pub fn case_insensitive() -> Collation {
+    Collation::builder().locale("en").strength(CollationStrength::Secondary).build()
+}
+
+fn document_to_credentials(user: &Document) -> Credentials {
+    Credentials {
+        user_id: user.get_object_id("_id").map(|id| id.to_hex()).unwrap_or_default(),
+        name: user.get_str("name").unwrap_or_default().to_string(),
+        email: user.get_str("email").unwrap_or_default().to_string(),
+        password_hash: user.get_str("password_hash").ok().map(Secret::new),
+        roles: match user.get_array("roles") {
+            Ok(roles) => parse_names(roles.iter().filter_map(|role| role.as_str())),
+            Err(_) => vec![Role::User],
+        },
+        email_verified: user.get_bool("email_verified").unwrap_or(false),
+        totp_secret: user.get_str("totp_secret").ok().map(Secret::new),
+        totp_enabled: user.get_bool("totp_enabled").unwrap_or(false),
+    }
+}
+
+#[async_trait]
+impl CredentialRepository for Database {
+    async fn find_credentials_by_email(&self, email: &str) -> Result<Option<Credentials>, AppError> {
+        info!("Fetching credentials from MongoDB");
+        let options = FindOneOptions::builder().collation(case_insensitive()).build();
+        let user = self.collection::<Document>("users")
+            .find_one(doc! { "email": email }, options)
+            .await?;
+
+        Ok(user.as_ref().map(document_to_credentials))
+    }
+
+    async fn find_credentials_by_id(&self, user_id: &str) -> Result<Option<Credentials>, AppError> {
+        info!("Fetching credentials of user {} from MongoDB", user_id);
+        let Ok(id) = ObjectId::parse_str(user_id) else {
+            return Ok(None);
+        };
+        let user = self.collection::<Document>("users").find_one(doc! { "_id": id }, None).await?;
+
+        Ok(user.as_ref().map(document_to_credentials))
+    }
+
+    async fn set_password_hash(&self, user_id: &str, password_hash: &str) -> Result<(), AppError> {
+        info!("Updating password hash of user {} in MongoDB", user_id);
+        let id = parse_user_id(user_id)?;
+        let result = self.collection::<Document>("users")
+            .update_one(doc! { "_id": id }, doc! { "$set": { "password_hash": password_hash } }, None)
+            .await?;
+
+        if result.matched_count == 0 {
+            error!("User not found for password update: {}", user_id);
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+        Ok(())
+    }
+    async fn set_roles(&self, user_id: &str, roles: &[Role]) -> Result<(), AppError> {
+        info!("Setting roles of user {} in MongoDB: {:?}", user_id, roles);
+        let id = parse_user_id(user_id)?;
+        let roles = roles.iter().map(|role| role.as_str()).collect::<Vec<_>>();
+        let result = self.collection::<Document>("users")
+            .update_one(doc! { "_id": id }, doc! { "$set": { "roles": roles } }, None)
+            .await?;
+
+        if result.matched_count == 0 {
+            error!("User not found for role update: {}", user_id);
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+        Ok(())
+    }
+    async fn mark_email_verified(&self, user_id: &str, email: &str) -> Result<bool, AppError> {
+        info!("Marking email of user {} as verified in MongoDB", user_id);
+        let id = parse_user_id(user_id)?;
+        let options = UpdateOptions::builder().collation(case_insensitive()).build();
+        let result = self.collection::<Document>("users")
+            .update_one(doc! { "_id": id, "email": email }, doc! { "$set": { "email_verified": true } }, options)
+            .await?;
+        Ok(result.matched_count == 1)
+    }
+}
@@ -0,0 +1,88 @@
+use std::time::{Duration, SystemTime};
+
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use mongodb::{Collection, Database};
+use rocket::async_trait;
+use log::info;
+
+use crate::errors::app_error::AppError;
+use crate::models::lockout::{LockoutScope, LoginFailures};
+use crate::repositories::login_failure_repository::LoginFailureRepository;
+
+fn login_failures(db: &Database) -> Collection<Document> {
+    db.collection::<Document>("login_failures")
+}
+
+fn document_id(scope: LockoutScope, key: &str) -> String {
+    format!("{}:{}", scope.as_str(), key)
+}
+
+fn document_to_login_failures(document: &Document) -> LoginFailures {
+    LoginFailures {
+        failures: document.get_i32("failures").unwrap_or_default().max(0) as u32,
+        last_failure_at: document.get_datetime("last_failure_at")
+            .map(|d| d.to_system_time())
+            .unwrap_or(std::time::UNIX_EPOCH),
+        locked_until: document.get_datetime("locked_until").ok().map(|d| d.to_system_time()),
+    }
+}
+
+/// Documents carry an `expires_at` past both their window and their lock, so
+/// the TTL index only removes counters that no longer matter.
+#[async_trait]
+impl LoginFailureRepository for Database {
+    async fn find_login_failures(&self, scope: LockoutScope, key: &str) -> Result<Option<LoginFailures>, AppError> {
+        let document = login_failures(self).find_one(doc! { "_id": document_id(scope, key) }, None).await?;
+        Ok(document.as_ref().map(document_to_login_failures))
+    }
+
+    async fn record_login_failure(
+        &self,
+        scope: LockoutScope,
+        key: &str,
+        window: Duration
+    ) -> Result<LoginFailures, AppError> {
+        let now = SystemTime::now();
+        let window_start = DateTime::from_system_time(now.checked_sub(window).unwrap_or(std::time::UNIX_EPOCH));
+        let window_end = DateTime::from_system_time(now + window);
+        let now = DateTime::from_system_time(now);
+
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        let document = login_failures(self).find_one_and_update(
+            doc! { "_id": document_id(scope, key) },
+            vec![doc! { "$set": {
+                "failures": { "$cond": [
+                    { "$gte": [{ "$ifNull": ["$last_failure_at", DateTime::MIN] }, window_start] },
+                    { "$add": [{ "$ifNull": ["$failures", 0] }, 1] },
+                    1,
+                ] },
+                "last_failure_at": now,
+                "expires_at": { "$max": [{ "$ifNull": ["$locked_until", now] }, window_end] },
+            } }],
+            options
+        ).await?;
+        document.as_ref()
+            .map(document_to_login_failures)
+            .ok_or_else(|| AppError::DatabaseError("Failed to record login failure".to_string()))
+    }
+
+    async fn lock_login(&self, scope: LockoutScope, key: &str, until: SystemTime) -> Result<(), AppError> {
+        info!("Locking {} {} in MongoDB", scope.as_str(), key);
+        let until = DateTime::from_system_time(until);
+        login_failures(self).update_one(
+            doc! { "_id": document_id(scope, key) },
+            doc! { "$set": { "locked_until": until }, "$max": { "expires_at": until } },
+            None
+        ).await?;
+        Ok(())
+    }
+
+    async fn clear_login_failures(&self, scope: LockoutScope, key: &str) -> Result<bool, AppError> {
+        let result = login_failures(self).delete_one(doc! { "_id": document_id(scope, key) }, None).await?;
+        Ok(result.deleted_count == 1)
+    }
+}
@@ -0,0 +1,106 @@
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::{Collection, Database};
+use rocket::async_trait;
+use rocket::futures::TryStreamExt;
+use log::info;
+
+use crate::errors::app_error::AppError;
+use crate::models::oauth::{AuthorizationCode, GrantType, OAuthClient};
+use crate::repositories::oauth_repository::OAuthRepository;
+
+fn clients(db: &Database) -> Collection<Document> {
+    db.collection::<Document>("oauth_clients")
+}
+
+fn authorization_codes(db: &Database) -> Collection<Document> {
+    db.collection::<Document>("oauth_authorization_codes")
+}
+
+fn strings(document: &Document, key: &str) -> Vec<String> {
+    document.get_array(key)
+        .map(|values| values.iter().filter_map(|value| value.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+fn document_to_client(document: &Document) -> OAuthClient {
+    OAuthClient {
+        client_id: document.get_str("_id").unwrap_or_default().to_string(),
+        client_secret_hash: document.get_str("client_secret_hash").ok().map(str::to_string),
+        name: document.get_str("name").unwrap_or_default().to_string(),
+        redirect_uris: strings(document, "redirect_uris"),
+        grant_types: strings(document, "grant_types").iter().filter_map(|grant| grant.parse().ok()).collect(),
+        scopes: strings(document, "scopes"),
+        confidential: document.get_str("client_secret_hash").is_ok(),
+        created_at: document.get_datetime("created_at").map(|d| d.to_system_time()).unwrap_or(std::time::UNIX_EPOCH),
+    }
+}
+
+#[async_trait]
+impl OAuthRepository for Database {
+    async fn insert_oauth_client(&self, client: &OAuthClient) -> Result<(), AppError> {
+        info!("Registering OAuth client {} in MongoDB", client.client_id);
+        let grant_types: Vec<&str> = client.grant_types.iter().map(|grant| GrantType::as_str(*grant)).collect();
+        clients(self).insert_one(doc! {
+            "_id": &client.client_id,
+            "client_secret_hash": client.client_secret_hash.as_deref(),
+            "name": &client.name,
+            "redirect_uris": &client.redirect_uris,
+            "grant_types": grant_types,
+            "scopes": &client.scopes,
+            "created_at": DateTime::from_system_time(client.created_at),
+        }, None).await?;
+        Ok(())
+    }
+
+    async fn list_oauth_clients(&self) -> Result<Vec<OAuthClient>, AppError> {
+        let documents: Vec<Document> = clients(self).find(None, None).await?.try_collect().await?;
+        Ok(documents.iter().map(document_to_client).collect())
+    }
+
+    async fn find_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>, AppError> {
+        let document = clients(self).find_one(doc! { "_id": client_id }, None).await?;
+        Ok(document.as_ref().map(document_to_client))
+    }
+
+    async fn delete_oauth_client(&self, client_id: &str) -> Result<(), AppError> {
+        info!("Deleting OAuth client {} in MongoDB", client_id);
+        let result = clients(self).delete_one(doc! { "_id": client_id }, None).await?;
+        if result.deleted_count == 0 {
+            return Err(AppError::NotFound("OAuth client not found".to_string()));
+        }
+        authorization_codes(self).delete_many(doc! { "client_id": client_id }, None).await?;
+        Ok(())
+    }
+
+    async fn insert_authorization_code(&self, code: &AuthorizationCode) -> Result<(), AppError> {
+        authorization_codes(self).insert_one(doc! {
+            "_id": &code.code_hash,
+            "client_id": &code.client_id,
+            "user_id": &code.user_id,
+            "redirect_uri": &code.redirect_uri,
+            "scope": &code.scope,
+            "nonce": code.nonce.as_deref(),
+            "code_challenge": &code.code_challenge,
+            "expires_at": DateTime::from_system_time(code.expires_at),
+        }, None).await?;
+        Ok(())
+    }
+
+    async fn consume_authorization_code(&self, code_hash: &str) -> Result<Option<AuthorizationCode>, AppError> {
+        let document = authorization_codes(self).find_one_and_delete(
+            doc! { "_id": code_hash, "expires_at": { "$gt": DateTime::now() } },
+            None
+        ).await?;
+
+        Ok(document.map(|document| AuthorizationCode {
+            code_hash: code_hash.to_string(),
+            client_id: document.get_str("client_id").unwrap_or_default().to_string(),
+            user_id: document.get_str("user_id").unwrap_or_default().to_string(),
+            redirect_uri: document.get_str("redirect_uri").unwrap_or_default().to_string(),
+            scope: document.get_str("scope").unwrap_or_default().to_string(),
+            nonce: document.get_str("nonce").ok().map(str::to_string),
+            code_challenge: document.get_str("code_challenge").unwrap_or_default().to_string(),
+            expires_at: document.get_datetime("expires_at").map(|d| d.to_system_time()).unwrap_or(std::time::UNIX_EPOCH),
+        }))
+    }
+}
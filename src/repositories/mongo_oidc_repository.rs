@@ -0,0 +1,103 @@
+use mongodb::bson::{doc, oid::ObjectId, DateTime, Document};
+use mongodb::{Collection, Database};
+use rocket::async_trait;
+use log::{info, error};
+
+use crate::db::mongo::is_duplicate_key;
+use crate::errors::app_error::AppError;
+use crate::models::oidc::{ExternalIdentity, OidcLoginState};
+use crate::models::role::Role;
+use crate::repositories::oidc_repository::OidcRepository;
+
+fn login_states(db: &Database) -> Collection<Document> {
+    db.collection::<Document>("oidc_login_states")
+}
+
+fn identities(db: &Database) -> Collection<Document> {
+    db.collection::<Document>("external_identities")
+}
+
+/// Identities are keyed by `provider:subject` in `_id`; provider names cannot contain `:`.
+fn identity_id(provider: &str, subject: &str) -> String {
+    format!("{}:{}", provider, subject)
+}
+
+fn identity_document(identity: &ExternalIdentity, user_id: &str) -> Document {
+    doc! {
+        "_id": identity_id(&identity.provider, &identity.subject),
+        "provider": &identity.provider,
+        "subject": &identity.subject,
+        "user_id": user_id,
+        "email": identity.email.as_deref(),
+        "created_at": DateTime::now(),
+    }
+}
+
+#[async_trait]
+impl OidcRepository for Database {
+    async fn insert_oidc_state(&self, state: &OidcLoginState) -> Result<(), AppError> {
+        login_states(self).insert_one(doc! {
+            "_id": &state.state_hash,
+            "provider": &state.provider,
+            "nonce": &state.nonce,
+            "code_verifier": &state.code_verifier,
+            "expires_at": DateTime::from_system_time(state.expires_at),
+        }, None).await?;
+        Ok(())
+    }
+
+    async fn consume_oidc_state(&self, state_hash: &str) -> Result<Option<OidcLoginState>, AppError> {
+        let document = login_states(self).find_one_and_delete(
+            doc! { "_id": state_hash, "expires_at": { "$gt": DateTime::now() } },
+            None
+        ).await?;
+
+        Ok(document.map(|document| OidcLoginState {
+            state_hash: state_hash.to_string(),
+            provider: document.get_str("provider").unwrap_or_default().to_string(),
+            nonce: document.get_str("nonce").unwrap_or_default().to_string(),
+            code_verifier: document.get_str("code_verifier").unwrap_or_default().to_string(),
+            expires_at: document.get_datetime("expires_at").map(|d| d.to_system_time()).unwrap_or(std::time::UNIX_EPOCH),
+        }))
+    }
+
+    async fn find_identity_user(&self, provider: &str, subject: &str) -> Result<Option<String>, AppError> {
+        let document = identities(self).find_one(doc! { "_id": identity_id(provider, subject) }, None).await?;
+        Ok(document.and_then(|document| document.get_str("user_id").ok().map(str::to_string)))
+    }
+
+    async fn link_identity(&self, identity: &ExternalIdentity, user_id: &str) -> Result<(), AppError> {
+        info!("Linking {} identity to user {} in MongoDB", identity.provider, user_id);
+        match identities(self).insert_one(identity_document(identity, user_id), None).await {
+            Err(e) if is_duplicate_key(&e) => Ok(()),
+            result => result.map(|_| ()).map_err(AppError::from),
+        }
+    }
+
+    async fn create_user_with_identity(&self, identity: &ExternalIdentity, name: &str, email: &str) -> Result<String, AppError> {
+        info!("Creating user for {} identity in MongoDB", identity.provider);
+        let id = ObjectId::new();
+        self.collection::<Document>("users").insert_one(doc! {
+            "_id": id,
+            "name": name,
+            "email": email,
+            "version": 1,
+            "email_verified": identity.email_verified,
+            "roles": [Role::User.as_str()],
+        }, None).await.map_err(|e| {
+            if is_duplicate_key(&e) {
+                error!("Duplicate email rejected by MongoDB: {}", e);
+                return AppError::Conflict("A user with this email already exists".to_string());
+            }
+            e.into()
+        })?;
+
+        let user_id = id.to_hex();
+        if let Err(e) = identities(self).insert_one(identity_document(identity, &user_id), None).await {
+            // No transaction here, so undo the user rather than leave it unreachable.
+            self.collection::<Document>("users").delete_one(doc! { "_id": id }, None).await?;
+            return Err(e.into());
+        }
+        Ok(user_id)
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::SystemTime;
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::options::CountOptions;
+use mongodb::Database;
+use rocket::async_trait;
+use log::info;
+
+use crate::errors::app_error::AppError;
+use crate::models::auth::{OneTimeToken, TokenPurpose};
+use crate::repositories::mongo_credential_repository::case_insensitive;
+use crate::repositories::one_time_token_repository::OneTimeTokenRepository;
+
+#[async_trait]
+impl OneTimeTokenRepository for Database {
+    async fn insert_one_time_token(&self, token: &OneTimeToken) -> Result<(), AppError> {
+        info!("Storing {} token for user {} in MongoDB", token.purpose.as_str(), token.user_id);
+        self.collection::<Document>("one_time_tokens").insert_one(doc! {
+            "_id": &token.token_hash,
+            "purpose": token.purpose.as_str(),
+            "user_id": &token.user_id,
+            "email": &token.email,
+            "created_at": DateTime::now(),
+            "expires_at": DateTime::from_system_time(token.expires_at),
+        }, None).await?;
+        Ok(())
+    }
+
+    async fn consume_one_time_token(&self, token_hash: &str, purpose: TokenPurpose) -> Result<Option<OneTimeToken>, AppError> {
+        let token = self.collection::<Document>("one_time_tokens").find_one_and_update(
+            doc! {
+                "_id": token_hash,
+                "purpose": purpose.as_str(),
+                "used_at": null,
+                "expires_at": { "$gt": DateTime::now() },
+            },
+            doc! { "$set": { "used_at": DateTime::now() } },
+            None
+        ).await?;
+
+        Ok(token.map(|token| OneTimeToken {
+            token_hash: token_hash.to_string(),
+            purpose,
+            user_id: token.get_str("user_id").unwrap_or_default().to_string(),
+            email: token.get_str("email").unwrap_or_default().to_string(),
+            expires_at: token.get_datetime("expires_at").map(|d| d.to_system_time()).unwrap_or(std::time::UNIX_EPOCH),
+        }))
+    }
+
+    async fn revoke_user_one_time_tokens(&self, user_id: &str, purpose: TokenPurpose) -> Result<u64, AppError> {
+        let result = self.collection::<Document>("one_time_tokens").update_many(
+            doc! { "user_id": user_id, "purpose": purpose.as_str(), "used_at": null },
+            doc! { "$set": { "used_at": DateTime::now() } },
+            None
+        ).await?;
+        Ok(result.modified_count)
+    }
+
+    async fn count_one_time_tokens_since(&self, email: &str, purpose: TokenPurpose, since: SystemTime) -> Result<u64, AppError> {
+        let options = CountOptions::builder().collation(case_insensitive()).build();
+        Ok(self.collection::<Document>("one_time_tokens").count_documents(doc! {
+            "email": email,
+            "purpose": purpose.as_str(),
+            "created_at": { "$gte": DateTime::from_system_time(since) },
+        }, options).await?)
+    }
+}
@@ -0,0 +1,72 @@
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::{Collection, Database};
+use rocket::async_trait;
+use log::info;
+
+use crate::errors::app_error::AppError;
+use crate::models::auth::RefreshToken;
+use crate::repositories::refresh_token_repository::RefreshTokenRepository;
+
+fn refresh_tokens(db: &Database) -> Collection<Document> {
+    db.collection::<Document>("refresh_tokens")
+}
+
+fn document_to_refresh_token(document: &Document) -> RefreshToken {
+    RefreshToken {
+        token_hash: document.get_str("_id").unwrap_or_default().to_string(),
+        user_id: document.get_str("user_id").unwrap_or_default().to_string(),
+        family_id: document.get_str("family_id").unwrap_or_default().to_string(),
+        expires_at: document.get_datetime("expires_at").map(|d| d.to_system_time()).unwrap_or(std::time::UNIX_EPOCH),
+        used: document.get_datetime("used_at").is_ok(),
+        revoked: document.get_datetime("revoked_at").is_ok(),
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepository for Database {
+    async fn insert_refresh_token(&self, token: &RefreshToken) -> Result<(), AppError> {
+        info!("Storing refresh token for user {} in MongoDB", token.user_id);
+        refresh_tokens(self).insert_one(doc! {
+            "_id": &token.token_hash,
+            "user_id": &token.user_id,
+            "family_id": &token.family_id,
+            "created_at": DateTime::now(),
+            "expires_at": DateTime::from_system_time(token.expires_at),
+        }, None).await?;
+        Ok(())
+    }
+
+    async fn find_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshToken>, AppError> {
+        let document = refresh_tokens(self).find_one(doc! { "_id": token_hash }, None).await?;
+        Ok(document.as_ref().map(document_to_refresh_token))
+    }
+
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<bool, AppError> {
+        let result = refresh_tokens(self).update_one(
+            doc! { "_id": token_hash, "used_at": null, "revoked_at": null },
+            doc! { "$set": { "used_at": DateTime::now() } },
+            None
+        ).await?;
+        Ok(result.modified_count == 1)
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id: &str) -> Result<u64, AppError> {
+        info!("Revoking refresh token family {} in MongoDB", family_id);
+        let result = refresh_tokens(self).update_many(
+            doc! { "family_id": family_id, "revoked_at": null },
+            doc! { "$set": { "revoked_at": DateTime::now() } },
+            None
+        ).await?;
+        Ok(result.modified_count)
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: &str) -> Result<u64, AppError> {
+        info!("Revoking all refresh tokens of user {} in MongoDB", user_id);
+        let result = refresh_tokens(self).update_many(
+            doc! { "user_id": user_id, "revoked_at": null },
+            doc! { "$set": { "revoked_at": DateTime::now() } },
+            None
+        ).await?;
+        Ok(result.modified_count)
+    }
+}
@@ -0,0 +1,72 @@
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database};
+use rocket::async_trait;
+use log::info;
+
+use crate::errors::app_error::AppError;
+use crate::repositories::mongo_credential_repository::parse_user_id;
+use crate::repositories::two_factor_repository::TwoFactorRepository;
+
+fn users(db: &Database) -> Collection<Document> {
+    db.collection::<Document>("users")
+}
+
+#[async_trait]
+impl TwoFactorRepository for Database {
+    async fn set_pending_totp_secret(&self, user_id: &str, secret: &str) -> Result<bool, AppError> {
+        info!("Storing pending TOTP secret of user {} in MongoDB", user_id);
+        let result = users(self).update_one(
+            doc! { "_id": parse_user_id(user_id)?, "totp_enabled": { "$ne": true } },
+            doc! {
+                "$set": { "totp_secret": secret, "totp_enabled": false, "recovery_code_hashes": [] },
+                "$unset": { "totp_last_step": "" },
+            },
+            None
+        ).await?;
+        Ok(result.matched_count == 1)
+    }
+
+    async fn enable_totp(&self, user_id: &str, recovery_code_hashes: &[String]) -> Result<(), AppError> {
+        info!("Enabling TOTP for user {} in MongoDB", user_id);
+        users(self).update_one(
+            doc! { "_id": parse_user_id(user_id)?, "totp_secret": { "$exists": true } },
+            doc! { "$set": { "totp_enabled": true, "recovery_code_hashes": recovery_code_hashes } },
+            None
+        ).await?;
+        Ok(())
+    }
+
+    async fn disable_totp(&self, user_id: &str) -> Result<(), AppError> {
+        info!("Disabling TOTP for user {} in MongoDB", user_id);
+        users(self).update_one(
+            doc! { "_id": parse_user_id(user_id)? },
+            doc! {
+                "$set": { "totp_enabled": false, "recovery_code_hashes": [] },
+                "$unset": { "totp_secret": "", "totp_last_step": "" },
+            },
+            None
+        ).await?;
+        Ok(())
+    }
+
+    async fn record_totp_step(&self, user_id: &str, step: i64) -> Result<bool, AppError> {
+        let result = users(self).update_one(
+            doc! {
+                "_id": parse_user_id(user_id)?,
+                "$or": [{ "totp_last_step": null }, { "totp_last_step": { "$lt": step } }],
+            },
+            doc! { "$set": { "totp_last_step": step } },
+            None
+        ).await?;
+        Ok(result.modified_count == 1)
+    }
+
+    async fn consume_recovery_code(&self, user_id: &str, code_hash: &str) -> Result<bool, AppError> {
+        let result = users(self).update_one(
+            doc! { "_id": parse_user_id(user_id)?, "recovery_code_hashes": code_hash },
+            doc! { "$pull": { "recovery_code_hashes": code_hash } },
+            None
+        ).await?;
+        Ok(result.modified_count == 1)
+    }
+}
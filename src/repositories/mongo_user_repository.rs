@@ -0,0 +1,256 @@
+use mongodb::{Collection, Database, bson::{doc, oid::ObjectId, to_document, Document}, options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument}};
+use rocket::async_trait;
+use rocket::futures::TryStreamExt;
+use log::{info, error};
+
+use crate::db::mongo::is_duplicate_key;
+use crate::errors::app_error::AppError;
+use crate::models::pagination::{ListOptions, Page, PageCursor, SortDirection, UserSortField};
+use crate::models::role::Role;
+use crate::models::user::{UserMongo, UserPatch};
+use crate::repositories::user_repository::UserRepository;
+
+fn users(db: &Database) -> Collection<UserMongo> {
+    db.collection::<UserMongo>("users")
+}
+
+/// Escapes regex metacharacters so user input is matched literally.
+fn escape_regex(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if "\\^$.|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn map_write_error(error: mongodb::error::Error) -> AppError {
+    if is_duplicate_key(&error) {
+        error!("Duplicate email rejected by MongoDB: {}", error);
+        return AppError::Conflict("A user with this email already exists".to_string());
+    }
+    error.into()
+}
+
+/// Makes `find_one_and_update` return the document as it is after the update,
+/// like `RETURNING` does in PostgreSQL.
+fn returning_updated() -> FindOneAndUpdateOptions {
+    FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build()
+}
+
+/// Pipeline expression keeping `email_verified` only while the email stays the same.
+///
+/// Updates are pipelines so the flag can depend on the stored email; values
+/// from clients go through `$literal` so a leading `$` is not read as a field path.
+fn keeps_verification(email: &str) -> Document {
+    doc! { "$and": [{ "$ifNull": ["$email_verified", false] }, { "$eq": ["$email", { "$literal": email }] }] }
+}
+
+/// Filter matching the user only while it is at one of `expected_versions`.
+fn versioned_filter(id: ObjectId, expected_versions: &Option<Vec<i64>>) -> Document {
+    match expected_versions {
+        Some(versions) => doc! { "_id": id, "version": { "$in": versions } },
+        None => doc! { "_id": id },
+    }
+}
+
+/// Explains why a conditional write matched no document: the user is either
+/// gone or at a version the caller did not expect.
+async fn missed_write(db: &Database, id: ObjectId) -> AppError {
+    match users(db).count_documents(doc! { "_id": id }, None).await {
+        Ok(0) => {
+            error!("User not found: {}", id);
+            AppError::NotFound("User not found".to_string())
+        }
+        Ok(_) => {
+            error!("Version precondition failed for user {}", id);
+            AppError::PreconditionFailed("User has been modified since it was read".to_string())
+        }
+        Err(e) => e.into(),
+    }
+}
+
+fn sort_field(sort: UserSortField) -> &'static str {
+    match sort {
+        UserSortField::Id => "_id",
+        UserSortField::Name => "name",
+        UserSortField::Email => "email",
+    }
+}
+
+fn cursor_for(user: &UserMongo, options: &ListOptions) -> PageCursor {
+    PageCursor {
+        sort: options.sort,
+        direction: options.direction,
+        key: match options.sort {
+            UserSortField::Id => None,
+            UserSortField::Name => Some(user.name.clone()),
+            UserSortField::Email => Some(user.email.clone()),
+        },
+        id: user.id.map(|id| id.to_hex()).unwrap_or_default(),
+    }
+}
+
+#[async_trait]
+impl UserRepository for Database {
+    type Id = ObjectId;
+    type User = UserMongo;
+
+    async fn create(&self, mut user: UserMongo, password_hash: Option<String>) -> Result<UserMongo, AppError> {
+        info!("Inserting user into MongoDB: {:?}", user);
+        let collection = users(self);
+
+        // Generate a new ObjectId for the id field
+        let id = ObjectId::new();
+        user.id = Some(id);
+        user.version = Some(1);
+        user.email_verified = Some(false);
+
+        // The id belongs in `_id`, otherwise MongoDB generates a second one. The
+        // hash is not part of `UserMongo`, so it never leaves the database.
+        let mut document = to_document(&user)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to serialize user: {}", e)))?;
+        document.remove("id");
+        document.insert("_id", id);
+        document.insert("roles", vec![Role::User.as_str()]);
+        if let Some(password_hash) = password_hash {
+            document.insert("password_hash", password_hash);
+        }
+
+        let result = self.collection::<Document>("users").insert_one(document, None).await
+            .map_err(map_write_error)?;
+
+        let inserted_id = result.inserted_id.as_object_id()
+            .ok_or_else(|| AppError::InternalServerError("Failed to get inserted ObjectId".to_string()))?;
+
+        let inserted_user = collection.find_one(doc! { "_id": inserted_id }, None).await?
+            .ok_or_else(|| AppError::InternalServerError("Failed to retrieve inserted user".to_string()))?;
+
+        info!("User inserted with id: {}", inserted_id);
+        Ok(inserted_user)
+    }
+
+    async fn get(&self, id: ObjectId) -> Result<UserMongo, AppError> {
+        info!("Fetching user {} from MongoDB", id);
+        users(self).find_one(doc! { "_id": id }, None).await?
+            .ok_or_else(|| {
+                error!("User not found: {}", id);
+                AppError::NotFound("User not found".to_string())
+            })
+    }
+
+    async fn list(&self, options: &ListOptions) -> Result<Page<UserMongo>, AppError> {
+        info!("Fetching users from MongoDB: {:?}", options);
+        let collection = users(self);
+
+        let mut filters: Vec<Document> = Vec::new();
+        if let Some(email) = &options.email_contains {
+            filters.push(doc! { "email": { "$regex": escape_regex(email), "$options": "i" } });
+        }
+        if let Some(name) = &options.name_prefix {
+            filters.push(doc! { "name": { "$regex": format!("^{}", escape_regex(name)), "$options": "i" } });
+        }
+
+        let as_filter = |filters: &[Document]| if filters.is_empty() {
+            doc! {}
+        } else {
+            doc! { "$and": filters.to_vec() }
+        };
+
+        let total = collection.count_documents(as_filter(&filters), None).await? as i64;
+
+        let field = sort_field(options.sort);
+        let (order, comparison) = match options.direction {
+            SortDirection::Asc => (1, "$gt"),
+            SortDirection::Desc => (-1, "$lt"),
+        };
+
+        let mut skip = options.offset as u64;
+        if let Some(cursor) = &options.cursor {
+            let id = ObjectId::parse_str(&cursor.id)
+                .map_err(|_| AppError::BadRequest("Invalid cursor".to_string()))?;
+            match &cursor.key {
+                Some(key) if options.sort != UserSortField::Id => filters.push(doc! {
+                    "$or": [
+                        { field: { comparison: key } },
+                        { field: key, "_id": { comparison: id } },
+                    ]
+                }),
+                _ => filters.push(doc! { "_id": { comparison: id } }),
+            }
+            skip = 0;
+        }
+
+        let sort = if options.sort == UserSortField::Id {
+            doc! { "_id": order }
+        } else {
+            doc! { field: order, "_id": order }
+        };
+        let find_options = FindOptions::builder()
+            .sort(sort)
+            .skip(skip)
+            .limit(options.limit + 1)
+            .build();
+
+        let mut cursor = collection.find(as_filter(&filters), find_options).await?;
+
+        let mut found = Vec::new();
+        while let Some(user) = cursor.try_next().await? {
+            found.push(user);
+        }
+
+        info!("Successfully fetched {} of {} users from MongoDB", found.len().min(options.limit as usize), total);
+        Ok(Page::from_rows(found, total, options, |user| cursor_for(user, options)))
+    }
+
+    async fn update(&self, id: ObjectId, user: UserMongo, expected_versions: Option<Vec<i64>>) -> Result<UserMongo, AppError> {
+        info!("Updating user {} in MongoDB", id);
+        let update = vec![doc! {
+            "$set": {
+                "name": { "$literal": &user.name },
+                "email": { "$literal": &user.email },
+                "version": { "$add": ["$version", 1_i64] },
+                "email_verified": keeps_verification(&user.email),
+            },
+        }];
+
+        let filter = versioned_filter(id, &expected_versions);
+        match users(self).find_one_and_update(filter, update, returning_updated()).await.map_err(map_write_error)? {
+            Some(updated) => Ok(updated),
+            None => Err(missed_write(self, id).await),
+        }
+    }
+
+    async fn patch(&self, id: ObjectId, patch: UserPatch, expected_versions: Option<Vec<i64>>) -> Result<UserMongo, AppError> {
+        info!("Patching user {} in MongoDB: {:?}", id, patch);
+        let mut changes = doc! { "version": { "$add": ["$version", 1_i64] } };
+        if let Some(name) = &patch.name {
+            changes.insert("name", doc! { "$literal": name });
+        }
+        if let Some(email) = &patch.email {
+            changes.insert("email", doc! { "$literal": email });
+            changes.insert("email_verified", keeps_verification(email));
+        }
+        let update = vec![doc! { "$set": changes }];
+
+        let filter = versioned_filter(id, &expected_versions);
+        match users(self).find_one_and_update(filter, update, returning_updated()).await.map_err(map_write_error)? {
+            Some(patched) => Ok(patched),
+            None => Err(missed_write(self, id).await),
+        }
+    }
+
+    async fn delete(&self, id: ObjectId, expected_versions: Option<Vec<i64>>) -> Result<(), AppError> {
+        info!("Deleting user {} from MongoDB", id);
+        let result = users(self).delete_one(versioned_filter(id, &expected_versions), None).await?;
+
+        if result.deleted_count == 0 {
+            return Err(missed_write(self, id).await);
+        }
+
+        info!("User deleted successfully: {}", id);
+        Ok(())
+    }
+}
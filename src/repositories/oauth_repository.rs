@@ -0,0 +1,17 @@
+use rocket::async_trait;
+
+use crate::errors::app_error::AppError;
+use crate::models::oauth::{AuthorizationCode, OAuthClient};
+
+/// Clients registered with the OAuth provider and the codes issued to them.
+#[async_trait]
+pub trait OAuthRepository: Send + Sync {
+    async fn insert_oauth_client(&self, client: &OAuthClient) -> Result<(), AppError>;
+    async fn list_oauth_clients(&self) -> Result<Vec<OAuthClient>, AppError>;
+    async fn find_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>, AppError>;
+    /// Deletes the client along with its outstanding authorization codes.
+    async fn delete_oauth_client(&self, client_id: &str) -> Result<(), AppError>;
+    async fn insert_authorization_code(&self, code: &AuthorizationCode) -> Result<(), AppError>;
+    /// Removes and returns an unexpired authorization code, so each one works once.
+    async fn consume_authorization_code(&self, code_hash: &str) -> Result<Option<AuthorizationCode>, AppError>;
+}
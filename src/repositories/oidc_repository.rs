@@ -0,0 +1,16 @@
+use rocket::async_trait;
+
+use crate::errors::app_error::AppError;
+use crate::models::oidc::{ExternalIdentity, OidcLoginState};
+
+/// Pending provider logins and the external identities linked to users.
+#[async_trait]
+pub trait OidcRepository: Send + Sync {
+    async fn insert_oidc_state(&self, state: &OidcLoginState) -> Result<(), AppError>;
+    /// Removes and returns an unexpired login state, so each one works once.
+    async fn consume_oidc_state(&self, state_hash: &str) -> Result<Option<OidcLoginState>, AppError>;
+    async fn find_identity_user(&self, provider: &str, subject: &str) -> Result<Option<String>, AppError>;
+    async fn link_identity(&self, identity: &ExternalIdentity, user_id: &str) -> Result<(), AppError>;
+    /// Creates a user without a password, linked to `identity`, and returns its id.
+    async fn create_user_with_identity(&self, identity: &ExternalIdentity, name: &str, email: &str) -> Result<String, AppError>;
+}
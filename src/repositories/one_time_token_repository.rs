@@ -0,0 +1,18 @@
+use std::time::SystemTime;
+use rocket::async_trait;
+
+use crate::errors::app_error::AppError;
+use crate::models::auth::{OneTimeToken, TokenPurpose};
+
+/// Single-use tokens sent by email.
+#[async_trait]
+pub trait OneTimeTokenRepository: Send + Sync {
+    async fn insert_one_time_token(&self, token: &OneTimeToken) -> Result<(), AppError>;
+    /// Atomically uses up an unexpired token, so a token works at most once
+    /// even when two requests present it together.
+    async fn consume_one_time_token(&self, token_hash: &str, purpose: TokenPurpose) -> Result<Option<OneTimeToken>, AppError>;
+    /// Uses up every outstanding `purpose` token of the user, returning how many.
+    async fn revoke_user_one_time_tokens(&self, user_id: &str, purpose: TokenPurpose) -> Result<u64, AppError>;
+    /// Counts tokens sent to `email` since `since`, for rate limiting.
+    async fn count_one_time_tokens_since(&self, email: &str, purpose: TokenPurpose, since: SystemTime) -> Result<u64, AppError>;
+}
@@ -0,0 +1,92 @@
+use std::time::SystemTime;
+use rocket::async_trait;
+use tokio_postgres::Row;
+use log::{info, error};
+
+use crate::db::postgres::{checkout, PgPool};
+use crate::errors::app_error::AppError;
+use crate::models::api_key::ApiKey;
+use crate::models::role::{parse_names, Permission};
+use crate::repositories::api_key_repository::ApiKeyRepository;
+
+const API_KEY_COLUMNS: &str =
+    "id, name, prefix, scopes, created_at, expires_at, last_used_at, revoked_at IS NOT NULL AS revoked";
+
+fn row_to_api_key(row: &Row) -> ApiKey {
+    ApiKey {
+        id: row.get::<_, i64>("id").to_string(),
+        name: row.get("name"),
+        prefix: row.get("prefix"),
+        scopes: parse_names(row.get::<_, Vec<String>>("scopes").iter().map(String::as_str)),
+        created_at: row.get("created_at"),
+        expires_at: row.get("expires_at"),
+        last_used_at: row.get("last_used_at"),
+        revoked: row.get("revoked"),
+    }
+}
+
+fn not_found() -> AppError {
+    AppError::NotFound("API key not found".to_string())
+}
+
+#[async_trait]
+impl ApiKeyRepository for PgPool {
+    async fn insert_api_key(
+        &self,
+        name: &str,
+        prefix: &str,
+        key_hash: &str,
+        scopes: &[Permission],
+        expires_at: Option<SystemTime>
+    ) -> Result<ApiKey, AppError> {
+        info!("Inserting API key {} into PostgreSQL", name);
+        let scopes = scopes.iter().map(|scope| scope.as_str()).collect::<Vec<_>>();
+        let conn = checkout(self).await?;
+        let row = conn.query_one(
+            &format!(
+                "INSERT INTO api_keys (name, prefix, key_hash, scopes, expires_at) VALUES ($1, $2, $3, $4, $5)
+                 RETURNING {}", API_KEY_COLUMNS
+            ),
+            &[&name, &prefix, &key_hash, &scopes, &expires_at]
+        ).await?;
+        Ok(row_to_api_key(&row))
+    }
+
+    async fn list_api_keys(&self) -> Result<Vec<ApiKey>, AppError> {
+        let conn = checkout(self).await?;
+        let rows = conn.query(&format!("SELECT {} FROM api_keys ORDER BY id", API_KEY_COLUMNS), &[]).await?;
+        Ok(rows.iter().map(row_to_api_key).collect())
+    }
+
+    async fn find_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError> {
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            &format!("SELECT {} FROM api_keys WHERE key_hash = $1", API_KEY_COLUMNS),
+            &[&key_hash]
+        ).await?;
+        Ok(row.as_ref().map(row_to_api_key))
+    }
+
+    async fn touch_api_key(&self, id: &str) -> Result<(), AppError> {
+        let id = id.parse::<i64>().map_err(|_| not_found())?;
+        let conn = checkout(self).await?;
+        conn.execute("UPDATE api_keys SET last_used_at = NOW() WHERE id = $1", &[&id]).await?;
+        Ok(())
+    }
+
+    async fn revoke_api_key(&self, id: &str) -> Result<(), AppError> {
+        info!("Revoking API key {} in PostgreSQL", id);
+        let key_id = id.parse::<i64>().map_err(|_| not_found())?;
+        let conn = checkout(self).await?;
+        let rows_affected = conn.execute(
+            "UPDATE api_keys SET revoked_at = COALESCE(revoked_at, NOW()) WHERE id = $1",
+            &[&key_id]
+        ).await?;
+
+        if rows_affected == 0 {
+            error!("API key not found: {}", id);
+            return Err(not_found());
+        }
+        Ok(())
+    }
+}
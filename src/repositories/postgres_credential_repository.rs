@@ -0,0 +1,98 @@
+use rocket::async_trait;
+use tokio_postgres::Row;
+use log::{info, error};
+
+use crate::db::postgres::{checkout, PgPool};
+use crate::errors::app_error::AppError;
+use crate::models::auth::Credentials;
+use crate::models::role::{parse_names, Role};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::utils::secret::Secret;
+
+/// Parses a string user id; ids that are not integers cannot exist in PostgreSQL.
+pub fn parse_user_id(user_id: &str) -> Result<i32, AppError> {
+    user_id.parse::<i32>().map_err(|_| {
+        error!("Invalid PostgreSQL user id: {}", user_id);
+        AppError::NotFound("User not found".to_string())
+    })
+}
+
+fn row_to_credentials(row: &Row) -> Credentials {
+    Credentials {
+        user_id: row.get::<_, i32>("id").to_string(),
+        name: row.get("name"),
+        email: row.get("email"),
+        password_hash: row.get::<_, Option<String>>("password_hash").map(Secret::new),
+        roles: parse_names(row.get::<_, Vec<String>>("roles").iter().map(String::as_str)),
+        email_verified: row.get("email_verified"),
+        totp_secret: row.get::<_, Option<String>>("totp_secret").map(Secret::new),
+        totp_enabled: row.get("totp_enabled"),
+    }
+}
+
+#[async_trait]
+impl CredentialRepository for PgPool {
+    async fn find_credentials_by_email(&self, email: &str) -> Result<Option<Credentials>, AppError> {
+        info!("Fetching credentials from PostgreSQL");
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            "SELECT id, name, email, password_hash, roles, email_verified, totp_secret, totp_enabled FROM users WHERE LOWER(email) = LOWER($1)",
+            &[&email]
+        ).await?;
+
+        Ok(row.as_ref().map(row_to_credentials))
+    }
+
+    async fn find_credentials_by_id(&self, user_id: &str) -> Result<Option<Credentials>, AppError> {
+        info!("Fetching credentials of user {} from PostgreSQL", user_id);
+        let Ok(id) = user_id.parse::<i32>() else {
+            return Ok(None);
+        };
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            "SELECT id, name, email, password_hash, roles, email_verified, totp_secret, totp_enabled FROM users WHERE id = $1",
+            &[&id]
+        ).await?;
+
+        Ok(row.as_ref().map(row_to_credentials))
+    }
+
+    async fn set_password_hash(&self, user_id: &str, password_hash: &str) -> Result<(), AppError> {
+        info!("Updating password hash of user {} in PostgreSQL", user_id);
+        let id = parse_user_id(user_id)?;
+        let conn = checkout(self).await?;
+        let rows_affected = conn.execute(
+            "UPDATE users SET password_hash = $1 WHERE id = $2",
+            &[&password_hash, &id]
+        ).await?;
+
+        if rows_affected == 0 {
+            error!("User not found for password update: {}", user_id);
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+        Ok(())
+    }
+    async fn set_roles(&self, user_id: &str, roles: &[Role]) -> Result<(), AppError> {
+        info!("Setting roles of user {} in PostgreSQL: {:?}", user_id, roles);
+        let id = parse_user_id(user_id)?;
+        let roles = roles.iter().map(|role| role.as_str()).collect::<Vec<_>>();
+        let conn = checkout(self).await?;
+        let rows_affected = conn.execute("UPDATE users SET roles = $1 WHERE id = $2", &[&roles, &id]).await?;
+
+        if rows_affected == 0 {
+            error!("User not found for role update: {}", user_id);
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+        Ok(())
+    }
+    async fn mark_email_verified(&self, user_id: &str, email: &str) -> Result<bool, AppError> {
+        info!("Marking email of user {} as verified in PostgreSQL", user_id);
+        let id = parse_user_id(user_id)?;
+        let conn = checkout(self).await?;
+        let rows_affected = conn.execute(
+            "UPDATE users SET email_verified = TRUE WHERE id = $1 AND LOWER(email) = LOWER($2)",
+            &[&id, &email]
+        ).await?;
+        Ok(rows_affected == 1)
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::{Duration, SystemTime};
+
+use rocket::async_trait;
+use tokio_postgres::Row;
+use log::info;
+
+use crate::db::postgres::{checkout, PgPool};
+use crate::errors::app_error::AppError;
+use crate::models::lockout::{LockoutScope, LoginFailures};
+use crate::repositories::login_failure_repository::LoginFailureRepository;
+
+fn row_to_login_failures(row: &Row) -> LoginFailures {
+    LoginFailures {
+        failures: row.get::<_, i32>("failures").max(0) as u32,
+        last_failure_at: row.get("last_failure_at"),
+        locked_until: row.get("locked_until"),
+    }
+}
+
+#[async_trait]
+impl LoginFailureRepository for PgPool {
+    async fn find_login_failures(&self, scope: LockoutScope, key: &str) -> Result<Option<LoginFailures>, AppError> {
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            "SELECT failures, last_failure_at, locked_until FROM login_failures WHERE scope = $1 AND key = $2",
+            &[&scope.as_str(), &key]
+        ).await?;
+        Ok(row.as_ref().map(row_to_login_failures))
+    }
+
+    async fn record_login_failure(
+        &self,
+        scope: LockoutScope,
+        key: &str,
+        window: Duration
+    ) -> Result<LoginFailures, AppError> {
+        let conn = checkout(self).await?;
+        let row = conn.query_one(
+            "INSERT INTO login_failures (scope, key, failures, last_failure_at) VALUES ($1, $2, 1, NOW())
+             ON CONFLICT (scope, key) DO UPDATE SET
+                 failures = CASE WHEN login_failures.last_failure_at < NOW() - make_interval(secs => $3)
+                                 THEN 1 ELSE login_failures.failures + 1 END,
+                 last_failure_at = NOW()
+             RETURNING failures, last_failure_at, locked_until",
+            &[&scope.as_str(), &key, &window.as_secs_f64()]
+        ).await?;
+        Ok(row_to_login_failures(&row))
+    }
+
+    async fn lock_login(&self, scope: LockoutScope, key: &str, until: SystemTime) -> Result<(), AppError> {
+        info!("Locking {} {} in PostgreSQL", scope.as_str(), key);
+        let conn = checkout(self).await?;
+        conn.execute(
+            "UPDATE login_failures SET locked_until = $3 WHERE scope = $1 AND key = $2",
+            &[&scope.as_str(), &key, &until]
+        ).await?;
+        Ok(())
+    }
+
+    async fn clear_login_failures(&self, scope: LockoutScope, key: &str) -> Result<bool, AppError> {
+        let conn = checkout(self).await?;
+        let rows_affected = conn.execute(
+            "DELETE FROM login_failures WHERE scope = $1 AND key = $2",
+            &[&scope.as_str(), &key]
+        ).await?;
+        Ok(rows_affected == 1)
+    }
+}
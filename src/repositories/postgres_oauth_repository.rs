@@ -0,0 +1,104 @@
+use rocket::async_trait;
+use tokio_postgres::Row;
+use log::info;
+
+use crate::db::postgres::{checkout, PgPool};
+use crate::errors::app_error::AppError;
+use crate::models::oauth::{AuthorizationCode, GrantType, OAuthClient};
+use crate::repositories::oauth_repository::OAuthRepository;
+use crate::repositories::postgres_credential_repository::parse_user_id;
+
+const CLIENT_COLUMNS: &str = "client_id, client_secret_hash, name, redirect_uris, grant_types, scopes, created_at";
+
+fn row_to_client(row: &Row) -> OAuthClient {
+    OAuthClient {
+        client_id: row.get("client_id"),
+        client_secret_hash: row.get("client_secret_hash"),
+        name: row.get("name"),
+        redirect_uris: row.get("redirect_uris"),
+        grant_types: row.get::<_, Vec<String>>("grant_types").iter()
+            .filter_map(|grant| grant.parse().ok())
+            .collect(),
+        scopes: row.get("scopes"),
+        confidential: row.get::<_, Option<String>>("client_secret_hash").is_some(),
+        created_at: row.get("created_at"),
+    }
+}
+
+#[async_trait]
+impl OAuthRepository for PgPool {
+    async fn insert_oauth_client(&self, client: &OAuthClient) -> Result<(), AppError> {
+        info!("Registering OAuth client {} in PostgreSQL", client.client_id);
+        let grant_types: Vec<&str> = client.grant_types.iter().map(|grant| GrantType::as_str(*grant)).collect();
+        let conn = checkout(self).await?;
+        conn.execute(
+            "INSERT INTO oauth_clients (client_id, client_secret_hash, name, redirect_uris, grant_types, scopes, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            &[
+                &client.client_id, &client.client_secret_hash, &client.name, &client.redirect_uris,
+                &grant_types, &client.scopes, &client.created_at,
+            ]
+        ).await?;
+        Ok(())
+    }
+
+    async fn list_oauth_clients(&self) -> Result<Vec<OAuthClient>, AppError> {
+        let conn = checkout(self).await?;
+        let rows = conn.query(&format!("SELECT {} FROM oauth_clients ORDER BY created_at", CLIENT_COLUMNS), &[]).await?;
+        Ok(rows.iter().map(row_to_client).collect())
+    }
+
+    async fn find_oauth_client(&self, client_id: &str) -> Result<Option<OAuthClient>, AppError> {
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            &format!("SELECT {} FROM oauth_clients WHERE client_id = $1", CLIENT_COLUMNS),
+            &[&client_id]
+        ).await?;
+        Ok(row.as_ref().map(row_to_client))
+    }
+
+    async fn delete_oauth_client(&self, client_id: &str) -> Result<(), AppError> {
+        info!("Deleting OAuth client {} in PostgreSQL", client_id);
+        let conn = checkout(self).await?;
+        let deleted = conn.execute("DELETE FROM oauth_clients WHERE client_id = $1", &[&client_id]).await?;
+        if deleted == 0 {
+            return Err(AppError::NotFound("OAuth client not found".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn insert_authorization_code(&self, code: &AuthorizationCode) -> Result<(), AppError> {
+        let user_id = parse_user_id(&code.user_id)?;
+        let conn = checkout(self).await?;
+        conn.execute(
+            "INSERT INTO oauth_authorization_codes
+             (code_hash, client_id, user_id, redirect_uri, scope, nonce, code_challenge, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[
+                &code.code_hash, &code.client_id, &user_id, &code.redirect_uri, &code.scope,
+                &code.nonce, &code.code_challenge, &code.expires_at,
+            ]
+        ).await?;
+        Ok(())
+    }
+
+    async fn consume_authorization_code(&self, code_hash: &str) -> Result<Option<AuthorizationCode>, AppError> {
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            "DELETE FROM oauth_authorization_codes WHERE code_hash = $1 AND expires_at > NOW()
+             RETURNING code_hash, client_id, user_id, redirect_uri, scope, nonce, code_challenge, expires_at",
+            &[&code_hash]
+        ).await?;
+
+        Ok(row.map(|row| AuthorizationCode {
+            code_hash: row.get("code_hash"),
+            client_id: row.get("client_id"),
+            user_id: row.get::<_, i32>("user_id").to_string(),
+            redirect_uri: row.get("redirect_uri"),
+            scope: row.get("scope"),
+            nonce: row.get("nonce"),
+            code_challenge: row.get("code_challenge"),
+            expires_at: row.get("expires_at"),
+        }))
+    }
+}
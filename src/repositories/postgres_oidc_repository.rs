@@ -0,0 +1,83 @@
+use rocket::async_trait;
+use log::{info, error};
+
+use crate::db::postgres::{checkout, is_unique_violation, PgPool};
+use crate::errors::app_error::AppError;
+use crate::models::oidc::{ExternalIdentity, OidcLoginState};
+use crate::repositories::oidc_repository::OidcRepository;
+use crate::repositories::postgres_credential_repository::parse_user_id;
+
+#[async_trait]
+impl OidcRepository for PgPool {
+    async fn insert_oidc_state(&self, state: &OidcLoginState) -> Result<(), AppError> {
+        let conn = checkout(self).await?;
+        conn.execute(
+            "INSERT INTO oidc_login_states (state_hash, provider, nonce, code_verifier, expires_at)
+             VALUES ($1, $2, $3, $4, $5)",
+            &[&state.state_hash, &state.provider, &state.nonce, &state.code_verifier, &state.expires_at]
+        ).await?;
+        Ok(())
+    }
+
+    async fn consume_oidc_state(&self, state_hash: &str) -> Result<Option<OidcLoginState>, AppError> {
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            "DELETE FROM oidc_login_states WHERE state_hash = $1 AND expires_at > NOW()
+             RETURNING state_hash, provider, nonce, code_verifier, expires_at",
+            &[&state_hash]
+        ).await?;
+
+        Ok(row.map(|row| OidcLoginState {
+            state_hash: row.get("state_hash"),
+            provider: row.get("provider"),
+            nonce: row.get("nonce"),
+            code_verifier: row.get("code_verifier"),
+            expires_at: row.get("expires_at"),
+        }))
+    }
+
+    async fn find_identity_user(&self, provider: &str, subject: &str) -> Result<Option<String>, AppError> {
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            "SELECT user_id FROM external_identities WHERE provider = $1 AND subject = $2",
+            &[&provider, &subject]
+        ).await?;
+        Ok(row.map(|row| row.get::<_, i32>("user_id").to_string()))
+    }
+
+    async fn link_identity(&self, identity: &ExternalIdentity, user_id: &str) -> Result<(), AppError> {
+        info!("Linking {} identity to user {} in PostgreSQL", identity.provider, user_id);
+        let id = parse_user_id(user_id)?;
+        let conn = checkout(self).await?;
+        conn.execute(
+            "INSERT INTO external_identities (provider, subject, user_id, email) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (provider, subject) DO NOTHING",
+            &[&identity.provider, &identity.subject, &id, &identity.email]
+        ).await?;
+        Ok(())
+    }
+
+    async fn create_user_with_identity(&self, identity: &ExternalIdentity, name: &str, email: &str) -> Result<String, AppError> {
+        info!("Creating user for {} identity in PostgreSQL", identity.provider);
+        let mut conn = checkout(self).await?;
+        let transaction = conn.transaction().await?;
+        let row = transaction.query_one(
+            "INSERT INTO users (name, email, email_verified) VALUES ($1, $2, $3) RETURNING id",
+            &[&name, &email, &identity.email_verified]
+        ).await.map_err(|e| {
+            if is_unique_violation(&e) {
+                error!("Duplicate email rejected by PostgreSQL: {}", e);
+                return AppError::Conflict("A user with this email already exists".to_string());
+            }
+            e.into()
+        })?;
+        let id: i32 = row.get("id");
+
+        transaction.execute(
+            "INSERT INTO external_identities (provider, subject, user_id, email) VALUES ($1, $2, $3, $4)",
+            &[&identity.provider, &identity.subject, &id, &identity.email]
+        ).await?;
+        transaction.commit().await?;
+        Ok(id.to_string())
+    }
+}
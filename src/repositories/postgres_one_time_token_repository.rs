@@ -0,0 +1,59 @@
+use std::time::SystemTime;
+use rocket::async_trait;
+use log::info;
+
+use crate::db::postgres::{checkout, PgPool};
+use crate::errors::app_error::AppError;
+use crate::models::auth::{OneTimeToken, TokenPurpose};
+use crate::repositories::one_time_token_repository::OneTimeTokenRepository;
+use crate::repositories::postgres_credential_repository::parse_user_id;
+
+#[async_trait]
+impl OneTimeTokenRepository for PgPool {
+    async fn insert_one_time_token(&self, token: &OneTimeToken) -> Result<(), AppError> {
+        info!("Storing {} token for user {} in PostgreSQL", token.purpose.as_str(), token.user_id);
+        let user_id = parse_user_id(&token.user_id)?;
+        let conn = checkout(self).await?;
+        conn.execute(
+            "INSERT INTO one_time_tokens (token_hash, purpose, user_id, email, expires_at) VALUES ($1, $2, $3, $4, $5)",
+            &[&token.token_hash, &token.purpose.as_str(), &user_id, &token.email, &token.expires_at]
+        ).await?;
+        Ok(())
+    }
+
+    async fn consume_one_time_token(&self, token_hash: &str, purpose: TokenPurpose) -> Result<Option<OneTimeToken>, AppError> {
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            "UPDATE one_time_tokens SET used_at = NOW()
+             WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > NOW()
+             RETURNING token_hash, user_id, email, expires_at",
+            &[&token_hash, &purpose.as_str()]
+        ).await?;
+
+        Ok(row.map(|row| OneTimeToken {
+            token_hash: row.get("token_hash"),
+            purpose,
+            user_id: row.get::<_, i32>("user_id").to_string(),
+            email: row.get("email"),
+            expires_at: row.get("expires_at"),
+        }))
+    }
+
+    async fn revoke_user_one_time_tokens(&self, user_id: &str, purpose: TokenPurpose) -> Result<u64, AppError> {
+        let user_id = parse_user_id(user_id)?;
+        let conn = checkout(self).await?;
+        Ok(conn.execute(
+            "UPDATE one_time_tokens SET used_at = NOW() WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL",
+            &[&user_id, &purpose.as_str()]
+        ).await?)
+    }
+
+    async fn count_one_time_tokens_since(&self, email: &str, purpose: TokenPurpose, since: SystemTime) -> Result<u64, AppError> {
+        let conn = checkout(self).await?;
+        let count: i64 = conn.query_one(
+            "SELECT COUNT(*) FROM one_time_tokens WHERE LOWER(email) = LOWER($1) AND purpose = $2 AND created_at >= $3",
+            &[&email, &purpose.as_str(), &since]
+        ).await?.get(0);
+        Ok(count as u64)
+    }
+}
@@ -0,0 +1,74 @@
+use rocket::async_trait;
+use tokio_postgres::Row;
+use log::info;
+
+use crate::db::postgres::{checkout, PgPool};
+use crate::errors::app_error::AppError;
+use crate::models::auth::RefreshToken;
+use crate::repositories::postgres_credential_repository::parse_user_id;
+use crate::repositories::refresh_token_repository::RefreshTokenRepository;
+
+fn row_to_refresh_token(row: &Row) -> RefreshToken {
+    RefreshToken {
+        token_hash: row.get("token_hash"),
+        user_id: row.get::<_, i32>("user_id").to_string(),
+        family_id: row.get("family_id"),
+        expires_at: row.get("expires_at"),
+        used: row.get("used"),
+        revoked: row.get("revoked"),
+    }
+}
+
+#[async_trait]
+impl RefreshTokenRepository for PgPool {
+    async fn insert_refresh_token(&self, token: &RefreshToken) -> Result<(), AppError> {
+        info!("Storing refresh token for user {} in PostgreSQL", token.user_id);
+        let user_id = parse_user_id(&token.user_id)?;
+        let conn = checkout(self).await?;
+        conn.execute(
+            "INSERT INTO refresh_tokens (token_hash, user_id, family_id, expires_at) VALUES ($1, $2, $3, $4)",
+            &[&token.token_hash, &user_id, &token.family_id, &token.expires_at]
+        ).await?;
+        Ok(())
+    }
+
+    async fn find_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshToken>, AppError> {
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            "SELECT token_hash, user_id, family_id, expires_at,
+                    used_at IS NOT NULL AS used, revoked_at IS NOT NULL AS revoked
+             FROM refresh_tokens WHERE token_hash = $1",
+            &[&token_hash]
+        ).await?;
+        Ok(row.as_ref().map(row_to_refresh_token))
+    }
+
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<bool, AppError> {
+        let conn = checkout(self).await?;
+        let rows_affected = conn.execute(
+            "UPDATE refresh_tokens SET used_at = NOW()
+             WHERE token_hash = $1 AND used_at IS NULL AND revoked_at IS NULL",
+            &[&token_hash]
+        ).await?;
+        Ok(rows_affected == 1)
+    }
+
+    async fn revoke_refresh_token_family(&self, family_id: &str) -> Result<u64, AppError> {
+        info!("Revoking refresh token family {} in PostgreSQL", family_id);
+        let conn = checkout(self).await?;
+        Ok(conn.execute(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+            &[&family_id]
+        ).await?)
+    }
+
+    async fn revoke_user_refresh_tokens(&self, user_id: &str) -> Result<u64, AppError> {
+        info!("Revoking all refresh tokens of user {} in PostgreSQL", user_id);
+        let id = parse_user_id(user_id)?;
+        let conn = checkout(self).await?;
+        Ok(conn.execute(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+            &[&id]
+        ).await?)
+    }
+}
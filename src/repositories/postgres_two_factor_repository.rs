@@ -0,0 +1,66 @@
+use rocket::async_trait;
+use log::info;
+
+use crate::db::postgres::{checkout, PgPool};
+use crate::errors::app_error::AppError;
+use crate::repositories::postgres_credential_repository::parse_user_id;
+use crate::repositories::two_factor_repository::TwoFactorRepository;
+
+#[async_trait]
+impl TwoFactorRepository for PgPool {
+    async fn set_pending_totp_secret(&self, user_id: &str, secret: &str) -> Result<bool, AppError> {
+        info!("Storing pending TOTP secret of user {} in PostgreSQL", user_id);
+        let id = parse_user_id(user_id)?;
+        let conn = checkout(self).await?;
+        let rows_affected = conn.execute(
+            "UPDATE users SET totp_secret = $1, totp_enabled = FALSE, totp_last_step = NULL, recovery_code_hashes = '{}'
+             WHERE id = $2 AND NOT totp_enabled",
+            &[&secret, &id]
+        ).await?;
+        Ok(rows_affected == 1)
+    }
+
+    async fn enable_totp(&self, user_id: &str, recovery_code_hashes: &[String]) -> Result<(), AppError> {
+        info!("Enabling TOTP for user {} in PostgreSQL", user_id);
+        let id = parse_user_id(user_id)?;
+        let conn = checkout(self).await?;
+        conn.execute(
+            "UPDATE users SET totp_enabled = TRUE, recovery_code_hashes = $1 WHERE id = $2 AND totp_secret IS NOT NULL",
+            &[&recovery_code_hashes, &id]
+        ).await?;
+        Ok(())
+    }
+
+    async fn disable_totp(&self, user_id: &str) -> Result<(), AppError> {
+        info!("Disabling TOTP for user {} in PostgreSQL", user_id);
+        let id = parse_user_id(user_id)?;
+        let conn = checkout(self).await?;
+        conn.execute(
+            "UPDATE users SET totp_secret = NULL, totp_enabled = FALSE, totp_last_step = NULL, recovery_code_hashes = '{}'
+             WHERE id = $1",
+            &[&id]
+        ).await?;
+        Ok(())
+    }
+
+    async fn record_totp_step(&self, user_id: &str, step: i64) -> Result<bool, AppError> {
+        let id = parse_user_id(user_id)?;
+        let conn = checkout(self).await?;
+        let rows_affected = conn.execute(
+            "UPDATE users SET totp_last_step = $1 WHERE id = $2 AND (totp_last_step IS NULL OR totp_last_step < $1)",
+            &[&step, &id]
+        ).await?;
+        Ok(rows_affected == 1)
+    }
+
+    async fn consume_recovery_code(&self, user_id: &str, code_hash: &str) -> Result<bool, AppError> {
+        let id = parse_user_id(user_id)?;
+        let conn = checkout(self).await?;
+        let rows_affected = conn.execute(
+            "UPDATE users SET recovery_code_hashes = array_remove(recovery_code_hashes, $1)
+             WHERE id = $2 AND $1 = ANY(recovery_code_hashes)",
+            &[&code_hash, &id]
+        ).await?;
+        Ok(rows_affected == 1)
+    }
+}
@@ -0,0 +1,223 @@
+use rocket::async_trait;
+use tokio_postgres::Row;
+use tokio_postgres::types::ToSql;
+use log::{info, error};
+
+use crate::db::postgres::{checkout, is_unique_violation, PgConnection, PgPool};
+use crate::errors::app_error::AppError;
+use crate::models::pagination::{ListOptions, Page, PageCursor, SortDirection, UserSortField};
+use crate::models::user::{User, UserPatch};
+use crate::repositories::user_repository::UserRepository;
+
+fn row_to_user(row: &Row) -> User {
+    User {
+        id: Some(row.get("id")),
+        name: row.get("name"),
+        email: row.get("email"),
+        version: Some(row.get("version")),
+        email_verified: Some(row.get("email_verified")),
+        password: None,
+    }
+}
+
+/// Escapes `%`, `_` and `\\` so user input is matched literally by `LIKE`.
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn map_write_error(error: tokio_postgres::Error) -> AppError {
+    if is_unique_violation(&error) {
+        error!("Duplicate email rejected by PostgreSQL: {}", error);
+        return AppError::Conflict("A user with this email already exists".to_string());
+    }
+    error.into()
+}
+
+/// Explains why a conditional write matched no row: the user is either gone or
+/// at a version the caller did not expect.
+async fn missed_write(conn: &PgConnection<'_>, id: i32) -> AppError {
+    match conn.query_opt("SELECT 1 FROM users WHERE id = $1", &[&id]).await {
+        Ok(Some(_)) => {
+            error!("Version precondition failed for user {}", id);
+            AppError::PreconditionFailed("User has been modified since it was read".to_string())
+        }
+        Ok(None) => {
+            error!("User not found: {}", id);
+            AppError::NotFound("User not found".to_string())
+        }
+        Err(e) => e.into(),
+    }
+}
+
+fn sort_column(sort: UserSortField) -> &'static str {
+    match sort {
+        UserSortField::Id => "id",
+        UserSortField::Name => "name",
+        UserSortField::Email => "email",
+    }
+}
+
+fn cursor_for(user: &User, options: &ListOptions) -> PageCursor {
+    PageCursor {
+        sort: options.sort,
+        direction: options.direction,
+        key: match options.sort {
+            UserSortField::Id => None,
+            UserSortField::Name => Some(user.name.clone()),
+            UserSortField::Email => Some(user.email.clone()),
+        },
+        id: user.id.unwrap_or_default().to_string(),
+    }
+}
+
+#[async_trait]
+impl UserRepository for PgPool {
+    type Id = i32;
+    type User = User;
+
+    async fn create(&self, user: User, password_hash: Option<String>) -> Result<User, AppError> {
+        info!("Inserting user into PostgreSQL: {:?}", user);
+        let conn = checkout(self).await?;
+        let row = conn.query_one(
+            "INSERT INTO users (name, email, password_hash) VALUES ($1, $2, $3)
+             RETURNING id, name, email, version, email_verified",
+            &[&user.name, &user.email, &password_hash]
+        ).await.map_err(map_write_error)?;
+
+        let created = row_to_user(&row);
+        info!("User inserted with id: {:?}", created.id);
+        Ok(created)
+    }
+
+    async fn get(&self, id: i32) -> Result<User, AppError> {
+        info!("Fetching user {} from PostgreSQL", id);
+        let conn = checkout(self).await?;
+        let row = conn.query_opt("SELECT id, name, email, version, email_verified FROM users WHERE id = $1", &[&id]).await?
+            .ok_or_else(|| {
+                error!("User not found: {}", id);
+                AppError::NotFound("User not found".to_string())
+            })?;
+
+        Ok(row_to_user(&row))
+    }
+
+    async fn list(&self, options: &ListOptions) -> Result<Page<User>, AppError> {
+        info!("Fetching users from PostgreSQL: {:?}", options);
+        let mut conditions: Vec<String> = Vec::new();
+        let mut params: Vec<Box<dyn ToSql + Sync + Send>> = Vec::new();
+
+        if let Some(email) = &options.email_contains {
+            params.push(Box::new(format!("%{}%", escape_like(email))));
+            conditions.push(format!("email ILIKE ${}", params.len()));
+        }
+        if let Some(name) = &options.name_prefix {
+            params.push(Box::new(format!("{}%", escape_like(name))));
+            conditions.push(format!("name ILIKE ${}", params.len()));
+        }
+
+        let filter_count = params.len();
+        let filters = |conditions: &[String]| if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+
+        let conn = checkout(self).await?;
+        let count_params: Vec<&(dyn ToSql + Sync)> = params[..filter_count].iter()
+            .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+            .collect();
+        let total: i64 = conn.query_one(
+            &format!("SELECT COUNT(*) FROM users{}", filters(&conditions)),
+            &count_params
+        ).await?.get(0);
+
+        let column = sort_column(options.sort);
+        let (direction, comparison) = match options.direction {
+            SortDirection::Asc => ("ASC", ">"),
+            SortDirection::Desc => ("DESC", "<"),
+        };
+
+        let mut offset = options.offset;
+        if let Some(cursor) = &options.cursor {
+            let id = cursor.id.parse::<i32>()
+                .map_err(|_| AppError::BadRequest("Invalid cursor".to_string()))?;
+            params.push(Box::new(id));
+            match &cursor.key {
+                Some(key) if options.sort != UserSortField::Id => {
+                    params.push(Box::new(key.clone()));
+                    conditions.push(format!(
+                        "({}, id) {} (${}, ${})", column, comparison, params.len(), params.len() - 1
+                    ));
+                }
+                _ => conditions.push(format!("id {} ${}", comparison, params.len())),
+            }
+            offset = 0;
+        }
+
+        params.push(Box::new(options.limit + 1));
+        params.push(Box::new(offset));
+        let query = format!(
+            "SELECT id, name, email, version, email_verified FROM users{} ORDER BY {} {}, id {} LIMIT ${} OFFSET ${}",
+            filters(&conditions), column, direction, direction, params.len() - 1, params.len()
+        );
+
+        let page_params: Vec<&(dyn ToSql + Sync)> = params.iter()
+            .map(|p| p.as_ref() as &(dyn ToSql + Sync))
+            .collect();
+        let rows = conn.query(&query, &page_params).await?;
+        let users = rows.iter().map(row_to_user).collect::<Vec<User>>();
+
+        info!("Successfully fetched {} of {} users from PostgreSQL", users.len().min(options.limit as usize), total);
+        Ok(Page::from_rows(users, total, options, |user| cursor_for(user, options)))
+    }
+
+    async fn update(&self, id: i32, user: User, expected_versions: Option<Vec<i64>>) -> Result<User, AppError> {
+        info!("Updating user {} in PostgreSQL", id);
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            "UPDATE users SET name = $1, email = $2, version = version + 1,
+                 email_verified = email_verified AND LOWER(email) = LOWER($2)
+             WHERE id = $3 AND ($4::BIGINT[] IS NULL OR version = ANY($4))
+             RETURNING id, name, email, version, email_verified",
+            &[&user.name, &user.email, &id, &expected_versions]
+        ).await.map_err(map_write_error)?;
+
+        match row {
+            Some(row) => Ok(row_to_user(&row)),
+            None => Err(missed_write(&conn, id).await),
+        }
+    }
+
+    async fn patch(&self, id: i32, patch: UserPatch, expected_versions: Option<Vec<i64>>) -> Result<User, AppError> {
+        info!("Patching user {} in PostgreSQL: {:?}", id, patch);
+        let conn = checkout(self).await?;
+        let row = conn.query_opt(
+            "UPDATE users SET name = COALESCE($1, name), email = COALESCE($2, email), version = version + 1,
+                 email_verified = email_verified AND ($2::TEXT IS NULL OR LOWER(email) = LOWER($2))
+             WHERE id = $3 AND ($4::BIGINT[] IS NULL OR version = ANY($4))
+             RETURNING id, name, email, version, email_verified",
+            &[&patch.name, &patch.email, &id, &expected_versions]
+        ).await.map_err(map_write_error)?;
+
+        match row {
+            Some(row) => Ok(row_to_user(&row)),
+            None => Err(missed_write(&conn, id).await),
+        }
+    }
+
+    async fn delete(&self, id: i32, expected_versions: Option<Vec<i64>>) -> Result<(), AppError> {
+        info!("Deleting user {} from PostgreSQL", id);
+        let conn = checkout(self).await?;
+        let rows_affected = conn.execute(
+            "DELETE FROM users WHERE id = $1 AND ($2::BIGINT[] IS NULL OR version = ANY($2))",
+            &[&id, &expected_versions]
+        ).await?;
+
+        if rows_affected == 0 {
+            return Err(missed_write(&conn, id).await);
+        }
+
+        info!("User deleted successfully: {}", id);
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+use rocket::async_trait;
+
+use crate::errors::app_error::AppError;
+use crate::models::auth::RefreshToken;
+
+/// Server-side state of refresh tokens.
+#[async_trait]
+pub trait RefreshTokenRepository: Send + Sync {
+    async fn insert_refresh_token(&self, token: &RefreshToken) -> Result<(), AppError>;
+    async fn find_refresh_token(&self, token_hash: &str) -> Result<Option<RefreshToken>, AppError>;
+    /// Marks a live token as used. Returns `false` if it was already used or
+    /// revoked, which happens when two requests race with the same token.
+    async fn consume_refresh_token(&self, token_hash: &str) -> Result<bool, AppError>;
+    async fn revoke_refresh_token_family(&self, family_id: &str) -> Result<u64, AppError>;
+    async fn revoke_user_refresh_tokens(&self, user_id: &str) -> Result<u64, AppError>;
+}
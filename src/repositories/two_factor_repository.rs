@@ -0,0 +1,17 @@
+use rocket::async_trait;
+
+use crate::errors::app_error::AppError;
+
+/// TOTP secrets and recovery codes stored alongside users.
+#[async_trait]
+pub trait TwoFactorRepository: Send + Sync {
+    /// Stores a new, not yet enabled secret. Returns `false` if 2FA is already enabled.
+    async fn set_pending_totp_secret(&self, user_id: &str, secret: &str) -> Result<bool, AppError>;
+    async fn enable_totp(&self, user_id: &str, recovery_code_hashes: &[String]) -> Result<(), AppError>;
+    async fn disable_totp(&self, user_id: &str) -> Result<(), AppError>;
+    /// Records the time step of an accepted code. Returns `false` if that step
+    /// or a later one was already used, so a code cannot be replayed.
+    async fn record_totp_step(&self, user_id: &str, step: i64) -> Result<bool, AppError>;
+    /// Removes a recovery code. Returns `false` if the user does not have it.
+    async fn consume_recovery_code(&self, user_id: &str, code_hash: &str) -> Result<bool, AppError>;
+}
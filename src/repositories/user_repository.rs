@@ -0,0 +1,39 @@
+use rocket::async_trait;
+
+use crate::errors::app_error::AppError;
+use crate::models::pagination::{ListOptions, Page};
+use crate::models::user::UserPatch;
+
+/// Storage-agnostic access to users.
+///
+/// Handlers only talk to this trait, so `/postgres` and `/mongo` share the same
+/// behavior, errors and response shapes for every operation.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    type Id: Send + Sync + 'static;
+    type User: Send + Sync;
+
+    /// Stores a new user together with the argon2 hash of its password, if any.
+    async fn create(&self, user: Self::User, password_hash: Option<String>) -> Result<Self::User, AppError>;
+    async fn get(&self, id: Self::Id) -> Result<Self::User, AppError>;
+    async fn list(&self, options: &ListOptions) -> Result<Page<Self::User>, AppError>;
+
+    // Writes take the versions the stored user may have (from `If-Match`) and
+    // fail with `AppError::PreconditionFailed` when it has moved on; `None`
+    // makes them unconditional. Every successful update bumps the version.
+
+    async fn update(
+        &self,
+        id: Self::Id,
+        user: Self::User,
+        expected_versions: Option<Vec<i64>>,
+    ) -> Result<Self::User, AppError>;
+    /// Applies only the fields present in `patch`, in a single atomic write.
+    async fn patch(
+        &self,
+        id: Self::Id,
+        patch: UserPatch,
+        expected_versions: Option<Vec<i64>>,
+    ) -> Result<Self::User, AppError>;
+    async fn delete(&self, id: Self::Id, expected_versions: Option<Vec<i64>>) -> Result<(), AppError>;
+}
@@ -1,21 +1,30 @@
 use rocket::Route;
 
-use crate::handlers::{user_handler, mongo_user_handler};
+#[cfg(feature = "mongo")]
+use crate::handlers::mongo_user_handler;
+#[cfg(feature = "postgres")]
+use crate::handlers::user_handler;
 
+#[cfg(feature = "postgres")]
 pub fn user_routes() -> Vec<Route> {
     routes![
         user_handler::add_user,
         user_handler::get_users,
+        user_handler::get_user,
         user_handler::update_user,
+        user_handler::patch_user,
         user_handler::delete_user
     ]
 }
 
+#[cfg(feature = "mongo")]
 pub fn user_mongo_routes() -> Vec<Route> {
     routes![
         mongo_user_handler::adding_user,
         mongo_user_handler::getting_users,
+        mongo_user_handler::getting_user,
         mongo_user_handler::updating_user,
+        mongo_user_handler::patching_user,
         mongo_user_handler::deleting_user
     ]
 }
\ No newline at end of file
@@ -0,0 +1,81 @@
+use std::time::{Duration, SystemTime};
+
+use crate::errors::app_error::AppError;
+use crate::models::api_key::{CreatedApiKey, NewApiKey};
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::token_service::{generate_opaque_token, hash_opaque_token};
+use crate::utils::authentication::Caller;
+
+/// Marks our keys so they are easy to spot in logs and secret scanners.
+const API_KEY_PREFIX: &str = "usk_";
+/// Characters of the key kept in clear to tell keys apart.
+const VISIBLE_PREFIX_LENGTH: usize = 12;
+
+/// Generates a key and stores its hash; the returned secret is never stored.
+///
+/// The key may only be granted scopes `caller` holds itself, so that managing
+/// API keys is not a way to gain other permissions.
+pub async fn create_api_key(store: &AuthStore, caller: &Caller, new_key: NewApiKey) -> Result<CreatedApiKey, AppError> {
+    if let Some(scope) = new_key.scopes.iter().find(|scope| !caller.has_permission(**scope)) {
+        return Err(AppError::Forbidden(format!("Cannot grant the {} scope, which you do not hold", scope.as_str())));
+    }
+
+    let api_key = format!("{}{}", API_KEY_PREFIX, generate_opaque_token());
+    let expires_at = new_key.expires_in_days
+        .map(|days| SystemTime::now() + Duration::from_secs(u64::from(days) * 24 * 3600));
+
+    let key = store.insert_api_key(
+        &new_key.name,
+        &api_key[..VISIBLE_PREFIX_LENGTH],
+        &hash_opaque_token(&api_key),
+        &new_key.scopes,
+        expires_at
+    ).await?;
+
+    Ok(CreatedApiKey { key, api_key })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::role::{Permission, Role};
+    use crate::repositories::memory_auth_repository::MemoryStore;
+    use crate::utils::authentication::{ApiKeyCaller, AuthenticatedUser};
+
+    fn new_key(scopes: &[Permission]) -> NewApiKey {
+        NewApiKey { name: "ci".to_string(), scopes: scopes.to_vec(), expires_in_days: None }
+    }
+
+    fn service(scopes: &[Permission]) -> Caller {
+        Caller::Service(ApiKeyCaller { key_id: "1".to_string(), name: "provisioner".to_string(), scopes: scopes.to_vec() })
+    }
+
+    #[tokio::test]
+    async fn grants_scopes_the_caller_holds() {
+        let store = MemoryStore::new().into_store();
+        let admin = Caller::User(AuthenticatedUser {
+            user_id: "1".to_string(),
+            email: "admin@example.com".to_string(),
+            roles: vec![Role::Admin],
+        });
+
+        let created = create_api_key(&store, &admin, new_key(&[Permission::ManageRoles, Permission::ListUsers])).await.unwrap();
+        assert!(created.api_key.starts_with(API_KEY_PREFIX));
+        assert_eq!(created.key.scopes, vec![Permission::ManageRoles, Permission::ListUsers]);
+
+        let stored = store.find_api_key_by_hash(&hash_opaque_token(&created.api_key)).await.unwrap().unwrap();
+        assert_eq!(stored.id, created.key.id);
+    }
+
+    #[tokio::test]
+    async fn refuses_scopes_the_caller_does_not_hold() {
+        let store = MemoryStore::new().into_store();
+        let caller = service(&[Permission::ManageApiKeys]);
+
+        let result = create_api_key(&store, &caller, new_key(&[Permission::ManageApiKeys, Permission::ManageRoles])).await;
+        assert!(matches!(result, Err(AppError::Forbidden(message)) if message.contains("manage_roles")));
+        assert!(store.list_api_keys().await.unwrap().is_empty());
+
+        assert!(create_api_key(&store, &caller, new_key(&[Permission::ManageApiKeys])).await.is_ok());
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+
+use log::warn;
+use rocket::request::{FromRequest, Outcome, Request};
+use tokio::task::JoinSet;
+use tokio::time::{timeout, Instant};
+
+use crate::config::health_config::HealthConfig;
+use crate::db::migrations::PendingMigrations;
+#[cfg(feature = "mongo")]
+use crate::db::mongo;
+#[cfg(feature = "postgres")]
+use crate::db::postgres::{self, PgPool};
+use crate::errors::app_error::AppError;
+use crate::models::health::{CheckStatus, DependencyHealth, Readiness};
+
+/// Handles to the databases this instance serves, the disabled ones being
+/// `None`. They are cheap clones, so checks can run as separate tasks.
+pub struct Dependencies {
+    #[cfg(feature = "postgres")]
+    postgres: Option<PgPool>,
+    #[cfg(feature = "mongo")]
+    mongo: Option<mongodb::Database>,
+    pending_migrations: PendingMigrations,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Dependencies {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let rocket = request.rocket();
+        Outcome::Success(Dependencies {
+            #[cfg(feature = "postgres")]
+            postgres: rocket.state::<PgPool>().cloned(),
+            #[cfg(feature = "mongo")]
+            mongo: rocket.state::<mongodb::Database>().cloned(),
+            pending_migrations: rocket.state::<PendingMigrations>().cloned().unwrap_or_default(),
+        })
+    }
+}
+
+/// Probes every dependency concurrently: `SELECT 1` on PostgreSQL and a
+/// `ping` command on MongoDB, each bounded by the configured timeout. One
+/// that answers is still down while its migrations are pending.
+pub async fn readiness(dependencies: Dependencies, config: &HealthConfig) -> Readiness {
+    let mut probes = JoinSet::new();
+
+    #[cfg(feature = "postgres")]
+    if let Some(pool) = dependencies.postgres {
+        probes.spawn(probe("postgres", config, async move { postgres::ping(&pool).await }));
+    }
+    #[cfg(feature = "mongo")]
+    if let Some(db) = dependencies.mongo {
+        probes.spawn(probe("mongo", config, async move { mongo::ping(&db).await }));
+    }
+
+    let mut checks = BTreeMap::new();
+    while let Some(result) = probes.join_next().await {
+        match result {
+            Ok((name, mut health)) => {
+                if health.status == CheckStatus::Up && dependencies.pending_migrations.contains(name) {
+                    health.status = CheckStatus::Down;
+                    health.error = Some("migrations pending");
+                }
+                checks.insert(name, health);
+            }
+            Err(e) => warn!("Health check task failed: {}", e),
+        }
+    }
+    Readiness::new(checks)
+}
+
+/// Runs one check. Returns a future that owns everything it needs, so it can
+/// be spawned.
+fn probe(
+    name: &'static str,
+    config: &HealthConfig,
+    check: impl Future<Output = Result<(), AppError>>,
+) -> impl Future<Output = (&'static str, DependencyHealth)> {
+    let required = config.is_required(name);
+    let limit = config.timeout;
+
+    async move {
+        let started = Instant::now();
+        let outcome = timeout(limit, check).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+
+        let (status, error) = match outcome {
+            Ok(Ok(())) => (CheckStatus::Up, None),
+            Ok(Err(e)) => {
+                warn!("Health check of {} failed: {}", name, e);
+                (CheckStatus::Down, Some("unreachable"))
+            }
+            Err(_) => {
+                warn!("Health check of {} timed out after {} ms", name, limit.as_millis());
+                (CheckStatus::Down, Some("timed out"))
+            }
+        };
+        (name, DependencyHealth { status, required, latency_ms, error })
+    }
+}
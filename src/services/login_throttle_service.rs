@@ -0,0 +1,152 @@
+use std::net::IpAddr;
+use std::time::SystemTime;
+
+use log::warn;
+
+use crate::config::lockout_config::LockoutConfig;
+use crate::errors::app_error::AppError;
+use crate::models::lockout::LockoutScope;
+use crate::repositories::auth_repository::AuthStore;
+
+/// Counters a login attempt is checked against, with their thresholds. The
+/// account is keyed by the email tried, so unknown emails lock the same way
+/// as real ones and the lock does not reveal which accounts exist.
+fn counters(config: &LockoutConfig, email: &str, client_ip: Option<IpAddr>) -> Vec<(LockoutScope, String, u32)> {
+    let mut counters = vec![(LockoutScope::Account, email.to_string(), config.account_max_failures)];
+    if let Some(ip) = client_ip {
+        counters.push((LockoutScope::Ip, ip.to_string(), config.ip_max_failures));
+    }
+    counters
+}
+
+/// Rejects the attempt outright while the account or the client address is locked.
+pub async fn ensure_login_allowed(
+    store: &AuthStore,
+    config: &LockoutConfig,
+    email: &str,
+    client_ip: Option<IpAddr>
+) -> Result<(), AppError> {
+    for (scope, key, _) in counters(config, email, client_ip) {
+        if store.find_login_failures(scope, &key).await?.is_some_and(|failures| failures.is_locked()) {
+            warn!("Rejected login attempt for locked {} {}", scope.as_str(), key);
+            return Err(AppError::Unauthorized("Too many failed login attempts, try again later".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Counts a failed attempt and locks whatever went past its threshold, for
+/// longer with every further failure.
+pub async fn record_failed_login(
+    store: &AuthStore,
+    config: &LockoutConfig,
+    email: &str,
+    client_ip: Option<IpAddr>
+) -> Result<(), AppError> {
+    for (scope, key, max_failures) in counters(config, email, client_ip) {
+        let failures = store.record_login_failure(scope, &key, config.failure_window).await?;
+        if let Some(lockout) = config.lockout_for(failures.failures, max_failures) {
+            warn!("Locking {} {} for {:?} after {} failed logins", scope.as_str(), key, lockout, failures.failures);
+            store.lock_login(scope, &key, SystemTime::now() + lockout).await?;
+        }
+    }
+    Ok(())
+}
+
+/// A successful login clears the account's failures. The address keeps its
+/// count, so one valid account does not reset a credential stuffing run.
+pub async fn record_successful_login(store: &AuthStore, email: &str) -> Result<(), AppError> {
+    store.clear_login_failures(LockoutScope::Account, email).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::repositories::memory_auth_repository::MemoryStore;
+
+    fn config() -> LockoutConfig {
+        LockoutConfig {
+            account_max_failures: 3,
+            ip_max_failures: 5,
+            failure_window: Duration::from_secs(900),
+            base_lockout: Duration::from_secs(60),
+            max_lockout: Duration::from_secs(240),
+        }
+    }
+
+    fn ip(last: u8) -> Option<IpAddr> {
+        Some(IpAddr::from([192, 0, 2, last]))
+    }
+
+    async fn fail(store: &AuthStore, email: &str, client_ip: Option<IpAddr>, times: u32) {
+        for _ in 0..times {
+            record_failed_login(store, &config(), email, client_ip).await.unwrap();
+        }
+    }
+
+    async fn allowed(store: &AuthStore, email: &str, client_ip: Option<IpAddr>) -> bool {
+        ensure_login_allowed(store, &config(), email, client_ip).await.is_ok()
+    }
+
+    /// How much longer `key` stays locked, rounded to whole seconds.
+    async fn locked_for(store: &AuthStore, scope: LockoutScope, key: &str) -> Option<u64> {
+        let until = store.find_login_failures(scope, key).await.unwrap()?.locked_until?;
+        let remaining = until.duration_since(SystemTime::now()).unwrap();
+        Some(remaining.as_secs_f64().round() as u64)
+    }
+
+    #[tokio::test]
+    async fn locks_the_account_at_its_threshold() {
+        let store = MemoryStore::new().into_store();
+        fail(&store, "alice@example.com", ip(1), 2).await;
+        assert!(allowed(&store, "alice@example.com", ip(1)).await);
+
+        fail(&store, "alice@example.com", ip(1), 1).await;
+        assert!(!allowed(&store, "alice@example.com", ip(1)).await);
+        assert!(!allowed(&store, "alice@example.com", ip(2)).await);
+        // The address is still under its own, higher threshold.
+        assert!(allowed(&store, "bob@example.com", ip(1)).await);
+    }
+
+    #[tokio::test]
+    async fn locks_the_address_across_accounts() {
+        let store = MemoryStore::new().into_store();
+        for user in ["a", "b", "c", "d", "e"] {
+            fail(&store, &format!("{}@example.com", user), ip(1), 1).await;
+        }
+
+        assert!(!allowed(&store, "fresh@example.com", ip(1)).await);
+        assert!(allowed(&store, "fresh@example.com", ip(2)).await);
+        assert!(allowed(&store, "fresh@example.com", None).await);
+        assert!(allowed(&store, "a@example.com", ip(2)).await);
+    }
+
+    #[tokio::test]
+    async fn doubles_the_lock_with_every_further_failure() {
+        let store = MemoryStore::new().into_store();
+        let mut locks = Vec::new();
+        for _ in 0..5 {
+            fail(&store, "alice@example.com", None, 1).await;
+            locks.push(locked_for(&store, LockoutScope::Account, "alice@example.com").await);
+        }
+        assert_eq!(locks, vec![None, None, Some(60), Some(120), Some(240)]);
+
+        fail(&store, "alice@example.com", None, 1).await;
+        assert_eq!(locked_for(&store, LockoutScope::Account, "alice@example.com").await, Some(240));
+    }
+
+    #[tokio::test]
+    async fn success_resets_the_account_but_not_the_address() {
+        let store = MemoryStore::new().into_store();
+        fail(&store, "alice@example.com", ip(1), 2).await;
+        record_successful_login(&store, "alice@example.com").await.unwrap();
+
+        fail(&store, "alice@example.com", ip(1), 2).await;
+        assert!(allowed(&store, "alice@example.com", ip(1)).await);
+        let address = store.find_login_failures(LockoutScope::Ip, "192.0.2.1").await.unwrap().unwrap();
+        assert_eq!(address.failures, 4);
+    }
+}
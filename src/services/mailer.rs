@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use rocket::async_trait;
+use tokio::io::AsyncWriteExt;
+use log::{info, error};
+
+use crate::config::mail_config::{MailConfig, MailTransport};
+use crate::errors::app_error::AppError;
+
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Sends transactional email. Handlers only see this trait, so the transport
+/// is picked by configuration.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, message: EmailMessage) -> Result<(), AppError>;
+}
+
+pub type SharedMailer = Arc<dyn Mailer>;
+
+fn mail_error(e: impl std::fmt::Display) -> AppError {
+    error!("Failed to send email: {}", e);
+    AppError::InternalServerError("Failed to send email".to_string())
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, message: EmailMessage) -> Result<(), AppError> {
+        let to = message.to.parse::<Mailbox>().map_err(mail_error)?;
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject)
+            .body(message.body)
+            .map_err(mail_error)?;
+
+        self.transport.send(email).await.map_err(mail_error)?;
+        info!("Sent email to {} via SMTP", message.to);
+        Ok(())
+    }
+}
+
+/// Writes messages to a file (or stdout) instead of sending them, for local
+/// development and tests.
+pub struct FileMailer {
+    path: Option<PathBuf>,
+    from: String,
+}
+
+impl FileMailer {
+    fn render(&self, message: &EmailMessage) -> String {
+        format!(
+            "From: {}\nTo: {}\nSubject: {}\n\n{}\n\n",
+            self.from, message.to, message.subject, message.body
+        )
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, message: EmailMessage) -> Result<(), AppError> {
+        let rendered = self.render(&message);
+        match &self.path {
+            Some(path) => {
+                let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await
+                    .map_err(mail_error)?;
+                file.write_all(rendered.as_bytes()).await.map_err(mail_error)?;
+                info!("Wrote email to {} into {}", message.to, path.display());
+            }
+            None => print!("{}", rendered),
+        }
+        Ok(())
+    }
+}
+
+pub fn build_mailer(config: &MailConfig) -> Result<SharedMailer, String> {
+    match &config.transport {
+        MailTransport::Smtp { host, port, username, password, security } => {
+            let builder = match security.as_str() {
+                "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+                "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+                "none" => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)),
+                other => return Err(format!("SMTP_SECURITY must be tls, starttls or none, got {}", other)),
+            }.map_err(|e| format!("Invalid SMTP_HOST: {}", e))?;
+
+            let builder = match (username, password) {
+                (Some(username), Some(password)) => builder.credentials(Credentials::new(username.clone(), password.clone())),
+                _ => builder,
+            };
+            let builder = match port {
+                Some(port) => builder.port(*port),
+                None => builder,
+            };
+            let from = config.from.parse::<Mailbox>().map_err(|e| format!("Invalid MAIL_FROM: {}", e))?;
+            info!("Sending email through SMTP relay {}", host);
+            Ok(Arc::new(SmtpMailer { transport: builder.build(), from }))
+        }
+        MailTransport::File(path) => Ok(Arc::new(FileMailer { path: path.clone(), from: config.from.clone() })),
+    }
+}
@@ -1 +1,12 @@
-pub mod user_service;
\ No newline at end of file
+pub mod password_service;
+pub mod token_service;
+pub mod session_service;
+pub mod api_key_service;
+pub mod mailer;
+pub mod verification_service;
+pub mod password_reset_service;
+pub mod two_factor_service;
+pub mod login_throttle_service;
+pub mod oidc_client;
+pub mod oidc_service;pub mod oauth_service;
+pub mod health_service;
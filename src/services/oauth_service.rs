@@ -0,0 +1,425 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::Algorithm;
+use log::{info, warn};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+
+use crate::config::jwt_config::JwtConfig;
+use crate::config::oauth_provider_config::OAuthProviderConfig;
+use crate::errors::app_error::AppError;
+use crate::errors::oauth_error::OAuthError;
+use crate::models::auth::Credentials;
+use crate::models::oauth::{
+    AuthorizationCode, AuthorizationRequest, CreatedOAuthClient, GrantType, IdToken, NewOAuthClient, OAuthClaims,
+    OAuthClient, TokenRequest, TokenResponse, UserInfo, OPENID_SCOPES,
+};
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::token_service::{generate_opaque_token, hash_opaque_token, sign_jwt};
+use crate::utils::authentication::ClientSecretBasic;
+
+/// Marks client secrets so they are easy to spot in logs and secret scanners.
+const CLIENT_SECRET_PREFIX: &str = "ocs_";
+/// Characters of a generated opaque token kept for client ids (128 bits).
+const CLIENT_ID_LENGTH: usize = 22;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// Relying parties verify our tokens with the published public keys, which
+/// only exist with RS256.
+pub fn ensure_provider_enabled(jwt: &JwtConfig) -> Result<(), AppError> {
+    if jwt.algorithm == Algorithm::RS256 {
+        Ok(())
+    } else {
+        Err(AppError::NotFound("The OAuth provider requires JWT_ALGORITHM=RS256".to_string()))
+    }
+}
+
+fn scopes(scope: &str) -> Vec<&str> {
+    scope.split(' ').filter(|scope| !scope.is_empty()).collect()
+}
+
+/// Registers a client. Confidential clients get a secret, returned only here.
+pub async fn create_client(store: &AuthStore, new_client: NewOAuthClient) -> Result<CreatedOAuthClient, AppError> {
+    let uses_code = new_client.grant_types.contains(&GrantType::AuthorizationCode);
+    if uses_code && new_client.redirect_uris.is_empty() {
+        return Err(AppError::BadRequest("authorization_code clients need at least one redirect URI".to_string()));
+    }
+    if new_client.grant_types.contains(&GrantType::ClientCredentials) && !new_client.confidential {
+        return Err(AppError::BadRequest("client_credentials requires a confidential client".to_string()));
+    }
+
+    let mut scopes = new_client.scopes;
+    if scopes.is_empty() && uses_code {
+        scopes = OPENID_SCOPES.iter().map(|scope| scope.to_string()).collect();
+    }
+    let client_secret = new_client.confidential
+        .then(|| format!("{}{}", CLIENT_SECRET_PREFIX, generate_opaque_token()));
+
+    let client = OAuthClient {
+        client_id: generate_opaque_token()[..CLIENT_ID_LENGTH].to_string(),
+        client_secret_hash: client_secret.as_deref().map(hash_opaque_token),
+        name: new_client.name,
+        redirect_uris: new_client.redirect_uris,
+        grant_types: new_client.grant_types,
+        scopes,
+        confidential: client_secret.is_some(),
+        created_at: SystemTime::now(),
+    };
+    store.insert_oauth_client(&client).await?;
+
+    Ok(CreatedOAuthClient { client, client_secret })
+}
+
+/// `redirect_uri` with `params` added to its query.
+fn redirect_with(redirect_uri: &str, params: &[(&str, Option<&str>)]) -> Result<String, AppError> {
+    let mut url = Url::parse(redirect_uri)
+        .map_err(|_| AppError::InternalServerError("Registered redirect URI is invalid".to_string()))?;
+    {
+        let mut query = url.query_pairs_mut();
+        for (name, value) in params {
+            if let Some(value) = value {
+                query.append_pair(name, value);
+            }
+        }
+    }
+    Ok(url.into())
+}
+
+/// Approves an authorization request for the signed-in user and returns the
+/// client redirect carrying the code.
+///
+/// Requests that cannot be tied to a registered redirect URI are rejected
+/// outright; later problems are reported to the client through the redirect,
+/// as OAuth requires.
+pub async fn authorize(
+    store: &AuthStore,
+    config: &OAuthProviderConfig,
+    user_id: &str,
+    request: AuthorizationRequest
+) -> Result<String, AppError> {
+    let client = store.find_oauth_client(&request.client_id).await?
+        .ok_or_else(|| AppError::BadRequest("Unknown client_id".to_string()))?;
+    if !client.redirect_uris.contains(&request.redirect_uri) {
+        return Err(AppError::BadRequest("redirect_uri is not registered for this client".to_string()));
+    }
+
+    let state = request.state.as_deref();
+    let iss = Some(config.issuer.as_str());
+    let fail = |error: &str, description: &str| {
+        warn!("Rejected authorization request from client {}: {}", client.client_id, description);
+        redirect_with(&request.redirect_uri, &[
+            ("error", Some(error)), ("error_description", Some(description)), ("state", state), ("iss", iss),
+        ])
+    };
+
+    if request.response_type != "code" {
+        return fail("unsupported_response_type", "Only the code response type is supported");
+    }
+    if !client.grant_types.contains(&GrantType::AuthorizationCode) {
+        return fail("unauthorized_client", "The client may not use the authorization_code grant");
+    }
+    let scope = request.scope.as_deref().unwrap_or("openid");
+    let requested = scopes(scope);
+    if requested.is_empty() || requested.iter().any(|scope| !client.scopes.iter().any(|allowed| allowed == scope)) {
+        return fail("invalid_scope", "The requested scope is not allowed for this client");
+    }
+    let code_challenge = match (&request.code_challenge, request.code_challenge_method.as_deref()) {
+        (Some(challenge), Some("S256")) if (43..=128).contains(&challenge.len()) => challenge.clone(),
+        _ => return fail("invalid_request", "A code_challenge with code_challenge_method S256 is required"),
+    };
+
+    let code = generate_opaque_token();
+    store.insert_authorization_code(&AuthorizationCode {
+        code_hash: hash_opaque_token(&code),
+        client_id: client.client_id.clone(),
+        user_id: user_id.to_string(),
+        redirect_uri: request.redirect_uri.clone(),
+        scope: requested.join(" "),
+        nonce: request.nonce,
+        code_challenge,
+        expires_at: SystemTime::now() + config.authorization_code_ttl,
+    }).await?;
+
+    info!("Issued authorization code for user {} to client {}", user_id, client.client_id);
+    redirect_with(&request.redirect_uri, &[("code", Some(&code)), ("state", state), ("iss", iss)])
+}
+
+async fn authenticate_client(
+    store: &AuthStore,
+    basic: Option<ClientSecretBasic>,
+    request: &TokenRequest
+) -> Result<OAuthClient, OAuthError> {
+    let (client_id, client_secret) = match (basic, &request.client_id) {
+        (Some(_), Some(_)) if request.client_secret.is_some() => {
+            return Err(OAuthError::invalid_request("Use only one client authentication method"));
+        }
+        (Some(basic), _) => (basic.client_id, Some(basic.client_secret)),
+        (None, Some(client_id)) => (client_id.clone(), request.client_secret.clone()),
+        (None, None) => return Err(OAuthError::invalid_client()),
+    };
+
+    let client = store.find_oauth_client(&client_id).await?.ok_or_else(OAuthError::invalid_client)?;
+    let authenticated = match (&client.client_secret_hash, client_secret) {
+        (Some(expected), Some(secret)) => hash_opaque_token(&secret) == *expected,
+        (None, None) => true,
+        _ => false,
+    };
+    if !authenticated {
+        warn!("Client authentication failed for {}", client_id);
+        return Err(OAuthError::invalid_client());
+    }
+    Ok(client)
+}
+
+/// Standard claims about `credentials`, limited to what `scope` grants.
+pub fn user_info(credentials: &Credentials, scope: &str) -> UserInfo {
+    let granted = scopes(scope);
+    let email = granted.contains(&"email");
+    UserInfo {
+        sub: credentials.user_id.clone(),
+        name: granted.contains(&"profile").then(|| credentials.name.clone()),
+        email: email.then(|| credentials.email.clone()),
+        email_verified: email.then_some(credentials.email_verified),
+    }
+}
+
+fn access_token(
+    jwt: &JwtConfig,
+    config: &OAuthProviderConfig,
+    client_id: &str,
+    subject: &str,
+    scope: &str
+) -> Result<String, AppError> {
+    let issued_at = now();
+    sign_jwt(jwt, &OAuthClaims {
+        iss: config.issuer.clone(),
+        sub: subject.to_string(),
+        aud: client_id.to_string(),
+        client_id: client_id.to_string(),
+        scope: scope.to_string(),
+        iat: issued_at,
+        exp: issued_at + jwt.access_token_ttl.as_secs(),
+    })
+}
+
+async fn exchange_code(
+    store: &AuthStore,
+    jwt: &JwtConfig,
+    config: &OAuthProviderConfig,
+    client: &OAuthClient,
+    request: &TokenRequest
+) -> Result<TokenResponse, OAuthError> {
+    let (Some(code), Some(redirect_uri), Some(code_verifier)) = (&request.code, &request.redirect_uri, &request.code_verifier) else {
+        return Err(OAuthError::invalid_request("code, redirect_uri and code_verifier are required"));
+    };
+
+    let invalid_code = || OAuthError::invalid_grant("The authorization code is invalid or has expired");
+    let code = store.consume_authorization_code(&hash_opaque_token(code)).await?.ok_or_else(invalid_code)?;
+    let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+    if code.client_id != client.client_id || code.redirect_uri != *redirect_uri || code.code_challenge != challenge {
+        warn!("Authorization code presented by {} failed verification", client.client_id);
+        return Err(invalid_code());
+    }
+    let credentials = store.find_credentials_by_id(&code.user_id).await?.ok_or_else(invalid_code)?;
+
+    let id_token = if scopes(&code.scope).contains(&"openid") {
+        let issued_at = now();
+        Some(sign_jwt(jwt, &IdToken {
+            iss: config.issuer.clone(),
+            aud: client.client_id.clone(),
+            iat: issued_at,
+            exp: issued_at + jwt.access_token_ttl.as_secs(),
+            nonce: code.nonce.clone(),
+            user_info: user_info(&credentials, &code.scope),
+        })?)
+    } else {
+        None
+    };
+
+    info!("Issued tokens for user {} to client {}", credentials.user_id, client.client_id);
+    Ok(TokenResponse {
+        access_token: access_token(jwt, config, &client.client_id, &credentials.user_id, &code.scope)?,
+        token_type: "Bearer".to_string(),
+        expires_in: jwt.access_token_ttl.as_secs(),
+        scope: code.scope,
+        id_token,
+    })
+}
+
+/// Client credentials act for the client itself, so user scopes are refused.
+/// Without a `scope` the client gets all of its service scopes.
+fn client_credentials(
+    jwt: &JwtConfig,
+    config: &OAuthProviderConfig,
+    client: &OAuthClient,
+    request: &TokenRequest
+) -> Result<TokenResponse, OAuthError> {
+    let service_scopes: Vec<&str> = client.scopes.iter()
+        .map(String::as_str)
+        .filter(|scope| !OPENID_SCOPES.contains(scope))
+        .collect();
+    let scope = match &request.scope {
+        Some(scope) => {
+            let requested = scopes(scope);
+            if requested.iter().any(|scope| !service_scopes.contains(scope)) {
+                return Err(OAuthError::invalid_scope("The requested scope is not allowed for this client"));
+            }
+            requested.join(" ")
+        }
+        None => service_scopes.join(" "),
+    };
+
+    info!("Issued client credentials token to client {}", client.client_id);
+    Ok(TokenResponse {
+        access_token: access_token(jwt, config, &client.client_id, &client.client_id, &scope)?,
+        token_type: "Bearer".to_string(),
+        expires_in: jwt.access_token_ttl.as_secs(),
+        scope,
+        id_token: None,
+    })
+}
+
+/// Handles `POST /oauth/token` for both supported grants.
+pub async fn issue_token(
+    store: &AuthStore,
+    jwt: &JwtConfig,
+    config: &OAuthProviderConfig,
+    basic: Option<ClientSecretBasic>,
+    request: TokenRequest
+) -> Result<TokenResponse, OAuthError> {
+    ensure_provider_enabled(jwt)?;
+    let client = authenticate_client(store, basic, &request).await?;
+    let grant_type = request.grant_type.parse::<GrantType>()
+        .map_err(|_| OAuthError::unsupported_grant_type(&request.grant_type))?;
+    if !client.grant_types.contains(&grant_type) {
+        return Err(OAuthError::unauthorized_client(format!("The client may not use {}", grant_type.as_str())));
+    }
+
+    match grant_type {
+        GrantType::AuthorizationCode => exchange_code(store, jwt, config, &client, &request).await,
+        GrantType::ClientCredentials => client_credentials(jwt, config, &client, &request),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::models::role::Role;
+    use crate::repositories::memory_auth_repository::MemoryStore;
+
+    const REDIRECT_URI: &str = "https://app.example.com/callback";
+    /// The verifier and its S256 challenge from RFC 7636, appendix B.
+    const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+
+    fn provider_config() -> OAuthProviderConfig {
+        OAuthProviderConfig {
+            issuer: "https://id.example.com".to_string(),
+            public_base_url: "https://id.example.com".to_string(),
+            authorization_page_url: "https://id.example.com/authorize".to_string(),
+            authorization_code_ttl: Duration::from_secs(300),
+        }
+    }
+
+    /// A store with a user and a public authorization code client.
+    async fn setup() -> (AuthStore, String, OAuthClient) {
+        let memory = MemoryStore::new();
+        let user_id = memory.add_user("alice@example.com", None, &[Role::User]);
+        let store = memory.into_store();
+        let created = create_client(&store, NewOAuthClient {
+            name: "App".to_string(),
+            redirect_uris: vec![REDIRECT_URI.to_string()],
+            grant_types: vec![GrantType::AuthorizationCode],
+            scopes: Vec::new(),
+            confidential: false,
+        }).await.unwrap();
+        assert!(created.client_secret.is_none());
+        (store, user_id, created.client)
+    }
+
+    fn authorization_request(client: &OAuthClient, challenge: Option<&str>, method: Option<&str>) -> AuthorizationRequest {
+        AuthorizationRequest {
+            response_type: "code".to_string(),
+            client_id: client.client_id.clone(),
+            redirect_uri: REDIRECT_URI.to_string(),
+            scope: Some("openid email".to_string()),
+            state: Some("xyz".to_string()),
+            nonce: None,
+            code_challenge: challenge.map(str::to_string),
+            code_challenge_method: method.map(str::to_string),
+        }
+    }
+
+    fn query_param(redirect: &str, name: &str) -> Option<String> {
+        Url::parse(redirect).unwrap().query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    }
+
+    async fn authorization_code(store: &AuthStore, user_id: &str, client: &OAuthClient) -> String {
+        let request = authorization_request(client, Some(CODE_CHALLENGE), Some("S256"));
+        let redirect = authorize(store, &provider_config(), user_id, request).await.unwrap();
+        assert_eq!(query_param(&redirect, "state").as_deref(), Some("xyz"));
+        query_param(&redirect, "code").unwrap()
+    }
+
+    fn token_request(client: &OAuthClient, code: &str, code_verifier: &str) -> TokenRequest {
+        TokenRequest {
+            grant_type: "authorization_code".to_string(),
+            code: Some(code.to_string()),
+            redirect_uri: Some(REDIRECT_URI.to_string()),
+            code_verifier: Some(code_verifier.to_string()),
+            client_id: Some(client.client_id.clone()),
+            client_secret: None,
+            scope: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn exchanges_a_code_with_the_matching_verifier() {
+        let (store, user_id, client) = setup().await;
+        let code = authorization_code(&store, &user_id, &client).await;
+
+        let request = token_request(&client, &code, CODE_VERIFIER);
+        let response = exchange_code(&store, &JwtConfig::for_tests(), &provider_config(), &client, &request).await.unwrap();
+        assert_eq!(response.scope, "openid email");
+        assert!(response.id_token.is_some());
+
+        // Codes are single use.
+        let replayed = exchange_code(&store, &JwtConfig::for_tests(), &provider_config(), &client, &request).await;
+        assert_eq!(replayed.err().map(|e| e.error), Some("invalid_grant"));
+    }
+
+    #[tokio::test]
+    async fn rejects_a_wrong_verifier_and_burns_the_code() {
+        let (store, user_id, client) = setup().await;
+        let code = authorization_code(&store, &user_id, &client).await;
+
+        let wrong = token_request(&client, &code, "a-verifier-that-does-not-match-the-challenge-0123");
+        let result = exchange_code(&store, &JwtConfig::for_tests(), &provider_config(), &client, &wrong).await;
+        assert_eq!(result.err().map(|e| e.error), Some("invalid_grant"));
+
+        let right = token_request(&client, &code, CODE_VERIFIER);
+        let result = exchange_code(&store, &JwtConfig::for_tests(), &provider_config(), &client, &right).await;
+        assert_eq!(result.err().map(|e| e.error), Some("invalid_grant"));
+    }
+
+    #[tokio::test]
+    async fn requires_an_s256_challenge() {
+        let (store, user_id, client) = setup().await;
+
+        let attempts = [(None, None), (Some(CODE_CHALLENGE), Some("plain")), (Some(CODE_CHALLENGE), None), (Some("short"), Some("S256"))];
+        for (challenge, method) in attempts {
+            let request = authorization_request(&client, challenge, method);
+            let redirect = authorize(&store, &provider_config(), &user_id, request).await.unwrap();
+            assert_eq!(query_param(&redirect, "error").as_deref(), Some("invalid_request"));
+            assert!(query_param(&redirect, "code").is_none());
+        }
+    }
+}
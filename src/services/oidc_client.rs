@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use log::{error, info, warn};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::config::oidc_config::OidcProviderConfig;
+use crate::errors::app_error::AppError;
+use crate::models::oidc::{IdTokenClaims, ProviderMetadata};
+
+/// How long discovery documents and signing keys are reused before being fetched again.
+const METADATA_TTL: Duration = Duration::from_secs(3600);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Signature algorithms accepted on ID tokens. Symmetric ones would let
+/// anyone holding the client secret mint tokens, and `none` anyone at all.
+const ID_TOKEN_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256, Algorithm::RS384, Algorithm::RS512,
+    Algorithm::PS256, Algorithm::PS384, Algorithm::PS512,
+    Algorithm::ES256, Algorithm::ES384, Algorithm::EdDSA,
+];
+
+struct CachedProvider {
+    metadata: Arc<ProviderMetadata>,
+    jwks: Arc<JwkSet>,
+    fetched_at: Instant,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+fn unreachable_provider(provider: &str, e: impl std::fmt::Display) -> AppError {
+    error!("Request to OIDC provider {} failed: {}", provider, e);
+    AppError::BadGateway(format!("Identity provider {} could not be reached", provider))
+}
+
+fn invalid_id_token(reason: impl std::fmt::Display) -> AppError {
+    warn!("Rejected ID token: {}", reason);
+    AppError::Unauthorized("Invalid ID token from identity provider".to_string())
+}
+
+/// Talks to OpenID Connect providers: discovery, key sets, code exchange and
+/// ID token verification. Provider metadata is fetched on first use, so the
+/// service starts even while a provider is down.
+pub struct OidcClient {
+    http: reqwest::Client,
+    cache: RwLock<HashMap<String, CachedProvider>>,
+}
+
+impl OidcClient {
+    pub fn new() -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to build the HTTP client: {}", e))?;
+        Ok(OidcClient { http, cache: RwLock::new(HashMap::new()) })
+    }
+
+    async fn get_json<T: for<'de> Deserialize<'de>>(&self, provider: &str, url: &str) -> Result<T, AppError> {
+        self.http.get(url).send().await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| unreachable_provider(provider, e))?
+            .json().await
+            .map_err(|e| unreachable_provider(provider, e))
+    }
+
+    async fn fetch_jwks(&self, provider: &OidcProviderConfig, metadata: &ProviderMetadata) -> Result<Arc<JwkSet>, AppError> {
+        Ok(Arc::new(self.get_json(&provider.name, &metadata.jwks_uri).await?))
+    }
+
+    /// The provider's discovery document and signing keys, from cache while fresh.
+    async fn provider(&self, provider: &OidcProviderConfig) -> Result<(Arc<ProviderMetadata>, Arc<JwkSet>), AppError> {
+        if let Some(cached) = self.cache.read().await.get(&provider.name) {
+            if cached.fetched_at.elapsed() < METADATA_TTL {
+                return Ok((cached.metadata.clone(), cached.jwks.clone()));
+            }
+        }
+
+        info!("Fetching discovery document of OIDC provider {}", provider.name);
+        let url = format!("{}/.well-known/openid-configuration", provider.issuer.trim_end_matches('/'));
+        let metadata: ProviderMetadata = self.get_json(&provider.name, &url).await?;
+        if metadata.issuer != provider.issuer {
+            error!("OIDC provider {} reports issuer {}, expected {}", provider.name, metadata.issuer, provider.issuer);
+            return Err(AppError::BadGateway(format!("Identity provider {} is misconfigured", provider.name)));
+        }
+
+        let metadata = Arc::new(metadata);
+        let jwks = self.fetch_jwks(provider, &metadata).await?;
+        self.cache.write().await.insert(provider.name.clone(), CachedProvider {
+            metadata: metadata.clone(),
+            jwks: jwks.clone(),
+            fetched_at: Instant::now(),
+        });
+        Ok((metadata, jwks))
+    }
+
+    pub async fn metadata(&self, provider: &OidcProviderConfig) -> Result<Arc<ProviderMetadata>, AppError> {
+        Ok(self.provider(provider).await?.0)
+    }
+
+    /// Exchanges an authorization code for the ID token, proving with the
+    /// PKCE verifier that this client started the login.
+    pub async fn exchange_code(
+        &self,
+        provider: &OidcProviderConfig,
+        code: &str,
+        code_verifier: &str
+    ) -> Result<String, AppError> {
+        let metadata = self.metadata(provider).await?;
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", provider.redirect_uri.as_str()),
+            ("client_id", provider.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+
+        let mut request = self.http.post(&metadata.token_endpoint);
+        if let Some(secret) = provider.client_secret.as_deref() {
+            // `client_secret_basic` is the default unless the provider only supports posting it.
+            let methods = &metadata.token_endpoint_auth_methods_supported;
+            if methods.is_empty() || methods.iter().any(|method| method == "client_secret_basic") {
+                request = request.basic_auth(&provider.client_id, Some(secret));
+            } else {
+                form.push(("client_secret", secret));
+            }
+        }
+
+        let response = request.form(&form).send().await.map_err(|e| unreachable_provider(&provider.name, e))?;
+        let status = response.status();
+        let body: TokenResponse = response.json().await.map_err(|e| unreachable_provider(&provider.name, e))?;
+
+        if status.is_client_error() {
+            warn!("OIDC provider {} rejected the authorization code: {:?} {:?}",
+                provider.name, body.error, body.error_description);
+            return Err(AppError::Unauthorized("Authorization code was rejected by the identity provider".to_string()));
+        }
+        if !status.is_success() {
+            return Err(unreachable_provider(&provider.name, status));
+        }
+        body.id_token.ok_or_else(|| invalid_id_token("token response has no id_token"))
+    }
+
+    /// Checks the ID token's signature, issuer, audience, expiry and nonce.
+    /// Keys are looked up by `kid`; an unknown one triggers a single refetch
+    /// of the key set, which is how providers roll keys over.
+    pub async fn verify_id_token(
+        &self,
+        provider: &OidcProviderConfig,
+        id_token: &str,
+        nonce: &str
+    ) -> Result<IdTokenClaims, AppError> {
+        let header = decode_header(id_token).map_err(invalid_id_token)?;
+        if !ID_TOKEN_ALGORITHMS.contains(&header.alg) {
+            return Err(invalid_id_token(format!("algorithm {:?} is not allowed", header.alg)));
+        }
+
+        let (metadata, mut jwks) = self.provider(provider).await?;
+        let find_key = |jwks: &JwkSet| match header.kid.as_deref() {
+            Some(kid) => jwks.find(kid).cloned(),
+            None if jwks.keys.len() == 1 => jwks.keys.first().cloned(),
+            None => None,
+        };
+        let mut jwk = find_key(&jwks);
+        if jwk.is_none() {
+            info!("Refreshing signing keys of OIDC provider {}", provider.name);
+            jwks = self.fetch_jwks(provider, &metadata).await?;
+            if let Some(cached) = self.cache.write().await.get_mut(&provider.name) {
+                cached.jwks = jwks.clone();
+            }
+            jwk = find_key(&jwks);
+        }
+        let jwk = jwk.ok_or_else(|| invalid_id_token(format!("no signing key {:?}", header.kid)))?;
+        let key = DecodingKey::from_jwk(&jwk).map_err(invalid_id_token)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&provider.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation).map_err(invalid_id_token)?.claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(invalid_id_token("nonce does not match"));
+        }
+        if claims.azp.as_deref().is_some_and(|azp| azp != provider.client_id) {
+            return Err(invalid_id_token("issued to another client"));
+        }
+        Ok(claims)
+    }
+}
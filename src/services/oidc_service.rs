@@ -0,0 +1,215 @@
+use std::time::SystemTime;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use log::{info, warn};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+
+use crate::config::oidc_config::{OidcConfig, OidcProviderConfig};
+use crate::errors::app_error::AppError;
+use crate::models::auth::Credentials;
+use crate::models::oidc::{ExternalIdentity, IdTokenClaims, OidcLoginState};
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::oidc_client::OidcClient;
+use crate::services::token_service::{generate_opaque_token, hash_opaque_token};
+use crate::utils::validation::normalize_email;
+
+/// Longest name accepted for users, as validated on `User` and `UserMongo`.
+const MAX_NAME_LENGTH: usize = 100;
+
+fn provider<'a>(config: &'a OidcConfig, name: &str) -> Result<&'a OidcProviderConfig, AppError> {
+    config.provider(name).ok_or_else(|| AppError::NotFound(format!("Unknown identity provider {}", name)))
+}
+
+fn invalid_state() -> AppError {
+    AppError::Unauthorized("Login state is invalid or has expired".to_string())
+}
+
+/// PKCE `S256` challenge for a code verifier.
+fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+/// Starts a login with `provider_name` and returns the provider URL to send
+/// the browser to. The state, nonce and PKCE verifier are kept server side
+/// until the provider redirects back.
+pub async fn begin_oidc_login(
+    store: &AuthStore,
+    client: &OidcClient,
+    config: &OidcConfig,
+    provider_name: &str
+) -> Result<String, AppError> {
+    let provider = provider(config, provider_name)?;
+    let metadata = client.metadata(provider).await?;
+
+    let state = generate_opaque_token();
+    let nonce = generate_opaque_token();
+    let code_verifier = generate_opaque_token();
+    store.insert_oidc_state(&OidcLoginState {
+        state_hash: hash_opaque_token(&state),
+        provider: provider.name.clone(),
+        nonce: nonce.clone(),
+        code_verifier: code_verifier.clone(),
+        expires_at: SystemTime::now() + config.login_ttl,
+    }).await?;
+
+    let mut url = Url::parse(&metadata.authorization_endpoint).map_err(|e| {
+        AppError::BadGateway(format!("Identity provider {} has an invalid authorization endpoint: {}", provider.name, e))
+    })?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &provider.client_id)
+        .append_pair("redirect_uri", &provider.redirect_uri)
+        .append_pair("scope", &provider.scopes)
+        .append_pair("state", &state)
+        .append_pair("nonce", &nonce)
+        .append_pair("code_challenge", &code_challenge(&code_verifier))
+        .append_pair("code_challenge_method", "S256");
+    Ok(url.into())
+}
+
+fn identity_from_claims(provider: &OidcProviderConfig, claims: IdTokenClaims) -> ExternalIdentity {
+    let email_verified = match claims.email_verified {
+        Some(serde_json::Value::Bool(verified)) => verified,
+        Some(serde_json::Value::String(verified)) => verified == "true",
+        _ => false,
+    };
+    ExternalIdentity {
+        provider: provider.name.clone(),
+        subject: claims.sub,
+        email: claims.email.map(|email| normalize_email(&email)),
+        email_verified,
+        name: claims.name.or(claims.preferred_username),
+    }
+}
+
+/// Finds the user an identity belongs to, linking or creating one on first login.
+///
+/// An existing account with the same email is only linked when the provider
+/// vouches for the email and the account cannot have been claimed by someone
+/// else first: it has no password, or its email is verified here too. Never
+/// one with two-factor authentication, which the provider would bypass.
+async fn resolve_user(store: &AuthStore, identity: &ExternalIdentity) -> Result<Credentials, AppError> {
+    if let Some(user_id) = store.find_identity_user(&identity.provider, &identity.subject).await? {
+        return store.find_credentials_by_id(&user_id).await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()));
+    }
+
+    let Some(email) = identity.email.as_deref() else {
+        return Err(AppError::BadRequest("The identity provider did not share an email address".to_string()));
+    };
+
+    if let Some(existing) = store.find_credentials_by_email(email).await? {
+        if existing.totp_enabled {
+            warn!("Not linking {} identity to user {}, who has two-factor authentication", identity.provider, existing.user_id);
+            return Err(AppError::Conflict(
+                "An account with this email already exists and uses two-factor authentication; sign in with its password".to_string()
+            ));
+        }
+        if !identity.email_verified || (existing.password_hash.is_some() && !existing.email_verified) {
+            warn!("Not linking {} identity to user {} with an unverified email", identity.provider, existing.user_id);
+            return Err(AppError::Conflict(
+                "An account with this email already exists; sign in with its password and verify the email first".to_string()
+            ));
+        }
+        store.link_identity(identity, &existing.user_id).await?;
+        if !existing.email_verified {
+            store.mark_email_verified(&existing.user_id, email).await?;
+        }
+        info!("Linked {} identity to existing user {}", identity.provider, existing.user_id);
+        return store.find_credentials_by_id(&existing.user_id).await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()));
+    }
+
+    let name = identity.name.as_deref().map(str::trim).filter(|name| !name.is_empty())
+        .unwrap_or_else(|| email.split('@').next().unwrap_or(email));
+    let name: String = name.chars().take(MAX_NAME_LENGTH).collect();
+    let user_id = store.create_user_with_identity(identity, &name, email).await?;
+    info!("Created user {} for {} identity", user_id, identity.provider);
+    store.find_credentials_by_id(&user_id).await?
+        .ok_or_else(|| AppError::InternalServerError("Failed to retrieve created user".to_string()))
+}
+
+/// Finishes a login when the provider redirects back: checks the state,
+/// exchanges the code, verifies the ID token and resolves the user.
+///
+/// The provider is trusted to have authenticated the user, so local
+/// two-factor authentication does not apply to these logins. That is only
+/// sound for identities the user linked themselves, which is why accounts
+/// with two-factor authentication are never linked automatically.
+pub async fn complete_oidc_login(
+    store: &AuthStore,
+    client: &OidcClient,
+    config: &OidcConfig,
+    provider_name: &str,
+    code: &str,
+    state: &str
+) -> Result<Credentials, AppError> {
+    let provider = provider(config, provider_name)?;
+    let login = store.consume_oidc_state(&hash_opaque_token(state)).await?.ok_or_else(invalid_state)?;
+    if login.provider != provider.name {
+        return Err(invalid_state());
+    }
+
+    let id_token = client.exchange_code(provider, code, &login.code_verifier).await?;
+    let claims = client.verify_id_token(provider, &id_token, &login.nonce).await?;
+    resolve_user(store, &identity_from_claims(provider, claims)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::role::Role;
+    use crate::repositories::memory_auth_repository::MemoryStore;
+
+    fn identity(email: &str, email_verified: bool) -> ExternalIdentity {
+        ExternalIdentity {
+            provider: "example".to_string(),
+            subject: "subject-1".to_string(),
+            email: Some(email.to_string()),
+            email_verified,
+            name: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn links_a_verified_account_with_the_same_email() {
+        let memory = MemoryStore::new();
+        let user_id = memory.add_user("alice@example.com", Some("hash"), &[Role::User]);
+        memory.update_user(&user_id, |user| user.email_verified = true);
+        let store = memory.into_store();
+
+        let credentials = resolve_user(&store, &identity("alice@example.com", true)).await.unwrap();
+        assert_eq!(credentials.user_id, user_id);
+        assert_eq!(store.find_identity_user("example", "subject-1").await.unwrap(), Some(user_id));
+    }
+
+    #[tokio::test]
+    async fn never_links_an_account_with_two_factor_authentication() {
+        let memory = MemoryStore::new();
+        let user_id = memory.add_user("admin@example.com", Some("hash"), &[Role::Admin]);
+        memory.update_user(&user_id, |user| {
+            user.email_verified = true;
+            user.totp_enabled = true;
+        });
+        let store = memory.into_store();
+
+        let result = resolve_user(&store, &identity("admin@example.com", true)).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+        assert_eq!(store.find_identity_user("example", "subject-1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn refuses_unverified_emails_and_creates_new_users() {
+        let memory = MemoryStore::new();
+        memory.add_user("alice@example.com", Some("hash"), &[Role::User]);
+        let store = memory.into_store();
+
+        let result = resolve_user(&store, &identity("alice@example.com", true)).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+
+        let created = resolve_user(&store, &identity("bob@example.com", true)).await.unwrap();
+        assert_eq!(created.name, "bob");
+        assert_eq!(created.roles, vec![Role::User]);
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::{Duration, SystemTime};
+use log::{info, warn};
+
+use crate::config::mail_config::MailConfig;
+use crate::errors::app_error::AppError;
+use crate::models::auth::{OneTimeToken, TokenPurpose};
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::mailer::{EmailMessage, SharedMailer};
+use crate::services::password_service::{check_password_strength, hash_password};
+use crate::services::token_service::{generate_opaque_token, hash_opaque_token};
+use crate::utils::secret::Secret;
+use crate::utils::validation::normalize_email;
+
+/// Mails a reset link if the email belongs to a user and the hourly limit
+/// for that address is not reached. Callers learn neither: the handler runs
+/// this after answering, so not even the response time depends on it.
+pub async fn request_password_reset(
+    store: &AuthStore,
+    mailer: &SharedMailer,
+    config: &MailConfig,
+    email: &str
+) -> Result<(), AppError> {
+    let Some(credentials) = store.find_credentials_by_email(&normalize_email(email)).await? else {
+        info!("Password reset requested for an unknown email");
+        return Ok(());
+    };
+
+    let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+    let recent = store.count_one_time_tokens_since(&credentials.email, TokenPurpose::PasswordReset, an_hour_ago).await?;
+    if recent >= config.password_reset_hourly_limit {
+        warn!("Password reset rate limit reached for user {}", credentials.user_id);
+        return Ok(());
+    }
+
+    let token = generate_opaque_token();
+    store.insert_one_time_token(&OneTimeToken {
+        token_hash: hash_opaque_token(&token),
+        purpose: TokenPurpose::PasswordReset,
+        user_id: credentials.user_id.clone(),
+        email: credentials.email.clone(),
+        expires_at: SystemTime::now() + config.password_reset_token_ttl,
+    }).await?;
+
+    let link = format!("{}/reset-password?token={}", config.public_base_url, token);
+    mailer.send(EmailMessage {
+        to: credentials.email,
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Hello {},\n\nSomeone asked to reset your password. If it was you, open this link within {} minutes:\n\n{}\n\nOtherwise you can ignore this email.\n",
+            credentials.name,
+            config.password_reset_token_ttl.as_secs() / 60,
+            link
+        ),
+    }).await?;
+
+    info!("Sent password reset email to user {}", credentials.user_id);
+    Ok(())
+}
+
+/// Sets a new password with a reset token and signs the user out everywhere.
+pub async fn confirm_password_reset(store: &AuthStore, token: &Secret, new_password: &Secret) -> Result<(), AppError> {
+    let invalid = || AppError::BadRequest("Invalid or expired password reset token".to_string());
+    // Rules that do not depend on the email are checked before the token is spent
+    check_password_strength("new_password", new_password, "")?;
+    let token = store.consume_one_time_token(&hash_opaque_token(token.expose()), TokenPurpose::PasswordReset).await?
+        .ok_or_else(invalid)?;
+
+    let credentials = store.find_credentials_by_id(&token.user_id).await?.ok_or_else(invalid)?;
+    if normalize_email(&credentials.email) != normalize_email(&token.email) {
+        warn!("User {} changed email since the password reset was requested", token.user_id);
+        return Err(invalid());
+    }
+
+    check_password_strength("new_password", new_password, &credentials.email)?;
+    let password_hash = hash_password(new_password).await?;
+    store.set_password_hash(&credentials.user_id, &password_hash).await?;
+    // Other links from earlier requests must not reset the new password again
+    let unused = store.revoke_user_one_time_tokens(&credentials.user_id, TokenPurpose::PasswordReset).await?;
+    let revoked = store.revoke_user_refresh_tokens(&credentials.user_id).await?;
+
+    info!(
+        "Password reset for user {}, revoked {} other reset tokens and {} refresh tokens",
+        credentials.user_id, unused, revoked
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::role::Role;
+    use crate::repositories::memory_auth_repository::MemoryStore;
+
+    async fn issue_reset_token(store: &AuthStore, user_id: &str, email: &str) -> Secret {
+        let token = generate_opaque_token();
+        store.insert_one_time_token(&OneTimeToken {
+            token_hash: hash_opaque_token(&token),
+            purpose: TokenPurpose::PasswordReset,
+            user_id: user_id.to_string(),
+            email: email.to_string(),
+            expires_at: SystemTime::now() + Duration::from_secs(600),
+        }).await.unwrap();
+        Secret::new(token)
+    }
+
+    #[tokio::test]
+    async fn a_reset_uses_up_the_other_outstanding_tokens() {
+        let memory = MemoryStore::new();
+        let user_id = memory.add_user("alice@example.com", None, &[Role::User]);
+        let store = memory.into_store();
+        let first = issue_reset_token(&store, &user_id, "alice@example.com").await;
+        let second = issue_reset_token(&store, &user_id, "alice@example.com").await;
+
+        confirm_password_reset(&store, &second, &Secret::new("Correct-Horse-9")).await.unwrap();
+        let credentials = store.find_credentials_by_id(&user_id).await.unwrap().unwrap();
+        assert!(credentials.password_hash.is_some());
+
+        for token in [&first, &second] {
+            let result = confirm_password_reset(&store, token, &Secret::new("Battery-Staple-7")).await;
+            assert!(matches!(result, Err(AppError::BadRequest(_))));
+        }
+    }
+}
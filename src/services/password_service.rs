@@ -0,0 +1,182 @@
+use std::sync::OnceLock;
+
+use argon2::password_hash::SaltString;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use log::error;
+use rand::rngs::OsRng;
+
+use crate::errors::app_error::AppError;
+use crate::utils::secret::Secret;
+use crate::utils::validation::FieldError;
+
+pub const MIN_PASSWORD_LENGTH: usize = 12;
+pub const MAX_PASSWORD_LENGTH: usize = 128;
+
+fn hash_blocking(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| {
+            error!("Failed to hash password: {}", e);
+            AppError::InternalServerError("Failed to hash password".to_string())
+        })
+}
+
+/// Hashes a password with argon2id (the `argon2` crate defaults) off the async
+/// workers, since a memory-hard hash takes tens of milliseconds.
+pub async fn hash_password(password: &Secret) -> Result<String, AppError> {
+    let password = password.clone();
+    tokio::task::spawn_blocking(move || hash_blocking(password.expose()))
+        .await
+        .map_err(|e| AppError::InternalServerError(format!("Password hashing task failed: {}", e)))?
+}
+
+/// Hash checked when the account has no password, so that unknown accounts
+/// take as long to reject as a wrong password.
+fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| hash_blocking("dummy password for timing").unwrap_or_default())
+}
+
+pub async fn verify_password(password: &Secret, password_hash: Option<&str>) -> Result<bool, AppError> {
+    let password = password.clone();
+    let (password_hash, known) = match password_hash {
+        Some(hash) => (hash.to_string(), true),
+        None => (String::new(), false),
+    };
+
+    tokio::task::spawn_blocking(move || {
+        let hash = if known { password_hash.as_str() } else { dummy_hash() };
+        let matches = PasswordHash::new(hash)
+            .map(|parsed| Argon2::default().verify_password(password.expose().as_bytes(), &parsed).is_ok())
+            .unwrap_or(false);
+        known && matches
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(format!("Password verification task failed: {}", e)))
+}
+
+/// Password rules: 12 to 128 characters, at least three of lower case, upper
+/// case, digits and symbols, and not containing the local part of the email.
+pub fn check_password_strength(field: &str, password: &Secret, email: &str) -> Result<(), AppError> {
+    let password = password.expose();
+    let mut errors = Vec::new();
+    let mut reject = |code: &str, message: String| errors.push(FieldError {
+        field: field.to_string(),
+        code: code.to_string(),
+        message,
+    });
+
+    let length = password.chars().count();
+    if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&length) {
+        reject("length", format!(
+            "{} must be between {} and {} characters", field, MIN_PASSWORD_LENGTH, MAX_PASSWORD_LENGTH
+        ));
+    }
+
+    let classes = [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_numeric()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ];
+    if classes.iter().filter(|present| **present).count() < 3 {
+        reject("complexity", format!(
+            "{} must mix at least three of lower case, upper case, digits and symbols", field
+        ));
+    }
+
+    let local_part = email.split('@').next().unwrap_or_default().to_lowercase();
+    if local_part.chars().count() >= 3 && password.to_lowercase().contains(&local_part) {
+        reject("contains_email", format!("{} must not contain the email address", field));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::ValidationFailed(errors))
+    }
+}
+
+/// Checks and hashes the password given when a user is created, if any.
+pub async fn hash_new_password(password: Option<Secret>, email: &str) -> Result<Option<String>, AppError> {
+    match password {
+        Some(password) => {
+            check_password_strength("password", &password, email)?;
+            Ok(Some(hash_password(&password).await?))
+        }
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejection_codes(password: &str, email: &str) -> Vec<String> {
+        match check_password_strength("password", &Secret::new(password), email) {
+            Ok(()) => Vec::new(),
+            Err(AppError::ValidationFailed(errors)) => errors.into_iter().map(|e| e.code).collect(),
+            Err(other) => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn accepts_long_mixed_passwords() {
+        assert!(rejection_codes("Correct-Horse-9", "alice@example.com").is_empty());
+        assert!(rejection_codes("correct horse 9 battery", "alice@example.com").is_empty());
+    }
+
+    #[test]
+    fn rejects_short_and_overlong_passwords() {
+        assert_eq!(rejection_codes("Sh0rt-pass", "alice@example.com"), vec!["length"]);
+        let overlong = format!("Aa1-{}", "x".repeat(MAX_PASSWORD_LENGTH));
+        assert_eq!(rejection_codes(&overlong, "alice@example.com"), vec!["length"]);
+    }
+
+    #[test]
+    fn rejects_passwords_with_fewer_than_three_classes() {
+        assert_eq!(rejection_codes("onlylowercaseletters", "alice@example.com"), vec!["complexity"]);
+        assert_eq!(rejection_codes("lowercase123456", "alice@example.com"), vec!["complexity"]);
+    }
+
+    #[test]
+    fn rejects_passwords_containing_the_email_local_part() {
+        assert_eq!(rejection_codes("My-ALICE-password-1", "alice@example.com"), vec!["contains_email"]);
+        // Local parts shorter than three characters are too common to reject.
+        assert!(rejection_codes("Correct-Horse-al9", "al@example.com").is_empty());
+    }
+
+    #[test]
+    fn reports_every_failed_rule() {
+        assert_eq!(rejection_codes("alice", "alice@example.com"), vec!["length", "complexity", "contains_email"]);
+    }
+
+    #[tokio::test]
+    async fn hashes_with_argon2id_and_a_fresh_salt() {
+        let password = Secret::new("Correct-Horse-9");
+        let first = hash_password(&password).await.unwrap();
+        let second = hash_password(&password).await.unwrap();
+
+        assert!(first.starts_with("$argon2id$"));
+        assert_ne!(first, second);
+        assert!(verify_password(&password, Some(&first)).await.unwrap());
+        assert!(verify_password(&password, Some(&second)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_passwords_and_missing_hashes() {
+        let hash = hash_password(&Secret::new("Correct-Horse-9")).await.unwrap();
+
+        assert!(!verify_password(&Secret::new("Correct-Horse-8"), Some(&hash)).await.unwrap());
+        assert!(!verify_password(&Secret::new("Correct-Horse-9"), None).await.unwrap());
+        assert!(!verify_password(&Secret::new("Correct-Horse-9"), Some("not a hash")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn skips_missing_passwords_of_new_users() {
+        assert_eq!(hash_new_password(None, "alice@example.com").await.unwrap(), None);
+        assert!(hash_new_password(Some(Secret::new("short")), "alice@example.com").await.is_err());
+    }
+}
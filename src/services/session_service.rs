@@ -0,0 +1,164 @@
+use std::time::SystemTime;
+use log::{info, warn};
+
+use crate::config::jwt_config::JwtConfig;
+use crate::errors::app_error::AppError;
+use crate::models::auth::{AccessToken, Credentials, RefreshToken};
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::token_service::{generate_opaque_token, hash_opaque_token, issue_access_token};
+
+fn invalid_refresh_token() -> AppError {
+    AppError::Unauthorized("Invalid refresh token".to_string())
+}
+
+/// Issues an access token plus a refresh token in `family_id`.
+async fn issue_tokens(
+    store: &AuthStore,
+    jwt: &JwtConfig,
+    credentials: &Credentials,
+    family_id: String
+) -> Result<AccessToken, AppError> {
+    let refresh_token = generate_opaque_token();
+    store.insert_refresh_token(&RefreshToken {
+        token_hash: hash_opaque_token(&refresh_token),
+        user_id: credentials.user_id.clone(),
+        family_id,
+        expires_at: SystemTime::now() + jwt.refresh_token_ttl,
+        used: false,
+        revoked: false,
+    }).await?;
+
+    let mut token = issue_access_token(jwt, &credentials.user_id, &credentials.email, &credentials.roles)?;
+    token.refresh_token = Some(refresh_token);
+    Ok(token)
+}
+
+/// Starts a new session (refresh token family) for a freshly authenticated user.
+pub async fn start_session(store: &AuthStore, jwt: &JwtConfig, credentials: &Credentials) -> Result<AccessToken, AppError> {
+    issue_tokens(store, jwt, credentials, generate_opaque_token()).await
+}
+
+/// Exchanges a refresh token for a new access and refresh token.
+///
+/// Each refresh token works once. Presenting a used or revoked one means it
+/// leaked, so the whole family is revoked and both parties have to log in again.
+pub async fn refresh_session(store: &AuthStore, jwt: &JwtConfig, refresh_token: &str) -> Result<AccessToken, AppError> {
+    let token_hash = hash_opaque_token(refresh_token);
+    let token = store.find_refresh_token(&token_hash).await?.ok_or_else(invalid_refresh_token)?;
+
+    if token.used || token.revoked || !store.consume_refresh_token(&token_hash).await? {
+        warn!("Refresh token reuse detected for user {}, revoking family {}", token.user_id, token.family_id);
+        store.revoke_refresh_token_family(&token.family_id).await?;
+        return Err(invalid_refresh_token());
+    }
+    if token.expires_at <= SystemTime::now() {
+        return Err(AppError::Unauthorized("Refresh token has expired".to_string()));
+    }
+
+    let credentials = store.find_credentials_by_id(&token.user_id).await?.ok_or_else(invalid_refresh_token)?;
+    info!("Rotating refresh token of user {}", token.user_id);
+    issue_tokens(store, jwt, &credentials, token.family_id).await
+}
+
+/// Revokes the session a refresh token belongs to. Unknown tokens are ignored.
+pub async fn end_session(store: &AuthStore, refresh_token: &str) -> Result<(), AppError> {
+    if let Some(token) = store.find_refresh_token(&hash_opaque_token(refresh_token)).await? {
+        info!("Ending session {} of user {}", token.family_id, token.user_id);
+        store.revoke_refresh_token_family(&token.family_id).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::models::role::Role;
+    use crate::repositories::memory_auth_repository::MemoryStore;
+
+    async fn logged_in() -> (AuthStore, JwtConfig, String, String) {
+        let memory = MemoryStore::new();
+        let user_id = memory.add_user("alice@example.com", None, &[Role::User]);
+        let store = memory.into_store();
+        let jwt = JwtConfig::for_tests();
+        let credentials = store.find_credentials_by_id(&user_id).await.unwrap().unwrap();
+        let token = start_session(&store, &jwt, &credentials).await.unwrap();
+        (store, jwt, user_id, token.refresh_token.unwrap())
+    }
+
+    fn is_unauthorized<T>(result: Result<T, AppError>) -> bool {
+        matches!(result, Err(AppError::Unauthorized(_)))
+    }
+
+    #[tokio::test]
+    async fn rotates_refresh_tokens_within_the_family() {
+        let (store, jwt, user_id, first) = logged_in().await;
+
+        let rotated = refresh_session(&store, &jwt, &first).await.unwrap();
+        let second = rotated.refresh_token.unwrap();
+        assert_ne!(first, second);
+
+        let first_record = store.find_refresh_token(&hash_opaque_token(&first)).await.unwrap().unwrap();
+        let second_record = store.find_refresh_token(&hash_opaque_token(&second)).await.unwrap().unwrap();
+        assert!(first_record.used);
+        assert!(!second_record.used && !second_record.revoked);
+        assert_eq!(first_record.family_id, second_record.family_id);
+        assert_eq!(second_record.user_id, user_id);
+
+        assert!(refresh_session(&store, &jwt, &second).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn reusing_a_rotated_token_revokes_the_family() {
+        let (store, jwt, _, first) = logged_in().await;
+        let second = refresh_session(&store, &jwt, &first).await.unwrap().refresh_token.unwrap();
+
+        assert!(is_unauthorized(refresh_session(&store, &jwt, &first).await));
+
+        // The legitimate holder of the newer token is logged out too.
+        let second_record = store.find_refresh_token(&hash_opaque_token(&second)).await.unwrap().unwrap();
+        assert!(second_record.revoked);
+        assert!(is_unauthorized(refresh_session(&store, &jwt, &second).await));
+    }
+
+    #[tokio::test]
+    async fn leaves_other_sessions_alone_on_reuse() {
+        let (store, jwt, user_id, first) = logged_in().await;
+        let credentials = store.find_credentials_by_id(&user_id).await.unwrap().unwrap();
+        let other = start_session(&store, &jwt, &credentials).await.unwrap().refresh_token.unwrap();
+
+        refresh_session(&store, &jwt, &first).await.unwrap();
+        assert!(is_unauthorized(refresh_session(&store, &jwt, &first).await));
+
+        assert!(refresh_session(&store, &jwt, &other).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_and_expired_tokens() {
+        let (store, jwt, user_id, _) = logged_in().await;
+        assert!(is_unauthorized(refresh_session(&store, &jwt, "not-a-token").await));
+
+        let expired = generate_opaque_token();
+        store.insert_refresh_token(&RefreshToken {
+            token_hash: hash_opaque_token(&expired),
+            user_id,
+            family_id: generate_opaque_token(),
+            expires_at: SystemTime::now() - Duration::from_secs(1),
+            used: false,
+            revoked: false,
+        }).await.unwrap();
+        assert!(is_unauthorized(refresh_session(&store, &jwt, &expired).await));
+    }
+
+    #[tokio::test]
+    async fn logout_revokes_the_session() {
+        let (store, jwt, _, first) = logged_in().await;
+        let second = refresh_session(&store, &jwt, &first).await.unwrap().refresh_token.unwrap();
+
+        end_session(&store, &second).await.unwrap();
+        assert!(is_unauthorized(refresh_session(&store, &jwt, &second).await));
+        // Unknown tokens are ignored.
+        end_session(&store, "not-a-token").await.unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, encode, errors::ErrorKind, Validation};
+use log::{error, warn};
+use rand::{rngs::OsRng, RngCore};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::jwt_config::JwtConfig;
+use crate::errors::app_error::AppError;
+use crate::models::auth::{AccessToken, Claims};
+use crate::models::role::Role;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// Signs `claims` with the current key, named in the header so tokens still
+/// verify after the key is rotated.
+pub fn sign_jwt<T: Serialize>(config: &JwtConfig, claims: &T) -> Result<String, AppError> {
+    encode(&config.header(), claims, &config.encoding_key).map_err(|e| {
+        error!("Failed to sign token: {}", e);
+        AppError::InternalServerError("Failed to issue token".to_string())
+    })
+}
+
+/// Decodes a token signed by this service with whichever key its `kid` names.
+pub fn decode_jwt<T: DeserializeOwned>(
+    config: &JwtConfig,
+    token: &str,
+    validation: &Validation
+) -> Result<T, jsonwebtoken::errors::Error> {
+    let header = decode_header(token)?;
+    let key = config.decoding_key_for(header.kid.as_deref()).ok_or(ErrorKind::InvalidSignature)?;
+    Ok(decode::<T>(token, key, validation)?.claims)
+}
+
+/// Signs a short-lived access token for the given user.
+pub fn issue_access_token(config: &JwtConfig, user_id: &str, email: &str, roles: &[Role]) -> Result<AccessToken, AppError> {
+    let issued_at = now();
+    let claims = Claims {
+        sub: user_id.to_string(),
+        email: email.to_string(),
+        roles: roles.to_vec(),
+        iss: config.issuer.clone(),
+        aud: config.audience.clone(),
+        iat: issued_at,
+        exp: issued_at + config.access_token_ttl.as_secs(),
+    };
+
+    Ok(AccessToken {
+        access_token: sign_jwt(config, &claims)?,
+        token_type: "Bearer".to_string(),
+        expires_in: config.access_token_ttl.as_secs(),
+        refresh_token: None,
+    })
+}
+
+/// Checks the signature, expiry, issuer and audience of an access token.
+pub fn verify_access_token(config: &JwtConfig, token: &str) -> Result<Claims, AppError> {
+    let mut validation = Validation::new(config.algorithm);
+    validation.set_issuer(&[&config.issuer]);
+    validation.set_audience(&[&config.audience]);
+    validation.leeway = 30;
+
+    decode_jwt::<Claims>(config, token, &validation)
+        .map_err(|e| {
+            warn!("Rejected access token: {}", e);
+            match e.kind() {
+                ErrorKind::ExpiredSignature => AppError::Unauthorized("Access token has expired".to_string()),
+                _ => AppError::Unauthorized("Invalid access token".to_string()),
+            }
+        })
+}
+
+/// 256 random bits, base64url encoded, for tokens the server looks up rather
+/// than verifies (refresh tokens, ...).
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Opaque tokens are stored as their SHA-256 so a database leak does not hand
+/// out live sessions. They are random enough that a slow hash is unnecessary.
+pub fn hash_opaque_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,231 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{error, info, warn};
+use rand::rngs::OsRng;
+use rand::{Rng, RngCore};
+use totp_rs::{Algorithm, Secret as TotpSecret, TOTP};
+
+use crate::errors::app_error::AppError;
+use crate::models::auth::{Credentials, RecoveryCodes, TotpEnrollment};
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::token_service::hash_opaque_token;
+use crate::utils::secret::Secret;
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP_SECS: u64 = 30;
+/// Steps accepted on either side of the current one, for clock drift.
+const TOTP_ALLOWED_DRIFT: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_GROUPS: usize = 3;
+const RECOVERY_CODE_GROUP_LEN: usize = 4;
+/// No 0/o, 1/l/i, so codes survive being read aloud or copied by hand.
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+fn invalid_code() -> AppError {
+    AppError::Unauthorized("Invalid two-factor code".to_string())
+}
+
+/// Builds the TOTP generator for a stored base32 secret. `:` separates issuer
+/// and account in otpauth URIs, so it is not allowed in either.
+fn totp(secret: &str, issuer: Option<&str>, account: &str) -> Result<TOTP, AppError> {
+    let bytes = TotpSecret::Encoded(secret.to_string()).to_bytes()
+        .map_err(|e| AppError::InternalServerError(format!("Stored TOTP secret is invalid: {:?}", e)))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP_SECS,
+        bytes,
+        issuer.map(|issuer| issuer.replace(':', "")),
+        account.replace(':', ""),
+    ).map_err(|e| AppError::InternalServerError(format!("Failed to build TOTP: {}", e)))
+}
+
+fn current_step() -> i64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    (now / TOTP_STEP_SECS) as i64
+}
+
+/// Checks `code` against the steps around now and records the matching one,
+/// so the same code cannot be used twice.
+async fn verify_totp(store: &AuthStore, credentials: &Credentials, secret: &str, code: &str) -> Result<(), AppError> {
+    let generator = totp(secret, None, &credentials.email)?;
+    let now = current_step();
+    let step = (now - TOTP_ALLOWED_DRIFT..=now + TOTP_ALLOWED_DRIFT)
+        .find(|step| generator.check(code, *step as u64 * TOTP_STEP_SECS))
+        .ok_or_else(invalid_code)?;
+
+    if !store.record_totp_step(&credentials.user_id, step).await? {
+        warn!("Replayed TOTP code for user {}", credentials.user_id);
+        return Err(invalid_code());
+    }
+    Ok(())
+}
+
+fn generate_recovery_code() -> String {
+    let groups: Vec<String> = (0..RECOVERY_CODE_GROUPS)
+        .map(|_| (0..RECOVERY_CODE_GROUP_LEN)
+            .map(|_| RECOVERY_CODE_ALPHABET[OsRng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+            .collect())
+        .collect();
+    groups.join("-")
+}
+
+/// Recovery codes are compared without dashes, spaces or case.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    hash_opaque_token(&normalize_recovery_code(code))
+}
+
+/// Starts (or restarts) enrollment with a fresh secret. It is only used for
+/// logins once `confirm_enrollment` has seen a code generated from it.
+pub async fn begin_enrollment(store: &AuthStore, issuer: &str, user_id: &str) -> Result<TotpEnrollment, AppError> {
+    let credentials = store.find_credentials_by_id(user_id).await?
+        .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", user_id)))?;
+    if credentials.totp_enabled {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let mut bytes = [0u8; 20];
+    OsRng.fill_bytes(&mut bytes);
+    let secret = TotpSecret::Raw(bytes.to_vec()).to_encoded().to_string();
+    if !store.set_pending_totp_secret(user_id, &secret).await? {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    info!("Started TOTP enrollment for user {}", user_id);
+    Ok(TotpEnrollment {
+        otpauth_uri: totp(&secret, Some(issuer), &credentials.email)?.get_url(),
+        secret,
+    })
+}
+
+/// Enables 2FA once the user proves their authenticator works, and returns
+/// the recovery codes. Only their hashes are kept.
+pub async fn confirm_enrollment(store: &AuthStore, user_id: &str, code: &Secret) -> Result<RecoveryCodes, AppError> {
+    let credentials = store.find_credentials_by_id(user_id).await?
+        .ok_or_else(|| AppError::NotFound(format!("User with id {} not found", user_id)))?;
+    if credentials.totp_enabled {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+    let Some(secret) = credentials.totp_secret.as_ref() else {
+        return Err(AppError::BadRequest("Two-factor enrollment has not been started".to_string()));
+    };
+
+    verify_totp(store, &credentials, secret.expose(), code.expose().trim()).await?;
+
+    let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect();
+    let hashes: Vec<String> = recovery_codes.iter().map(|code| hash_recovery_code(code)).collect();
+    store.enable_totp(user_id, &hashes).await?;
+    info!("Enabled TOTP for user {}", user_id);
+    Ok(RecoveryCodes { recovery_codes })
+}
+
+/// Checks the second factor of a user whose password was just verified: a
+/// current TOTP code, or one of the recovery codes, which is then used up.
+/// Users without 2FA pass.
+pub async fn verify_second_factor(
+    store: &AuthStore,
+    credentials: &Credentials,
+    code: Option<&Secret>
+) -> Result<(), AppError> {
+    if !credentials.totp_enabled {
+        return Ok(());
+    }
+    let Some(secret) = credentials.totp_secret.as_ref() else {
+        error!("User {} has 2FA enabled without a secret", credentials.user_id);
+        return Err(invalid_code());
+    };
+    let Some(code) = code.map(|code| code.expose().trim()).filter(|code| !code.is_empty()) else {
+        return Err(AppError::Unauthorized("Two-factor code required".to_string()));
+    };
+
+    if code.len() == TOTP_DIGITS && code.chars().all(|c| c.is_ascii_digit()) {
+        return verify_totp(store, credentials, secret.expose(), code).await;
+    }
+    if store.consume_recovery_code(&credentials.user_id, &hash_recovery_code(code)).await? {
+        info!("User {} used a recovery code", credentials.user_id);
+        return Ok(());
+    }
+    Err(invalid_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::role::Role;
+    use crate::repositories::memory_auth_repository::MemoryStore;
+
+    fn code_at(enrollment: &TotpEnrollment, step: i64) -> Secret {
+        let generator = totp(&enrollment.secret, None, "alice@example.com").unwrap();
+        Secret::new(generator.generate(step as u64 * TOTP_STEP_SECS))
+    }
+
+    /// A user with 2FA enabled, and the code used to confirm it.
+    async fn enrolled() -> (AuthStore, String, TotpEnrollment, RecoveryCodes, Secret) {
+        let memory = MemoryStore::new();
+        let user_id = memory.add_user("alice@example.com", None, &[Role::User]);
+        let store = memory.into_store();
+        let enrollment = begin_enrollment(&store, "User Service", &user_id).await.unwrap();
+        let code = code_at(&enrollment, current_step());
+        let recovery_codes = confirm_enrollment(&store, &user_id, &code).await.unwrap();
+        (store, user_id, enrollment, recovery_codes, code)
+    }
+
+    async fn credentials(store: &AuthStore, user_id: &str) -> Credentials {
+        store.find_credentials_by_id(user_id).await.unwrap().unwrap()
+    }
+
+    fn is_unauthorized(result: Result<(), AppError>) -> bool {
+        matches!(result, Err(AppError::Unauthorized(_)))
+    }
+
+    #[tokio::test]
+    async fn a_totp_code_is_accepted_only_once() {
+        let (store, user_id, enrollment, _, confirmation_code) = enrolled().await;
+        let credentials = credentials(&store, &user_id).await;
+        assert!(credentials.totp_enabled);
+
+        // The code that confirmed enrollment is already spent.
+        assert!(is_unauthorized(verify_second_factor(&store, &credentials, Some(&confirmation_code)).await));
+
+        let next = code_at(&enrollment, current_step() + 1);
+        verify_second_factor(&store, &credentials, Some(&next)).await.unwrap();
+        assert!(is_unauthorized(verify_second_factor(&store, &credentials, Some(&next)).await));
+        // Nor is a code from before the last one accepted.
+        let previous = code_at(&enrollment, current_step() - 1);
+        assert!(is_unauthorized(verify_second_factor(&store, &credentials, Some(&previous)).await));
+    }
+
+    #[tokio::test]
+    async fn a_recovery_code_is_accepted_only_once() {
+        let (store, user_id, _, recovery_codes, _) = enrolled().await;
+        let credentials = credentials(&store, &user_id).await;
+        assert_eq!(recovery_codes.recovery_codes.len(), RECOVERY_CODE_COUNT);
+
+        // Case and separators do not matter.
+        let code = Secret::new(recovery_codes.recovery_codes[0].to_uppercase().replace('-', " "));
+        verify_second_factor(&store, &credentials, Some(&code)).await.unwrap();
+        assert!(is_unauthorized(verify_second_factor(&store, &credentials, Some(&code)).await));
+
+        let other = Secret::new(recovery_codes.recovery_codes[1].clone());
+        verify_second_factor(&store, &credentials, Some(&other)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_code_is_required_once_enabled() {
+        let (store, user_id, _, _, _) = enrolled().await;
+        let alice = credentials(&store, &user_id).await;
+        assert!(is_unauthorized(verify_second_factor(&store, &alice, None).await));
+        assert!(is_unauthorized(verify_second_factor(&store, &alice, Some(&Secret::new("aaaa-bbbb-cccc"))).await));
+
+        let memory = MemoryStore::new();
+        let user_id = memory.add_user("bob@example.com", None, &[Role::User]);
+        let store = memory.into_store();
+        let bob = credentials(&store, &user_id).await;
+        verify_second_factor(&store, &bob, None).await.unwrap();
+    }
+}
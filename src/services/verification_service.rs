@@ -0,0 +1,66 @@
+use std::time::SystemTime;
+use log::{info, warn};
+
+use crate::config::mail_config::MailConfig;
+use crate::errors::app_error::AppError;
+use crate::models::auth::{EmailVerified, OneTimeToken, TokenPurpose};
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::mailer::{EmailMessage, SharedMailer};
+use crate::services::token_service::{generate_opaque_token, hash_opaque_token};
+
+/// Mails a single-use verification link for the user's current email.
+pub async fn request_email_verification(
+    store: &AuthStore,
+    mailer: &SharedMailer,
+    config: &MailConfig,
+    user_id: &str
+) -> Result<(), AppError> {
+    let credentials = store.find_credentials_by_id(user_id).await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    if credentials.email_verified {
+        return Err(AppError::Conflict("Email is already verified".to_string()));
+    }
+
+    let token = generate_opaque_token();
+    store.insert_one_time_token(&OneTimeToken {
+        token_hash: hash_opaque_token(&token),
+        purpose: TokenPurpose::EmailVerification,
+        user_id: credentials.user_id.clone(),
+        email: credentials.email.clone(),
+        expires_at: SystemTime::now() + config.verification_token_ttl,
+    }).await?;
+
+    let link = format!("{}/verify-email?token={}", config.public_base_url, token);
+    mailer.send(EmailMessage {
+        to: credentials.email,
+        subject: "Verify your email address".to_string(),
+        body: format!(
+            "Hello {},\n\nConfirm your email address by opening this link within {} hours:\n\n{}\n",
+            credentials.name,
+            config.verification_token_ttl.as_secs() / 3600,
+            link
+        ),
+    }).await?;
+
+    info!("Sent verification email to user {}", credentials.user_id);
+    Ok(())
+}
+
+/// Uses up a verification token and marks the address it was sent to as verified.
+pub async fn confirm_email(store: &AuthStore, token: &str) -> Result<EmailVerified, AppError> {
+    let invalid = || AppError::BadRequest("Invalid or expired verification token".to_string());
+    let token = store.consume_one_time_token(&hash_opaque_token(token), TokenPurpose::EmailVerification).await?
+        .ok_or_else(invalid)?;
+
+    if !store.mark_email_verified(&token.user_id, &token.email).await? {
+        warn!("User {} changed email since the verification was requested", token.user_id);
+        return Err(invalid());
+    }
+
+    info!("Verified email of user {}", token.user_id);
+    Ok(EmailVerified {
+        user_id: token.user_id,
+        email: token.email,
+        email_verified: true,
+    })
+}
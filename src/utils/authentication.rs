@@ -0,0 +1,381 @@
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use jsonwebtoken::Validation;
+use log::warn;
+use rocket::http::RawStr;
+use rocket::request::{self, FromRequest, Request};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::{Object, SecurityRequirement, SecurityScheme, SecuritySchemeData};
+use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::config::jwt_config::JwtConfig;
+use crate::config::oauth_provider_config::OAuthProviderConfig;
+use crate::errors::app_error::{AppError, GuardFailure};
+use crate::models::oauth::OAuthClaims;
+use crate::models::role::{Permission, Role};
+use crate::repositories::auth_repository::AuthStore;
+use crate::services::token_service::{decode_jwt, hash_opaque_token, verify_access_token};
+
+/// Names of the security schemes in the OpenAPI document.
+pub const BEARER_AUTH: &str = "bearerAuth";
+pub const API_KEY_AUTH: &str = "apiKeyAuth";
+pub const CLIENT_BASIC_AUTH: &str = "clientBasicAuth";
+pub const OAUTH_BEARER_AUTH: &str = "oauthBearerAuth";
+
+/// Fails a request guard with `error`, keeping its message for `json_catcher`.
+pub fn reject<T>(req: &Request<'_>, error: AppError) -> request::Outcome<T, AppError> {
+    let status = error.status();
+    req.local_cache(|| GuardFailure(Some(error.message())));
+    request::Outcome::Error((status, error))
+}
+
+/// The caller identified by a valid `Authorization: Bearer` access token.
+///
+/// Taking it as a handler argument is what makes a route protected: requests
+/// without a valid token are answered `401 Unauthorized` before the handler runs.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AuthenticatedUser {
+    pub user_id: String,
+    pub email: String,
+    pub roles: Vec<Role>,
+}
+
+impl AuthenticatedUser {
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.roles.iter().any(|role| role.permissions().contains(&permission))
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthenticatedUser {
+    type Error = AppError;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let Some(config) = req.rocket().state::<JwtConfig>() else {
+            return reject(req, AppError::InternalServerError("JWT configuration is not loaded".to_string()));
+        };
+
+        let token = req.headers().get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(str::trim);
+        let Some(token) = token else {
+            return reject(req, AppError::Unauthorized("Missing bearer token".to_string()));
+        };
+
+        match verify_access_token(config, token) {
+            Ok(claims) => request::Outcome::Success(AuthenticatedUser {
+                user_id: claims.sub,
+                email: claims.email,
+                roles: claims.roles,
+            }),
+            Err(e) => reject(req, e),
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for AuthenticatedUser {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        let scheme = SecurityScheme {
+            description: Some("Access token returned by `POST /auth/login`".to_owned()),
+            data: SecuritySchemeData::Http {
+                scheme: "bearer".to_owned(),
+                bearer_format: Some("JWT".to_owned()),
+            },
+            extensions: Object::default(),
+        };
+        let mut requirement = SecurityRequirement::new();
+        requirement.insert(BEARER_AUTH.to_owned(), Vec::new());
+        Ok(RequestHeaderInput::Security(BEARER_AUTH.to_owned(), scheme, requirement))
+    }
+}
+
+/// A service authenticated with an `X-Api-Key` header.
+#[derive(Debug)]
+pub struct ApiKeyCaller {
+    pub key_id: String,
+    pub name: String,
+    pub scopes: Vec<Permission>,
+}
+
+/// Whoever is calling a route open to both users and services: a bearer
+/// token identifies a user, an `X-Api-Key` header a service.
+#[derive(Debug)]
+pub enum Caller {
+    User(AuthenticatedUser),
+    Service(ApiKeyCaller),
+}
+
+impl Caller {
+    /// Identifies the caller in logs.
+    pub fn id(&self) -> String {
+        match self {
+            Caller::User(user) => user.user_id.clone(),
+            Caller::Service(service) => format!("api-key:{} ({})", service.key_id, service.name),
+        }
+    }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        match self {
+            Caller::User(user) => user.has_permission(permission),
+            Caller::Service(service) => service.scopes.contains(&permission),
+        }
+    }
+
+    /// Lets users act on their own account, and anyone with `permission` on any account.
+    pub fn require_self_or(&self, user_id: &str, permission: Permission) -> Result<(), AppError> {
+        let is_self = matches!(self, Caller::User(user) if user.user_id == user_id);
+        if is_self || self.has_permission(permission) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden("You are not allowed to access this user".to_string()))
+        }
+    }
+}
+
+async fn authenticate_api_key(req: &Request<'_>, api_key: &str) -> Result<ApiKeyCaller, AppError> {
+    let store = req.rocket().state::<AuthStore>()
+        .ok_or_else(|| AppError::InternalServerError("Auth store is not loaded".to_string()))?;
+
+    let key = store.find_api_key_by_hash(&hash_opaque_token(api_key.trim())).await?
+        .filter(|key| key.is_usable())
+        .ok_or_else(|| AppError::Unauthorized("Invalid API key".to_string()))?;
+
+    store.touch_api_key(&key.id).await?;
+    Ok(ApiKeyCaller {
+        key_id: key.id,
+        name: key.name,
+        scopes: key.scopes,
+    })
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Caller {
+    type Error = AppError;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        if let Some(api_key) = req.headers().get_one("X-Api-Key") {
+            return match authenticate_api_key(req, api_key).await {
+                Ok(service) => request::Outcome::Success(Caller::Service(service)),
+                Err(e) => reject(req, e),
+            };
+        }
+
+        AuthenticatedUser::from_request(req).await.map(Caller::User)
+    }
+}
+
+/// Documents the API key scheme; `openapi_routes` adds bearer auth as the alternative.
+impl<'r> OpenApiFromRequest<'r> for Caller {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        let scheme = SecurityScheme {
+            description: Some("API key created with `POST /auth/api-keys`".to_owned()),
+            data: SecuritySchemeData::ApiKey {
+                name: "X-Api-Key".to_owned(),
+                location: "header".to_owned(),
+            },
+            extensions: Object::default(),
+        };
+        let mut requirement = SecurityRequirement::new();
+        requirement.insert(API_KEY_AUTH.to_owned(), Vec::new());
+        Ok(RequestHeaderInput::Security(API_KEY_AUTH.to_owned(), scheme, requirement))
+    }
+}
+
+/// Ties a marker type used with `Permitted` to the permission it demands.
+pub trait RequiredPermission: Send + Sync + 'static {
+    const PERMISSION: Permission;
+}
+
+macro_rules! required_permissions {
+    ($($marker:ident => $permission:ident),* $(,)?) => {
+        $(
+            pub struct $marker;
+
+            impl RequiredPermission for $marker {
+                const PERMISSION: Permission = Permission::$permission;
+            }
+        )*
+    };
+}
+
+required_permissions! {
+    CanListUsers => ListUsers,
+    CanManageRoles => ManageRoles,
+    CanManageApiKeys => ManageApiKeys,
+    CanManageLockouts => ManageLockouts,
+    CanManageOAuthClients => ManageOAuthClients,
+}
+
+/// A user or service holding the permission `P` requires; anyone else gets
+/// `403 Forbidden` before the handler runs.
+pub struct Permitted<P: RequiredPermission> {
+    caller: Caller,
+    permission: PhantomData<P>,
+}
+
+impl<P: RequiredPermission> Deref for Permitted<P> {
+    type Target = Caller;
+
+    fn deref(&self) -> &Caller {
+        &self.caller
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, P: RequiredPermission> FromRequest<'r> for Permitted<P> {
+    type Error = AppError;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let caller = match Caller::from_request(req).await {
+            request::Outcome::Success(caller) => caller,
+            request::Outcome::Error(e) => return request::Outcome::Error(e),
+            request::Outcome::Forward(status) => return request::Outcome::Forward(status),
+        };
+
+        if caller.has_permission(P::PERMISSION) {
+            request::Outcome::Success(Permitted { caller, permission: PhantomData })
+        } else {
+            reject(req, AppError::Forbidden(format!("Requires the {:?} permission", P::PERMISSION)))
+        }
+    }
+}
+
+impl<'r, P: RequiredPermission> OpenApiFromRequest<'r> for Permitted<P> {
+    fn from_request_input(
+        gen: &mut OpenApiGenerator,
+        name: String,
+        required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        <Caller as OpenApiFromRequest>::from_request_input(gen, name, required)
+    }
+}
+
+/// Client credentials sent to the token endpoint with HTTP Basic, where the
+/// id and secret are form-encoded before being joined (RFC 6749, section 2.3.1).
+pub struct ClientSecretBasic {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+fn parse_basic(header: &str) -> Option<ClientSecretBasic> {
+    let encoded = header.strip_prefix("Basic ")?.trim();
+    let decoded = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    Some(ClientSecretBasic {
+        client_id: RawStr::new(client_id).url_decode_lossy().into_owned(),
+        client_secret: RawStr::new(client_secret).url_decode_lossy().into_owned(),
+    })
+}
+
+/// Forwards when there is no Basic `Authorization` header, so handlers take
+/// it as an `Option` and fall back to credentials in the form.
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientSecretBasic {
+    type Error = AppError;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        match req.headers().get_one("Authorization").and_then(parse_basic) {
+            Some(credentials) => request::Outcome::Success(credentials),
+            None => request::Outcome::Forward(rocket::http::Status::Unauthorized),
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for ClientSecretBasic {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        let scheme = SecurityScheme {
+            description: Some("Client id and secret of a registered OAuth client".to_owned()),
+            data: SecuritySchemeData::Http {
+                scheme: "basic".to_owned(),
+                bearer_format: None,
+            },
+            extensions: Object::default(),
+        };
+        let mut requirement = SecurityRequirement::new();
+        requirement.insert(CLIENT_BASIC_AUTH.to_owned(), Vec::new());
+        Ok(RequestHeaderInput::Security(CLIENT_BASIC_AUTH.to_owned(), scheme, requirement))
+    }
+}
+
+/// A user on whose behalf an OAuth client calls, identified by an access
+/// token from `POST /oauth/token` that was granted the `openid` scope.
+#[derive(Debug)]
+pub struct OAuthBearer {
+    pub claims: OAuthClaims,
+}
+
+fn verify_oauth_token(req: &Request<'_>, token: &str) -> Result<OAuthClaims, AppError> {
+    let (Some(jwt), Some(config)) = (req.rocket().state::<JwtConfig>(), req.rocket().state::<OAuthProviderConfig>()) else {
+        return Err(AppError::InternalServerError("OAuth provider configuration is not loaded".to_string()));
+    };
+
+    // Each token is issued to the client as its audience; any of them may call.
+    let mut validation = Validation::new(jwt.algorithm);
+    validation.set_issuer(&[&config.issuer]);
+    validation.validate_aud = false;
+    validation.leeway = 30;
+
+    let claims = decode_jwt::<OAuthClaims>(jwt, token, &validation).map_err(|e| {
+        warn!("Rejected OAuth access token: {}", e);
+        AppError::Unauthorized("Invalid or expired access token".to_string())
+    })?;
+    if !claims.has_scope("openid") {
+        return Err(AppError::Forbidden("The access token was not granted the openid scope".to_string()));
+    }
+    Ok(claims)
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for OAuthBearer {
+    type Error = AppError;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let token = req.headers().get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(str::trim);
+        let Some(token) = token else {
+            return reject(req, AppError::Unauthorized("Missing bearer token".to_string()));
+        };
+
+        match verify_oauth_token(req, token) {
+            Ok(claims) => request::Outcome::Success(OAuthBearer { claims }),
+            Err(e) => reject(req, e),
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for OAuthBearer {
+    fn from_request_input(
+        _gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        let scheme = SecurityScheme {
+            description: Some("Access token returned by `POST /oauth/token` with the openid scope".to_owned()),
+            data: SecuritySchemeData::Http {
+                scheme: "bearer".to_owned(),
+                bearer_format: Some("JWT".to_owned()),
+            },
+            extensions: Object::default(),
+        };
+        let mut requirement = SecurityRequirement::new();
+        requirement.insert(OAUTH_BEARER_AUTH.to_owned(), Vec::new());
+        Ok(RequestHeaderInput::Security(OAUTH_BEARER_AUTH.to_owned(), scheme, requirement))
+    }
+}
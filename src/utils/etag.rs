@@ -0,0 +1,271 @@
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use rocket::response::{self, Responder, Response};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::{Object, Parameter, ParameterValue, Responses};
+use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
+use rocket_okapi::response::OpenApiResponderInner;
+
+use crate::errors::app_error::AppError;
+use crate::utils::authentication::reject;
+
+/// Strong entity tag for a given resource version.
+pub fn etag(version: i64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Parsed `If-Match` / `If-None-Match` header value.
+#[derive(Debug)]
+enum EntityTags {
+    Any,
+    /// `(weak, version)` pairs; tags this service never issued are dropped.
+    List(Vec<(bool, i64)>),
+}
+
+/// Parses a comma-separated list of entity tags, or `*`. Returns `None` when
+/// the header is not valid entity tag syntax.
+fn parse_entity_tags(header: &str) -> Option<EntityTags> {
+    if header.trim() == "*" {
+        return Some(EntityTags::Any);
+    }
+
+    let separators: &[char] = &[',', ' ', '\t'];
+    let mut rest = header.trim_start_matches(separators);
+    let mut tags = Vec::new();
+    let mut seen = 0;
+    while !rest.is_empty() {
+        let (weak, quoted) = match rest.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, rest),
+        };
+        // Opaque tags may contain commas, so the list is split on quotes first.
+        let (opaque, after) = quoted.strip_prefix('"')?.split_once('"')?;
+        seen += 1;
+        if let Some(version) = opaque.parse::<i64>().ok().filter(|version| version.to_string() == opaque) {
+            tags.push((weak, version));
+        }
+
+        let after = after.trim_start_matches([' ', '\t']);
+        rest = match after.strip_prefix(',') {
+            Some(next) => next.trim_start_matches(separators),
+            None if after.is_empty() => after,
+            None => return None,
+        };
+    }
+
+    (seen > 0).then_some(EntityTags::List(tags))
+}
+
+fn header_parameter(gen: &mut OpenApiGenerator, name: &str, description: &str) -> RequestHeaderInput {
+    RequestHeaderInput::Parameter(Parameter {
+        name: name.to_owned(),
+        location: "header".to_owned(),
+        description: Some(description.to_owned()),
+        required: false,
+        deprecated: false,
+        allow_empty_value: false,
+        value: ParameterValue::Schema {
+            style: None,
+            explode: None,
+            allow_reserved: false,
+            schema: gen.json_schema::<String>(),
+            example: None,
+            examples: None,
+        },
+        extensions: Object::default(),
+    })
+}
+
+/// `If-Match` request header, used for optimistic concurrency on writes.
+#[derive(Debug)]
+pub struct IfMatch(Option<EntityTags>);
+
+impl IfMatch {
+    /// Versions the stored resource may have for the write to proceed, or
+    /// `None` when the write is unconditional (no header, or `*`).
+    ///
+    /// Weak tags never match, as required for `If-Match`, so a header holding
+    /// only weak or foreign tags expects no version and the write fails with
+    /// `412 Precondition Failed`.
+    pub fn expected_versions(&self) -> Option<Vec<i64>> {
+        match &self.0 {
+            Some(EntityTags::List(tags)) => Some(
+                tags.iter().filter(|(weak, _)| !weak).map(|(_, version)| *version).collect()
+            ),
+            Some(EntityTags::Any) | None => None,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfMatch {
+    type Error = AppError;
+
+    /// A header that is not an entity tag list is answered `400 Bad Request`
+    /// rather than ignored, which would make the write unconditional.
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        match req.headers().get_one("If-Match") {
+            None => request::Outcome::Success(IfMatch(None)),
+            Some(header) => match parse_entity_tags(header) {
+                Some(tags) => request::Outcome::Success(IfMatch(Some(tags))),
+                None => reject(req, AppError::BadRequest("Malformed If-Match header".to_string())),
+            },
+        }
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for IfMatch {
+    fn from_request_input(
+        gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(header_parameter(gen, "If-Match", "Only apply the change if the user's current ETag matches"))
+    }
+}
+
+/// `If-None-Match` request header, used for conditional reads.
+#[derive(Debug)]
+pub struct IfNoneMatch(Option<EntityTags>);
+
+impl IfNoneMatch {
+    /// Weak comparison against the current version, as required for `If-None-Match`.
+    pub fn matches(&self, version: i64) -> bool {
+        match &self.0 {
+            Some(EntityTags::Any) => true,
+            Some(EntityTags::List(tags)) => tags.iter().any(|(_, tag)| *tag == version),
+            None => false,
+        }
+    }
+
+    /// Answers `304 Not Modified` when the client already holds `version`.
+    pub fn respond<R>(&self, version: i64, inner: R) -> Conditional<R> {
+        if self.matches(version) {
+            Conditional::NotModified(etag(version))
+        } else {
+            Conditional::Modified(Tagged::new(version, inner))
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfNoneMatch {
+    type Error = ();
+
+    /// A malformed header is ignored, which just sends the full response.
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        request::Outcome::Success(IfNoneMatch(req.headers().get_one("If-None-Match").and_then(parse_entity_tags)))
+    }
+}
+
+impl<'r> OpenApiFromRequest<'r> for IfNoneMatch {
+    fn from_request_input(
+        gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        Ok(header_parameter(gen, "If-None-Match", "Answer 304 Not Modified if the user's current ETag matches"))
+    }
+}
+
+/// Wraps a responder and adds an `ETag` header for the resource version.
+pub struct Tagged<R> {
+    etag: String,
+    inner: R,
+}
+
+impl<R> Tagged<R> {
+    pub fn new(version: i64, inner: R) -> Self {
+        Tagged { etag: etag(version), inner }
+    }
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for Tagged<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = self.inner.respond_to(req)?;
+        response.set_raw_header("ETag", self.etag);
+        Ok(response)
+    }
+}
+
+impl<R: OpenApiResponderInner> OpenApiResponderInner for Tagged<R> {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        R::responses(gen)
+    }
+}
+
+/// Response to a conditional `GET`: the tagged resource, or `304 Not Modified`.
+pub enum Conditional<R> {
+    Modified(Tagged<R>),
+    NotModified(String),
+}
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for Conditional<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        match self {
+            Conditional::Modified(tagged) => tagged.respond_to(req),
+            Conditional::NotModified(etag) => Response::build()
+                .status(Status::NotModified)
+                .raw_header("ETag", etag)
+                .ok(),
+        }
+    }
+}
+
+impl<R: OpenApiResponderInner> OpenApiResponderInner for Conditional<R> {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = R::responses(gen)?;
+        rocket_okapi::util::ensure_status_code_exists(&mut responses, 304);
+        Ok(responses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn if_match(header: &str) -> Option<Vec<i64>> {
+        IfMatch(Some(parse_entity_tags(header).unwrap())).expected_versions()
+    }
+
+    fn if_none_match(header: &str) -> IfNoneMatch {
+        IfNoneMatch(parse_entity_tags(header))
+    }
+
+    #[test]
+    fn any_makes_writes_unconditional() {
+        assert_eq!(if_match("*"), None);
+        assert_eq!(IfMatch(None).expected_versions(), None);
+        assert!(if_none_match(" * ").matches(7));
+    }
+
+    #[test]
+    fn parses_lists_of_tags() {
+        assert_eq!(if_match(r#""3""#), Some(vec![3]));
+        assert_eq!(if_match(r#""3", "4","5""#), Some(vec![3, 4, 5]));
+        assert_eq!(if_match(r#", "3" ,,"#), Some(vec![3]));
+    }
+
+    #[test]
+    fn weak_tags_never_match_if_match() {
+        assert_eq!(if_match(r#"W/"3", "4""#), Some(vec![4]));
+        assert_eq!(if_match(r#"W/"3""#), Some(vec![]));
+
+        assert!(if_none_match(r#"W/"3""#).matches(3));
+        assert!(!if_none_match(r#"W/"3""#).matches(4));
+    }
+
+    #[test]
+    fn drops_tags_this_service_never_issued() {
+        assert_eq!(if_match(r#""abc", "a,b", "+3", "03", "3""#), Some(vec![3]));
+        assert_eq!(if_match(r#""""#), Some(vec![]));
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        for header in ["", " , ", "3", r#""3"x"#, r#""3" "4""#, r#""3"#, r#"W/3"#, r#"w/"3""#, r#""3", *"#] {
+            assert!(parse_entity_tags(header).is_none(), "accepted {:?}", header);
+        }
+        assert!(!IfNoneMatch(parse_entity_tags("3")).matches(3));
+    }
+}
@@ -0,0 +1,75 @@
+use std::marker::PhantomData;
+
+use rocket::data::{self, Data, FromData};
+use rocket::serde::json::Json;
+use rocket::Request;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::{MediaType, RequestBody};
+use rocket_okapi::request::OpenApiFromData;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::errors::app_error::AppError;
+use crate::utils::validation::FieldError;
+
+pub const MERGE_PATCH_MEDIA_TYPE: &str = "application/merge-patch+json";
+
+/// An RFC 7396 JSON Merge Patch body describing changes to `T`.
+///
+/// Every member of `T` is optional in the patch; a member set to `null` asks
+/// for its removal, which is rejected because the patched resources have no
+/// removable fields.
+pub struct MergePatch<T> {
+    document: Value,
+    _target: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> MergePatch<T> {
+    pub fn into_inner(self) -> Result<T, AppError> {
+        let members = self.document.as_object()
+            .ok_or_else(|| AppError::BadRequest("A merge patch must be a JSON object".to_string()))?;
+
+        let removed: Vec<FieldError> = members.iter()
+            .filter(|(_, value)| value.is_null())
+            .map(|(field, _)| FieldError {
+                field: field.clone(),
+                code: "required".to_string(),
+                message: format!("{} cannot be removed", field),
+            })
+            .collect();
+        if !removed.is_empty() {
+            return Err(AppError::ValidationFailed(removed));
+        }
+
+        serde_json::from_value(self.document)
+            .map_err(|e| AppError::BadRequest(format!("Invalid merge patch: {}", e)))
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: Send + 'static> FromData<'r> for MergePatch<T> {
+    type Error = <Json<Value> as FromData<'r>>::Error;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        Json::<Value>::from_data(req, data).await.map(|document| MergePatch {
+            document: document.into_inner(),
+            _target: PhantomData,
+        })
+    }
+}
+
+impl<'r, T: JsonSchema + Send + 'static> OpenApiFromData<'r> for MergePatch<T> {
+    fn request_body(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<RequestBody> {
+        Ok(RequestBody {
+            content: rocket_okapi::okapi::map! {
+                MERGE_PATCH_MEDIA_TYPE.to_string() => MediaType {
+                    schema: Some(gen.json_schema::<T>()),
+                    ..Default::default()
+                }
+            },
+            required: true,
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,7 @@
+pub mod validation;
+pub mod merge_patch;
+pub mod etag;
+pub mod secret;
+pub mod authentication;
+pub mod timestamp;
+pub mod retry;
\ No newline at end of file
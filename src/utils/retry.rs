@@ -0,0 +1,218 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use log::{info, warn};
+use rand::Rng;
+use tokio::time::{sleep, timeout, Instant};
+
+use crate::config::settings::SettingsSource;
+
+/// How to retry an operation that may fail while a dependency comes up.
+///
+/// Attempt `n` (from 1) waits `initial_delay * multiplier^(n-1)` before the
+/// next one, capped at `max_delay` and jittered down by up to half so that
+/// replicas started together do not retry in lockstep. Retrying stops after
+/// `max_attempts`, or once `deadline` has passed since the first attempt; an
+/// attempt still running at the deadline is abandoned.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    pub deadline: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            multiplier: 2.0,
+            deadline: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Reads `<prefix>_MAX_ATTEMPTS`, `<prefix>_INITIAL_DELAY_MS`,
+    /// `<prefix>_MAX_DELAY_MS`, `<prefix>_MULTIPLIER` and `<prefix>_DEADLINE_SECS`.
+    pub fn from_settings(settings: &SettingsSource, prefix: &str) -> Result<Self, String> {
+        let defaults = RetryPolicy::default();
+        let millis = |name: &str, default: Duration| {
+            Duration::from_millis(settings.parse(&format!("{}_{}", prefix, name), default.as_millis() as u64))
+        };
+        let policy = RetryPolicy {
+            max_attempts: settings.parse(&format!("{}_MAX_ATTEMPTS", prefix), defaults.max_attempts),
+            initial_delay: millis("INITIAL_DELAY_MS", defaults.initial_delay),
+            max_delay: millis("MAX_DELAY_MS", defaults.max_delay),
+            multiplier: settings.parse(&format!("{}_MULTIPLIER", prefix), defaults.multiplier),
+            deadline: Duration::from_secs(settings.parse(&format!("{}_DEADLINE_SECS", prefix), defaults.deadline.as_secs())),
+        };
+
+        if policy.max_attempts == 0 {
+            return Err(format!("{}_MAX_ATTEMPTS must be at least 1", prefix));
+        }
+        if policy.initial_delay > policy.max_delay {
+            return Err(format!("{}_INITIAL_DELAY_MS must not exceed {}_MAX_DELAY_MS", prefix, prefix));
+        }
+        if !(policy.multiplier >= 1.0 && policy.multiplier.is_finite()) {
+            return Err(format!("{}_MULTIPLIER must be a number of at least 1", prefix));
+        }
+        if policy.deadline.is_zero() {
+            return Err(format!("{}_DEADLINE_SECS must be at least 1", prefix));
+        }
+        Ok(policy)
+    }
+
+    /// The jittered wait after failed attempt `attempt`, counting from 1.
+    fn delay_after(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        let capped = backoff.min(self.max_delay.as_secs_f64());
+        Duration::from_secs_f64(capped * rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Runs `operation` until it succeeds or `policy` gives up, logging each
+/// failure. `what` names the operation in logs and in the final error.
+pub async fn retry<T, E, F, Fut>(policy: &RetryPolicy, what: &str, mut operation: F) -> Result<T, String>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let deadline = Instant::now() + policy.deadline;
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        info!("{} (attempt {} of {})", what, attempt, policy.max_attempts);
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let error = match timeout(remaining, operation()).await {
+            Ok(Ok(value)) => return Ok(value),
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => {
+                return Err(format!("{} timed out at the {}s deadline, on attempt {}", what, policy.deadline.as_secs(), attempt));
+            }
+        };
+
+        if attempt >= policy.max_attempts {
+            return Err(format!("{} failed after {} attempt(s): {}", what, attempt, error));
+        }
+        let delay = policy.delay_after(attempt);
+        if Instant::now() + delay >= deadline {
+            return Err(format!("{} failed, with no time left to retry before the {}s deadline: {}", what, policy.deadline.as_secs(), error));
+        }
+
+        warn!("{} failed: {}. Retrying in {} ms", what, error, delay.as_millis());
+        sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_attempts: u32, deadline: Duration) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            deadline,
+        }
+    }
+
+    #[test]
+    fn delays_grow_exponentially_up_to_the_cap_with_jitter() {
+        let policy = policy(10, Duration::from_secs(60));
+        for (attempt, full) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (40, 1000)] {
+            let full = Duration::from_millis(full);
+            for _ in 0..100 {
+                let delay = policy.delay_after(attempt);
+                assert!(delay >= full / 2 && delay <= full, "attempt {} waited {:?}", attempt, delay);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_and_validates_settings() {
+        let defaults = RetryPolicy::from_settings(&SettingsSource::for_tests(&[]), "DB_RETRY").unwrap();
+        assert_eq!(defaults.max_attempts, 5);
+        assert_eq!(defaults.deadline, Duration::from_secs(60));
+
+        let custom = RetryPolicy::from_settings(&SettingsSource::for_tests(&[
+            ("DB_RETRY_MAX_ATTEMPTS", "3"),
+            ("DB_RETRY_INITIAL_DELAY_MS", "50"),
+            ("DB_RETRY_MULTIPLIER", "1.5"),
+        ]), "DB_RETRY").unwrap();
+        assert_eq!(custom.max_attempts, 3);
+        assert_eq!(custom.initial_delay, Duration::from_millis(50));
+        assert_eq!(custom.multiplier, 1.5);
+
+        for (key, value) in [
+            ("DB_RETRY_MAX_ATTEMPTS", "0"),
+            ("DB_RETRY_INITIAL_DELAY_MS", "20000"),
+            ("DB_RETRY_MULTIPLIER", "0.5"),
+            ("DB_RETRY_MULTIPLIER", "inf"),
+            ("DB_RETRY_DEADLINE_SECS", "0"),
+        ] {
+            let error = RetryPolicy::from_settings(&SettingsSource::for_tests(&[(key, value)]), "DB_RETRY").unwrap_err();
+            assert!(error.starts_with(key), "{}={} gave {}", key, value, error);
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        tokio::time::pause();
+        let mut attempts = 0;
+        let result = retry(&policy(5, Duration::from_secs(60)), "connecting", || {
+            attempts += 1;
+            let attempt = attempts;
+            async move { if attempt < 3 { Err("refused") } else { Ok(attempt) } }
+        }).await;
+        assert_eq!(result, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn stops_after_max_attempts() {
+        tokio::time::pause();
+        let mut attempts = 0;
+        let result: Result<(), _> = retry(&policy(3, Duration::from_secs(60)), "connecting", || {
+            attempts += 1;
+            async { Err("refused") }
+        }).await;
+        assert_eq!(attempts, 3);
+        assert_eq!(result.unwrap_err(), "connecting failed after 3 attempt(s): refused");
+    }
+
+    #[tokio::test]
+    async fn abandons_an_attempt_running_at_the_deadline() {
+        tokio::time::pause();
+        let started = Instant::now();
+        let result: Result<(), String> = retry(&policy(5, Duration::from_secs(2)), "connecting", || async {
+            sleep(Duration::from_secs(10)).await;
+            Ok::<(), &str>(())
+        }).await;
+        assert_eq!(result.unwrap_err(), "connecting timed out at the 2s deadline, on attempt 1");
+        assert_eq!(started.elapsed().as_secs(), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_wait_past_the_deadline() {
+        tokio::time::pause();
+        let started = Instant::now();
+        let mut attempts = 0;
+        let result: Result<(), _> = retry(&policy(100, Duration::from_secs(2)), "connecting", || {
+            attempts += 1;
+            async { Err("refused") }
+        }).await;
+        assert!(result.unwrap_err().starts_with("connecting failed, with no time left to retry before the 2s deadline"));
+        assert!(attempts > 1 && attempts < 100, "made {} attempts", attempts);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
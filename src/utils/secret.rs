@@ -0,0 +1,52 @@
+use std::fmt;
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{Schema, SchemaObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer};
+
+/// A sensitive string (password, token, ...) read from a request.
+///
+/// It can be deserialized but never serialized, and its `Debug` output is
+/// redacted so the `info!("{:?}")` calls in handlers cannot leak it.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+impl JsonSchema for Secret {
+    fn schema_name() -> String {
+        "Secret".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let mut schema: SchemaObject = String::json_schema(gen).into();
+        schema.metadata().write_only = true;
+        schema.format = Some("password".to_string());
+        schema.into()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+}
@@ -0,0 +1,15 @@
+use std::time::SystemTime;
+
+use serde::Serializer;
+
+/// Serializes a timestamp as RFC 3339 (`2024-01-31T12:00:00Z`) for API responses.
+pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&humantime::format_rfc3339_seconds(*time))
+}
+
+pub fn serialize_option<S: Serializer>(time: &Option<SystemTime>, serializer: S) -> Result<S::Ok, S::Error> {
+    match time {
+        Some(time) => serialize(time, serializer),
+        None => serializer.serialize_none(),
+    }
+}
@@ -0,0 +1,78 @@
+use std::borrow::Cow;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+
+use crate::errors::app_error::AppError;
+
+/// A single rejected field in a 422 response body.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+/// Canonicalizes a payload (trimming, lower-casing, ...) before it is validated.
+pub trait Normalize {
+    fn normalize(self) -> Self;
+}
+
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Normalizes and validates a request payload, collecting every field error
+/// into an `AppError::ValidationFailed`.
+pub fn validated<T: Normalize + Validate>(payload: T) -> Result<T, AppError> {
+    let payload = payload.normalize();
+    payload.validate()?;
+    Ok(payload)
+}
+
+fn default_message(field: &str, code: &str) -> Cow<'static, str> {
+    match code {
+        "email" => Cow::Owned(format!("{} must be a valid email address", field)),
+        "required" => Cow::Owned(format!("{} is required", field)),
+        _ => Cow::Owned(format!("{} is invalid", field)),
+    }
+}
+
+fn collect_field_errors(prefix: Option<&str>, errors: &ValidationErrors, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = match prefix {
+            Some(prefix) => format!("{}.{}", prefix, field),
+            None => field.to_string(),
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                for error in field_errors {
+                    out.push(FieldError {
+                        field: path.clone(),
+                        code: error.code.to_string(),
+                        message: error.message.clone()
+                            .unwrap_or_else(|| default_message(&path, &error.code))
+                            .to_string(),
+                    });
+                }
+            }
+            ValidationErrorsKind::Struct(nested) => collect_field_errors(Some(&path), nested, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(Some(&format!("{}[{}]", path, index)), nested, out);
+                }
+            }
+        }
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut field_errors = Vec::new();
+        collect_field_errors(None, &errors, &mut field_errors);
+        field_errors.sort_by(|a, b| a.field.cmp(&b.field));
+        AppError::ValidationFailed(field_errors)
+    }
+}